@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// https://tools.ietf.org/html/rfc6265
+
+pub fn parse_cookies(header_value: &str) -> HashMap<String, String> {
+    // Cookie: name=value; name2=value2
+    // browsers put the cookie with the most specific path first, so the first of a name wins
+    let mut cookies = HashMap::new();
+
+    for pair in header_value.split(';') {
+        let mut split = pair.splitn(2, '=');
+        let name = split.next().unwrap_or("").trim();
+        let value = match split.next() {
+            Some(value) => value.trim(),
+            None => continue,
+        };
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+
+        if !name.is_empty() {
+            cookies.entry(name.to_string()).or_insert_with(|| value.to_string());
+        }
+    }
+
+    cookies
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+#[derive(Clone, Debug)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    max_age: Option<u64>,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl SetCookie {
+    pub fn new(name: String, value: String) -> SetCookie {
+        SetCookie { name, value, path: None, max_age: None, http_only: false, same_site: None }
+    }
+
+    pub fn path(mut self, path: String) -> SetCookie {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn max_age(mut self, seconds: u64) -> SetCookie {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    pub fn http_only(mut self) -> SetCookie {
+        self.http_only = true;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> SetCookie {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+impl fmt::Display for SetCookie {
+    // just the header value, without the `Set-Cookie: ` in front
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(ref path) = self.path {
+            write!(f, "; Path={}", path)?;
+        }
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age)?;
+        }
+
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict"),
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax"),
+            Some(SameSite::None) => write!(f, "; SameSite=None"),
+            None => Ok(()),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use pest::iterators::{Pairs, Pair};
 use pest::Parser;
 use std::str::FromStr;
+use crate::cookie::parse_cookies;
 
 #[derive(Parser)]
 #[grammar = "http_request.pest"]
@@ -12,15 +13,25 @@ pub struct HttpRequest {
     request_type: RequestType,
     resource_location: String,
     headers: HashMap<String, String>,
+    cookies: HashMap<String, String>,
 }
 
 impl HttpRequest {
+    pub fn request_type(&self) -> RequestType {
+        self.request_type
+    }
+
     pub fn resource_location(&self) -> &str {
         &self.resource_location
     }
 
     pub fn get_header_value(&self, name: &str) -> Option<&str> {
-        self.headers.get(name).map(String::as_str)
+        // header names are case insensitive, so we store them lowercase
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn get_cookie(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
     }
 }
 
@@ -35,17 +46,21 @@ impl FromStr for HttpRequest {
 
         let resource_location = pairs_iter.next().unwrap().as_str().to_string();
 
-        let headers = pairs_iter
+        let headers: HashMap<String, String> = pairs_iter
             .filter(|p| p.as_rule() != Rule::EOI)
             .map(|pair: Pair<Rule>| {
                 let mut iter = pair.into_inner();
-                let name = iter.next().unwrap().as_str().to_string();
+                let name = iter.next().unwrap().as_str().to_ascii_lowercase();
                 let value = iter.next().unwrap().as_str().to_string();
                 (name, value)
             })
             .collect();
 
-        Ok(HttpRequest { request_type, resource_location, headers })
+        let cookies = headers.get("cookie")
+            .map(|value| parse_cookies(value))
+            .unwrap_or_default();
+
+        Ok(HttpRequest { request_type, resource_location, headers, cookies })
     }
 }
 
@@ -60,7 +75,7 @@ pub enum ParseError {
     PestError(pest::error::Error<Rule>),
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum RequestType {
     Get,
     Head,
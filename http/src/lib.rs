@@ -3,6 +3,8 @@ extern crate pest_derive;
 
 mod http_request_parse;
 mod http_iterator;
+mod cookie;
 
 pub use crate::http_request_parse::{HttpRequest, RequestType, ParseError};
-pub use crate::http_iterator::HttpIterator;
\ No newline at end of file
+pub use crate::http_iterator::HttpIterator;
+pub use crate::cookie::{parse_cookies, SetCookie, SameSite};
\ No newline at end of file
@@ -0,0 +1,56 @@
+// the Cookie header browsers send us, and the Set-Cookie header we send them
+
+use std::collections::HashMap;
+
+use http::{parse_cookies, HttpRequest, SameSite, SetCookie};
+
+fn cookies(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn pairs() {
+    assert_eq!(parse_cookies("session=abc123; theme=dark"), cookies(&[("session", "abc123"), ("theme", "dark")]));
+    assert_eq!(parse_cookies("a=1;b=2"), cookies(&[("a", "1"), ("b", "2")]));
+    assert_eq!(parse_cookies("  a = 1 ;  b=2;"), cookies(&[("a", "1"), ("b", "2")]));
+    assert_eq!(parse_cookies(""), cookies(&[]));
+}
+
+#[test]
+fn values() {
+    assert_eq!(parse_cookies(r#"a="quoted value"; b="""#), cookies(&[("a", "quoted value"), ("b", "")]));
+    assert_eq!(parse_cookies(r#"a="unbalanced; b=x""#), cookies(&[("a", "\"unbalanced"), ("b", "x\"")]));
+    assert_eq!(parse_cookies("token=abc==; empty="), cookies(&[("token", "abc=="), ("empty", "")]));
+}
+
+#[test]
+fn first_of_a_name_wins() {
+    assert_eq!(parse_cookies("session=new; session=old"), cookies(&[("session", "new")]));
+}
+
+#[test]
+fn pairs_without_a_name_or_value_are_skipped() {
+    assert_eq!(parse_cookies("flag; a=1; =2; ;"), cookies(&[("a", "1")]));
+}
+
+#[test]
+fn from_a_request() {
+    let request: HttpRequest = "GET / HTTP/1.1\r\nHost: ethan.ws\r\nCookie: session=abc; session=def\r\n\r\n".parse().unwrap();
+    assert_eq!(request.get_cookie("session"), Some("abc"));
+    assert_eq!(request.get_cookie("theme"), None);
+}
+
+#[test]
+fn set_cookie() {
+    assert_eq!(SetCookie::new("a".into(), "1".into()).to_string(), "a=1");
+
+    let cookie = SetCookie::new("session".into(), "abc123".into())
+        .path("/".into())
+        .max_age(86400)
+        .http_only()
+        .same_site(SameSite::Lax);
+    assert_eq!(cookie.to_string(), "session=abc123; Path=/; Max-Age=86400; HttpOnly; SameSite=Lax");
+
+    assert_eq!(SetCookie::new("a".into(), "1".into()).same_site(SameSite::Strict).to_string(), "a=1; SameSite=Strict");
+    assert_eq!(SetCookie::new("a".into(), "1".into()).same_site(SameSite::None).to_string(), "a=1; SameSite=None");
+}
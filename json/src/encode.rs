@@ -1,15 +1,19 @@
-use std::collections::HashMap;
 use std::fmt;
-use std::str::{FromStr, from_utf8};
+use std::fmt::Write;
+use std::str::FromStr;
 
-#[derive(Clone, Debug)]
+use crate::map::Map;
+use crate::number::Number;
+use crate::parse::{parse, ParseError};
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum Json {
     Null,
     Boolean(bool),
-    Number(f64),
+    Number(Number),
     String(String),
     Array(Vec<Json>),
-    Object(HashMap<String, Json>),
+    Object(Map),
 }
 
 impl Json {
@@ -27,7 +31,20 @@ impl Json {
     }
     pub fn get_number(&self) -> Option<f64> {
         match *self {
-            Json::Number(n) => Some(n),
+            Json::Number(ref n) => n.as_f64(),
+            _ => None,
+        }
+    }
+    pub fn get_u64(&self) -> Option<u64> {
+        // for ids and times, which f64 can't hold exactly past 2^53
+        match *self {
+            Json::Number(ref n) => n.as_u64(),
+            _ => None,
+        }
+    }
+    pub fn get_i64(&self) -> Option<i64> {
+        match *self {
+            Json::Number(ref n) => n.as_i64(),
             _ => None,
         }
     }
@@ -43,7 +60,7 @@ impl Json {
             _ => None,
         }
     }
-    pub fn get_object(&self) -> Option<&HashMap<String, Json>> {
+    pub fn get_object(&self) -> Option<&Map> {
         match *self {
             Json::Object(ref o) => Some(o),
             _ => None,
@@ -51,13 +68,21 @@ impl Json {
     }
 }
 
+impl Json {
+    pub fn pretty(&self) -> Pretty<'_> {
+        // for logs and files people read, like `println!("{}", json.pretty().sort_keys(true))`.
+        // Display is the compact form we send over the wire
+        Pretty { json: self, indent: 2, sort_keys: false }
+    }
+}
+
 impl fmt::Display for Json {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Json::Null => write!(f, "null"),
             Json::Boolean(b) => write!(f, "{}", b),
-            Json::Number(n) => write!(f, "{}", n),
-            Json::String(ref s) => write!(f, "{}", into_json_string(s)),
+            Json::Number(ref n) => write!(f, "{}", n),
+            Json::String(ref s) => write!(f, "{}", Escaped(s)),
             Json::Array(ref a) => {
                 let maybe_comma = |i| if i < a.len()-1 { "," } else { "" };
                 write!(f, "[")?;
@@ -70,7 +95,7 @@ impl fmt::Display for Json {
                 let maybe_comma = |i| if i < m.len()-1 { "," } else { "" };
                 write!(f, "{{")?;
                 for (i, (k, v)) in m.iter().enumerate() {
-                    write!(f, "\"{}\":{}{}", k, v, maybe_comma(i))?;
+                    write!(f, "{}:{}{}", Escaped(k), v, maybe_comma(i))?;
                 }
                 write!(f, "}}")
             },
@@ -78,164 +103,101 @@ impl fmt::Display for Json {
     }
 }
 
-impl FromStr for Json {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Json, ()> {
-        let s = s.trim();
-
-        if let "null" = s {
-            Ok(Json::Null)
-        } else if let Ok(b) = s.parse::<bool>() {
-            Ok(Json::Boolean(b))
-        } else if let Ok(n) = s.parse::<f64>() {
-            Ok(Json::Number(n))
-        } else if let Ok(ret) = parse_json_string(s) {
-            Ok(Json::String(ret))
-        } else if s.starts_with('[') && s.ends_with(']') {
-
-            Ok(Json::Array(SplitTopLevel::new(&s[1..s.len()-1], b',')
-                .filter(|value| !value.chars().all(char::is_whitespace))
-                .map(|value| value.parse())
-                .collect::<Result<Vec<Json>, ()>>()?
-            ))
-
-        } else if s.starts_with('{') && s.ends_with('}') {
-            Ok(Json::Object(SplitTopLevel::new(&s[1..s.len()-1], b',')
-                .map(|keypair| {
-                    let mut a = SplitTopLevel::new(keypair, b':');
-                    let key = a.next().ok_or(())?;
-                    let value = a.next().ok_or(())?;
-                    Ok((parse_json_string(key.trim())?, value.parse()?))
-                })
-                .collect::<Result<HashMap<String, Json>, ()>>()?
-            ))
-        } else {
-            Err(())
-        }
-    }
+pub struct Pretty<'a> {
+    json: &'a Json,
+    indent: usize,
+    sort_keys: bool,
 }
 
-struct SplitTopLevel<'a> {
-    bytes: &'a [u8],
-    split_on: u8,
-}
+impl<'a> Pretty<'a> {
+    pub fn indent(mut self, spaces: usize) -> Pretty<'a> {
+        self.indent = spaces;
+        self
+    }
 
-impl<'a> SplitTopLevel<'a> {
-    fn new(s: &'a str, split_on: u8) -> SplitTopLevel<'a> {
-        SplitTopLevel {
-            bytes: s.as_bytes(),
-            split_on
-        }
+    pub fn sort_keys(mut self, sort_keys: bool) -> Pretty<'a> {
+        // instead of the order they were inserted in, so two objects built differently diff cleanly
+        self.sort_keys = sort_keys;
+        self
     }
-}
 
-impl<'a> Iterator for SplitTopLevel<'a> {
-    type Item = &'a str;
-
-    fn next(&mut self) -> Option<&'a str> {
-        if self.bytes.is_empty() { return None }
-        let mut bracket_count = 0;
-        let mut mustache_count = 0;
-        let mut quote_count_even = true;
-        let mut char_is_escaped = false;
-
-        for (i, &b) in self.bytes.iter().enumerate() {
-            if !char_is_escaped {
-                match b {
-                    b'[' if quote_count_even => bracket_count += 1,
-                    b']' if quote_count_even => bracket_count -= 1,
-                    b'{' if quote_count_even => mustache_count += 1,
-                    b'}' if quote_count_even => mustache_count -= 1,
-                    b'"' => quote_count_even = !quote_count_even,
-                    _ if b == self.split_on && quote_count_even && bracket_count == 0 && mustache_count == 0 => {
-                        let ret = from_utf8(&self.bytes[..i]).unwrap(); // we were passed in valid utf8
-                        self.bytes = &self.bytes[i+1..];
-                        return Some(ret)
-                    },
-                    _ => {},
+    fn write(&self, f: &mut fmt::Formatter, json: &Json, depth: usize) -> fmt::Result {
+        match *json {
+            Json::Array(ref a) if !a.is_empty() => {
+                writeln!(f, "[")?;
+                for (i, elem) in a.iter().enumerate() {
+                    self.write_indent(f, depth + 1)?;
+                    self.write(f, elem, depth + 1)?;
+                    writeln!(f, "{}", if i < a.len()-1 { "," } else { "" })?;
                 }
-            }
-
-            char_is_escaped = b == b'\\' && !char_is_escaped;
+                self.write_indent(f, depth)?;
+                write!(f, "]")
+            },
+            Json::Object(ref m) if !m.is_empty() => {
+                let mut entries: Vec<(&String, &Json)> = m.iter().collect();
+                if self.sort_keys { entries.sort_by_key(|&(k, _)| k) }
+
+                writeln!(f, "{{")?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    self.write_indent(f, depth + 1)?;
+                    write!(f, "{}: ", Escaped(k))?;
+                    self.write(f, v, depth + 1)?;
+                    writeln!(f, "{}", if i < entries.len()-1 { "," } else { "" })?;
+                }
+                self.write_indent(f, depth)?;
+                write!(f, "}}")
+            },
+            // everything else looks the same as it does compact
+            _ => write!(f, "{}", json),
         }
+    }
 
-        if bracket_count == 0 && mustache_count == 0 && quote_count_even {
-            let ret = from_utf8(self.bytes).unwrap(); // we were passed in valid utf8
-            self.bytes = &[];
-            Some(ret)
-        } else {
-            self.bytes = &[];
-            None
-        }
+    fn write_indent(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(f, "{:1$}", "", self.indent * depth)
     }
 }
 
+impl fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, self.json, 0)
+    }
+}
 
+impl FromStr for Json {
+    type Err = ParseError;
 
-fn into_json_string(s: &str) -> String {
-    let mut ret = String::with_capacity(s.len()); // might be longer but why not
-
-    for c in s.chars() {
-        match c {
-            '"' => ret.push_str("\\\""),
-            '\\' => ret.push_str("\\\\"),
-            '\x08' => ret.push_str("\\b"),
-            '\x0c' => ret.push_str("\\f"),
-            '\n' => ret.push_str("\\n"),
-            '\r' => ret.push_str("\\r"),
-            '\t' => ret.push_str("\\t"),
-            _ if c.is_ascii() && !c.is_ascii_control() => ret.push(c),
-            _ => ret.push_str({
-                let mut buf = [0u16; 2];
-                &match *c.encode_utf16(&mut buf) {
-                    [a] => format!(r#"\u{:04X?}"#, a),
-                    [a, b] => format!(r#"\u{:04X?}\u{:04X?}"#, a, b),
-                    _ => unreachable!(),
-                }
-            }),
-            // we don't encode forward slashes
-        }
+    fn from_str(s: &str) -> Result<Json, ParseError> {
+        parse(s)
     }
-
-    format!(r#""{}""#, ret)
 }
 
-fn parse_json_string(s: &str) -> Result<String, ()> {
-    if !(s.len() > 1 && s.starts_with('"') && s.ends_with('"')) {
-        return Err(());
-    }
-
-    let mut ret = Vec::new();
-    let mut chars = s[1..s.len()-1].chars();
-
-    loop {
-        let c = match chars.next() {
-            Some(c) => c,
-            None => break String::from_utf16(&ret).map_err(|_| ()),
-        };
-
-        match c {
-            '"' => return Err(()),
-            '\\' => match chars.next().ok_or(())? {
-                '"' => ret.push(b'"' as u16),
-                '\\' => ret.push(b'\\' as u16),
-                '/' => ret.push(b'/' as u16),
-                'b' => ret.push(b'\x08' as u16),
-                'f' => ret.push(b'\x0c' as u16),
-                'n' => ret.push(b'\n' as u16),
-                'r' => ret.push(b'\r' as u16),
-                't' => ret.push(b'\t' as u16),
-                'u' => {
-                    let mut z = String::new();
-                    for _ in 0..4 {
-                        z.push(chars.next().ok_or(())?);
+// a string written as a json string, quotes and all, without building it first
+pub(crate) struct Escaped<'a>(pub &'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\"")?;
+
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\x08' => f.write_str("\\b")?,
+                '\x0c' => f.write_str("\\f")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                _ if c.is_ascii() && !c.is_ascii_control() => f.write_char(c)?,
+                _ => {
+                    let mut buf = [0u16; 2];
+                    for unit in c.encode_utf16(&mut buf) {
+                        write!(f, r#"\u{:04X?}"#, unit)?;
                     }
-                    ret.push(u16::from_str_radix(&z, 16).map_err(|_| ())?);
                 },
-                _ => return Err(()),
-            },
-            _ => ret.push(c as u16),
+                // we don't encode forward slashes
+            }
         }
+
+        f.write_str("\"")
     }
 }
@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Json, Map, Number};
+
+// the other direction from Jsonable. `#[derive(FromJson)]` in json_derive writes this for structs
+// and enums
+pub trait FromJson: Sized {
+    fn from_json(json: &Json) -> Result<Self, FromJsonError>;
+}
+
+// what was wrong, and where. the path is a json pointer like `/players/2/x`, and empty when the
+// problem was the value we started with
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FromJsonError {
+    pub path: String,
+    pub reason: String,
+}
+
+impl FromJsonError {
+    pub fn new(reason: &str) -> FromJsonError {
+        FromJsonError { path: String::new(), reason: reason.to_string() }
+    }
+
+    pub fn expected(what: &str) -> FromJsonError {
+        FromJsonError::new(&format!("expected {}", what))
+    }
+
+    pub fn at_key(mut self, key: &str) -> FromJsonError {
+        // for when the error came from inside a value in an object
+        self.path = format!("/{}{}", key.replace('~', "~0").replace('/', "~1"), self.path);
+        self
+    }
+
+    pub fn at_index(mut self, index: usize) -> FromJsonError {
+        self.path = format!("/{}{}", index, self.path);
+        self
+    }
+
+    pub fn at_pointer(mut self, pointer: &str) -> FromJsonError {
+        // for when the error came from the value at `pointer`, which is already escaped
+        self.path = format!("{}{}", pointer, self.path);
+        self
+    }
+}
+
+impl fmt::Display for FromJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.reason)
+        } else {
+            write!(f, "{} at {}", self.reason, self.path)
+        }
+    }
+}
+
+impl Error for FromJsonError {}
+
+impl FromJson for Json {
+    fn from_json(json: &Json) -> Result<Json, FromJsonError> { Ok(json.clone()) }
+}
+
+impl FromJson for bool {
+    fn from_json(json: &Json) -> Result<bool, FromJsonError> {
+        json.get_bool().ok_or_else(|| FromJsonError::expected("a boolean"))
+    }
+}
+
+impl FromJson for String {
+    fn from_json(json: &Json) -> Result<String, FromJsonError> {
+        json.get_string().map(String::from).ok_or_else(|| FromJsonError::expected("a string"))
+    }
+}
+
+impl FromJson for Number {
+    fn from_json(json: &Json) -> Result<Number, FromJsonError> {
+        match *json {
+            Json::Number(ref n) => Ok(n.clone()),
+            _ => Err(FromJsonError::expected("a number")),
+        }
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(json: &Json) -> Result<f64, FromJsonError> {
+        Number::from_json(json)?.as_f64().ok_or_else(|| FromJsonError::new("number is too big for f64"))
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(json: &Json) -> Result<f32, FromJsonError> {
+        f64::from_json(json).map(|n| n as f32)
+    }
+}
+
+macro_rules! whole_number {
+    ($($t:ty),*) => {
+        $(
+        impl FromJson for $t {
+            fn from_json(json: &Json) -> Result<$t, FromJsonError> {
+                let fits = |n: &Number| n.as_i64().and_then(|n| <$t>::try_from(n).ok())
+                    .or_else(|| n.as_u64().and_then(|n| <$t>::try_from(n).ok()));
+
+                match *json {
+                    Json::Number(ref n) => fits(n),
+                    _ => None,
+                }.ok_or_else(|| FromJsonError::expected(concat!("a whole number that fits in ", stringify!($t))))
+            }
+        }
+        )*
+    };
+}
+
+whole_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(json: &Json) -> Result<Option<T>, FromJsonError> {
+        match *json {
+            Json::Null => Ok(None),
+            _ => T::from_json(json).map(Some),
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(json: &Json) -> Result<Box<T>, FromJsonError> {
+        T::from_json(json).map(Box::new)
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(json: &Json) -> Result<Vec<T>, FromJsonError> {
+        json.get_array().ok_or_else(|| FromJsonError::expected("an array"))?
+            .iter()
+            .enumerate()
+            .map(|(i, elem)| T::from_json(elem).map_err(|e| e.at_index(i)))
+            .collect()
+    }
+}
+
+impl FromJson for Map {
+    fn from_json(json: &Json) -> Result<Map, FromJsonError> {
+        Ok(object(json)?.clone())
+    }
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(json: &Json) -> Result<HashMap<String, T>, FromJsonError> {
+        object(json)?.iter().map(|(key, value)| entry(key, value)).collect()
+    }
+}
+
+impl<T: FromJson> FromJson for BTreeMap<String, T> {
+    fn from_json(json: &Json) -> Result<BTreeMap<String, T>, FromJsonError> {
+        object(json)?.iter().map(|(key, value)| entry(key, value)).collect()
+    }
+}
+
+fn entry<T: FromJson>(key: &str, value: &Json) -> Result<(String, T), FromJsonError> {
+    T::from_json(value).map(|value| (key.to_string(), value)).map_err(|e| e.at_key(key))
+}
+
+// the rest of this module is used by the code json_derive writes
+
+#[doc(hidden)]
+pub fn object(json: &Json) -> Result<&Map, FromJsonError> {
+    json.get_object().ok_or_else(|| FromJsonError::expected("an object"))
+}
+
+#[doc(hidden)]
+pub fn field<T: FromJson>(object: &Map, key: &str) -> Result<T, FromJsonError> {
+    match object.get(key) {
+        Some(value) => T::from_json(value).map_err(|e| e.at_key(key)),
+        None => Err(FromJsonError::new(&format!("missing field `{}`", key))),
+    }
+}
+
+#[doc(hidden)]
+pub fn optional_field<T: FromJson>(object: &Map, key: &str) -> Result<Option<T>, FromJsonError> {
+    // missing and null are both None
+    match object.get(key) {
+        Some(value) => Option::<T>::from_json(value).map_err(|e| e.at_key(key)),
+        None => Ok(None),
+    }
+}
+
+#[doc(hidden)]
+pub fn default_field<T: FromJson+Default>(object: &Map, key: &str) -> Result<T, FromJsonError> {
+    match object.get(key) {
+        Some(value) => T::from_json(value).map_err(|e| e.at_key(key)),
+        None => Ok(T::default()),
+    }
+}
+
+#[doc(hidden)]
+pub fn tag<'a>(object: &'a Map, key: &str) -> Result<&'a str, FromJsonError> {
+    match object.get(key) {
+        Some(value) => value.get_string().ok_or_else(|| FromJsonError::expected("a string").at_key(key)),
+        None => Err(FromJsonError::new(&format!("missing field `{}`", key))),
+    }
+}
+
+#[doc(hidden)]
+pub fn unknown_variant(value: &str, key: Option<&str>) -> FromJsonError {
+    let error = FromJsonError::new(&format!("unknown variant `{}`", value));
+    match key {
+        Some(key) => error.at_key(key),
+        None => error,
+    }
+}
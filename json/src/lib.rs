@@ -1,13 +1,41 @@
 mod encode;
-pub use encode::Json;
+mod map;
+mod number;
+mod parse;
+mod from_json;
+mod pointer;
+mod reader;
+mod schema;
+mod writer;
+pub use encode::{Json, Pretty};
+pub use map::Map;
+pub use number::Number;
+pub use parse::ParseError;
+pub use from_json::{FromJson, FromJsonError};
+pub use writer::JsonWriter;
+pub use reader::{JsonReader, Event, ReadError};
+pub use schema::{Schema, MessageSchemas};
+
+use std::collections::{BTreeMap, HashMap};
+
+// used by the code json_derive writes
+#[doc(hidden)]
+pub mod derive_support {
+    pub use crate::from_json::{object, field, optional_field, default_field, tag, unknown_variant};
+}
 
 #[macro_export]
 macro_rules! jsons {
     ($e:tt) => { json!($e).to_string() }
 }
 
+// keys can be a bare name, a string literal like "game-id", or any expression in parentheses
 #[macro_export]
 macro_rules! json {
+    (@key $name:ident) => { stringify!($name).to_string() };
+    (@key $name:literal) => { $name.to_string() };
+    (@key ($key:expr)) => { $key.to_string() };
+
     (null) => { json::Json::Null };
 
     ([$($e:tt),*]) => {
@@ -20,17 +48,17 @@ macro_rules! json {
 
     ([$($e:tt,)*]) => { json!([$($e),*]) };
 
-    ({$($name:ident: $e:tt),*}) => {{
-        let mut map = std::collections::HashMap::new();
+    ({$($key:tt: $e:tt),*}) => {{
+        let mut map = json::Map::new();
 
         $(
-        map.insert(stringify!($name).into(), json::json!($e));
+        map.insert(json::json!(@key $key), json::json!($e));
         )*
 
         json::Json::Object(map)
     }};
 
-    ({$($name:ident: $e:tt,)*}) => { json::json!({$($name: $e),*}) };
+    ({$($key:tt: $e:tt,)*}) => { json::json!({$($key: $e),*}) };
 
     ($e:expr) => { json::Jsonable::into_json($e) };
 }
@@ -43,6 +71,14 @@ impl Jsonable for Json {
     fn into_json(self) -> Json { self }
 }
 
+impl Jsonable for Map {
+    fn into_json(self) -> Json { Json::Object(self) }
+}
+
+impl Jsonable for Number {
+    fn into_json(self) -> Json { Json::Number(self) }
+}
+
 impl Jsonable for bool {
     fn into_json(self) -> Json { Json::Boolean(self) }
 }
@@ -55,18 +91,85 @@ impl Jsonable for String {
     fn into_json(self) -> Json { Json::String(self) }
 }
 
-impl Jsonable for f64 {
-    fn into_json(self) -> Json { Json::Number(self) }
+macro_rules! number {
+    ($($t:ty),*) => {
+        $(
+        impl Jsonable for $t {
+            fn into_json(self) -> Json { Json::Number(Number::from(self)) }
+        }
+        )*
+    };
 }
 
-impl Jsonable for i32 {
-    fn into_json(self) -> Json { Json::Number(self as f64) }
+number!(f32, f64, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: Jsonable> Jsonable for Option<T> {
+    fn into_json(self) -> Json {
+        match self {
+            Some(value) => value.into_json(),
+            None => Json::Null,
+        }
+    }
 }
 
-impl Jsonable for u8 {
-    fn into_json(self) -> Json { Json::Number(self as f64) }
+impl<T: Jsonable> Jsonable for Vec<T> {
+    fn into_json(self) -> Json {
+        Json::Array(self.into_iter().map(Jsonable::into_json).collect())
+    }
+}
+
+impl<T: Jsonable+Clone> Jsonable for &[T] {
+    fn into_json(self) -> Json {
+        Json::Array(self.iter().cloned().map(Jsonable::into_json).collect())
+    }
+}
+
+macro_rules! array {
+    ($($len:expr),*) => {
+        $(
+        impl<T: Jsonable> Jsonable for [T; $len] {
+            fn into_json(self) -> Json { Vec::from(self).into_json() }
+        }
+        )*
+    };
+}
+
+// one by one, since the compilers we support don't have const generics
+array!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32);
+
+impl<T: Jsonable+Clone> Jsonable for &T {
+    fn into_json(self) -> Json { self.clone().into_json() }
+}
+
+impl<T: Jsonable> Jsonable for HashMap<String, T> {
+    fn into_json(self) -> Json {
+        // in whatever order the HashMap has them
+        Json::Object(self.into_iter().map(|(key, value)| (key, value.into_json())).collect())
+    }
+}
+
+impl<T: Jsonable> Jsonable for BTreeMap<String, T> {
+    fn into_json(self) -> Json {
+        Json::Object(self.into_iter().map(|(key, value)| (key, value.into_json())).collect())
+    }
+}
+
+macro_rules! tuple {
+    ($($name:ident),*) => {
+        // tuples are arrays, like [x, y]
+        impl<$($name: Jsonable),*> Jsonable for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn into_json(self) -> Json {
+                let ($($name,)*) = self;
+                Json::Array(vec![$($name.into_json()),*])
+            }
+        }
+    };
 }
 
-impl Jsonable for usize {
-    fn into_json(self) -> Json { Json::Number(self as f64) }
-}
\ No newline at end of file
+tuple!(A);
+tuple!(A, B);
+tuple!(A, B, C);
+tuple!(A, B, C, D);
+tuple!(A, B, C, D, E);
+tuple!(A, B, C, D, E, F);
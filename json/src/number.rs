@@ -0,0 +1,195 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+// a json number, kept exactly. whole numbers that fit in 64 bits are integers, and anything else we
+// parse keeps the text it was written as, so ids past 2^53 and decimals like 1.50 are written back
+// out the same way. numbers are equal when they're the same value, however they're written
+#[derive(Clone)]
+pub struct Number(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Unsigned(u64),
+    Negative(i64), // always below zero
+    Float(f64),
+    Literal(String), // checked against the grammar already
+}
+
+impl Number {
+    pub(crate) fn from_literal(text: &str) -> Number {
+        // text that matches the json number grammar
+        let integer = if text.contains(|c| matches!(c, '.' | 'e' | 'E')) {
+            None
+        } else if text.starts_with('-') {
+            // not -0, which would come back out as 0
+            text.parse().ok().filter(|&n: &i64| n < 0).map(Repr::Negative)
+        } else {
+            text.parse().ok().map(Repr::Unsigned)
+        };
+
+        Number(integer.unwrap_or_else(|| Repr::Literal(text.to_string())))
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        // only when it's a whole number that fits
+        match self.0 {
+            Repr::Unsigned(n) => Some(n),
+            Repr::Negative(_) => None,
+            Repr::Float(f) if f.fract() == 0.0 && (0.0..18_446_744_073_709_551_616.0).contains(&f) => Some(f as u64),
+            Repr::Float(_) => None,
+            Repr::Literal(_) => self.whole_number_text()?.parse().ok(),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.0 {
+            Repr::Unsigned(n) => i64::try_from(n).ok(),
+            Repr::Negative(n) => Some(n),
+            Repr::Float(f) if f.fract() == 0.0 && (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&f) => Some(f as i64),
+            Repr::Float(_) => None,
+            Repr::Literal(_) => self.whole_number_text()?.parse().ok(),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        // the closest f64, which is only None when it's too big for one
+        let f = match self.0 {
+            Repr::Unsigned(n) => n as f64,
+            Repr::Negative(n) => n as f64,
+            Repr::Float(f) => f,
+            Repr::Literal(ref text) => text.parse().ok()?,
+        };
+
+        if f.is_finite() { Some(f) } else { None }
+    }
+
+    fn decimal(&self) -> Option<Decimal> {
+        match self.0 {
+            Repr::Unsigned(n) => Decimal::from_literal(&n.to_string()),
+            Repr::Negative(n) => Decimal::from_literal(&n.to_string()),
+            // the shortest digits that turn back into the same f64, which is what Display writes
+            Repr::Float(f) if f.is_finite() => Decimal::from_literal(&format!("{:e}", f)),
+            Repr::Float(_) => None,
+            Repr::Literal(ref text) => Decimal::from_literal(text),
+        }
+    }
+
+    fn whole_number_text(&self) -> Option<String> {
+        // like "-1200" for "-1.2e3", or None if it has a fraction or is absurdly long
+        let Decimal { negative, digits, exponent } = self.decimal()?;
+        if digits.is_empty() { return Some("0".to_string()) }
+
+        let zeros = usize::try_from(exponent).ok()?.checked_sub(digits.len())?;
+        if zeros > 20 { return None }
+
+        Some(format!("{}{}{}", if negative { "-" } else { "" }, digits, "0".repeat(zeros)))
+    }
+}
+
+// value = 0.digits * 10^exponent, with no leading or trailing zeros in digits. zero has no digits
+#[derive(PartialEq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    fn from_literal(text: &str) -> Option<Decimal> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+
+        let (mantissa, exponent) = match text.find(|c| c == 'e' || c == 'E') {
+            Some(i) => (&text[..i], text[i+1..].trim_start_matches('+').parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+
+        let (whole, fraction) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], &mantissa[i+1..]),
+            None => (mantissa, ""),
+        };
+
+        let all_digits = format!("{}{}", whole, fraction);
+        let digits = all_digits.trim_start_matches('0');
+        let leading_zeros = (all_digits.len() - digits.len()) as i64;
+        let digits = digits.trim_end_matches('0');
+
+        if digits.is_empty() {
+            return Some(Decimal { negative: false, digits: String::new(), exponent: 0 });
+        }
+
+        let exponent = exponent.checked_add(whole.len() as i64 - leading_zeros)?;
+        Some(Decimal { negative, digits: digits.to_string(), exponent })
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        // most comparisons are between numbers made the same way, which don't need the digits
+        match (&self.0, &other.0) {
+            (Repr::Unsigned(a), Repr::Unsigned(b)) => return a == b,
+            (Repr::Negative(a), Repr::Negative(b)) => return a == b,
+            (Repr::Unsigned(_), Repr::Negative(_)) | (Repr::Negative(_), Repr::Unsigned(_)) => return false,
+            // NaN and infinity are written as null, which isn't a number we can compare
+            (Repr::Float(a), Repr::Float(b)) => return a.is_finite() && a == b,
+            _ => {},
+        }
+
+        match (self.decimal(), other.decimal()) {
+            (Some(a), Some(b)) => a == b,
+            // exponents too big to work with can still be written the same way
+            (None, None) => match (&self.0, &other.0) {
+                (Repr::Literal(a), Repr::Literal(b)) => a == b,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Repr::Unsigned(n) => write!(f, "{}", n),
+            Repr::Negative(n) => write!(f, "{}", n),
+            Repr::Float(n) if n.is_finite() => write!(f, "{}", n),
+            Repr::Float(_) => f.write_str("null"), // json has no way to write NaN or infinity
+            Repr::Literal(ref text) => f.write_str(text),
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Number { Number(Repr::Float(n)) }
+}
+
+impl From<f32> for Number {
+    fn from(n: f32) -> Number { Number(Repr::Float(n as f64)) }
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+        impl From<$t> for Number {
+            fn from(n: $t) -> Number {
+                // anything too big for 64 bits is kept as its digits
+                match (u64::try_from(n), i64::try_from(n)) {
+                    (Ok(n), _) => Number(Repr::Unsigned(n)),
+                    (_, Ok(n)) => Number(Repr::Negative(n)),
+                    _ => Number(Repr::Literal(n.to_string())),
+                }
+            }
+        }
+        )*
+    };
+}
+
+integer!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
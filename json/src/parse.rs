@@ -0,0 +1,329 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::Json;
+use crate::map::Map;
+use crate::number::Number;
+
+// https://tools.ietf.org/html/rfc8259
+
+// deeper than any message we expect, and shallow enough that recursing this far can't overflow the stack
+pub(crate) const MAX_DEPTH: usize = 128;
+
+// where the input stopped being json, and why. lines and columns start at 1, and columns count chars
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.reason, self.line, self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+pub(crate) fn parse(text: &str) -> Result<Json, ParseError> {
+    let mut parser = Parser { text, pos: 0, depth: 0 };
+
+    parser.skip_whitespace();
+    let json = parser.value()?;
+    parser.skip_whitespace();
+
+    if parser.pos < text.len() {
+        return Err(parser.error("unexpected characters after the value"));
+    }
+
+    Ok(json)
+}
+
+// looks at every byte once, and only works out the line and column when there's an error
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn value(&mut self) -> Result<Json, ParseError> {
+        match self.peek() {
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Boolean(true)),
+            Some(b'f') => self.literal("false", Json::Boolean(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, json: Json) -> Result<Json, ParseError> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error("expected a value"));
+        }
+
+        self.pos += word.len();
+        Ok(json)
+    }
+
+    fn array(&mut self) -> Result<Json, ParseError> {
+        self.enter()?;
+        self.pos += 1;
+
+        let mut array = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(Json::Array(array));
+        }
+
+        loop {
+            self.skip_whitespace();
+            array.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(b',') => continue,
+                Some(b']') => break,
+                Some(_) => return Err(self.error_before("expected `,` or `]`")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+        }
+
+        self.depth -= 1;
+        Ok(Json::Array(array))
+    }
+
+    fn object(&mut self) -> Result<Json, ParseError> {
+        self.enter()?;
+        self.pos += 1;
+
+        let mut object = Map::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(Json::Object(object));
+        }
+
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'"') => {},
+                Some(_) => return Err(self.error("expected a string key")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+            let key = self.string()?;
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(b':') => {},
+                Some(_) => return Err(self.error_before("expected `:`")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+
+            self.skip_whitespace();
+            let value = self.value()?;
+            // the rfc leaves duplicate keys up to us, and the last one wins
+            object.insert(key, value);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b'}') => break,
+                Some(_) => return Err(self.error_before("expected `,` or `}`")),
+                None => return Err(self.error("unexpected end of input")),
+            }
+        }
+
+        self.depth -= 1;
+        Ok(Json::Object(object))
+    }
+
+    fn number(&mut self) -> Result<Json, ParseError> {
+        // -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+        let start = self.pos;
+
+        if self.peek() == Some(b'-') { self.pos += 1 }
+
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                if let Some(b'0'..=b'9') = self.peek() {
+                    return Err(self.error("numbers can't have leading zeros"));
+                }
+            },
+            Some(b'1'..=b'9') => self.digits()?,
+            _ => return Err(self.error("expected a digit")),
+        }
+
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.digits()?;
+        }
+
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() { self.pos += 1 }
+            self.digits()?;
+        }
+
+        Ok(Json::Number(Number::from_literal(&self.text[start..self.pos])))
+    }
+
+    fn digits(&mut self) -> Result<(), ParseError> {
+        // at least one
+        match self.peek() {
+            Some(b'0'..=b'9') => {},
+            _ => return Err(self.error("expected a digit")),
+        }
+
+        while let Some(b'0'..=b'9') = self.peek() { self.pos += 1 }
+
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.pos += 1; // the opening quote
+
+        let mut string = String::new();
+
+        loop {
+            // copy everything up to the next quote, escape or control character in one go. those
+            // are all ascii, so this never splits a char
+            let start = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 { break }
+                self.pos += 1;
+            }
+            string.push_str(&self.text[start..self.pos]);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(string);
+                },
+                Some(b'\\') => {
+                    self.pos += 1;
+                    string.push(self.escape()?);
+                },
+                Some(_) => return Err(self.error("control characters in strings must be escaped")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.next() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0c',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => return self.unicode_escape(),
+            Some(_) => return Err(self.error_before("invalid escape")),
+            None => return Err(self.error("unterminated string")),
+        };
+
+        Ok(c)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        // anything outside the basic multilingual plane is written as a utf16 surrogate pair
+        let start = self.pos - 2;
+        let first = self.hex4()?;
+
+        let code_point = match first {
+            0xd800..=0xdbff => {
+                if !self.text[self.pos..].starts_with("\\u") {
+                    self.pos = start;
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.pos += 2;
+
+                match self.hex4()? {
+                    second @ 0xdc00..=0xdfff => 0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00),
+                    _ => {
+                        self.pos = start;
+                        return Err(self.error("unpaired surrogate"));
+                    },
+                }
+            },
+            0xdc00..=0xdfff => {
+                self.pos = start;
+                return Err(self.error("unpaired surrogate"));
+            },
+            n => n,
+        };
+
+        Ok(std::char::from_u32(code_point).expect("surrogates were handled above"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let mut n = 0;
+
+        for _ in 0..4 {
+            let digit = match self.peek() {
+                Some(b) => (b as char).to_digit(16),
+                None => return Err(self.error("unterminated string")),
+            };
+            n = n * 16 + digit.ok_or_else(|| self.error("expected a hex digit"))?;
+            self.pos += 1;
+        }
+
+        Ok(n)
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn error_before(&mut self, reason: &'static str) -> ParseError {
+        // for when we already stepped past the byte that was wrong
+        self.pos -= 1;
+        self.error(reason)
+    }
+
+    fn error(&self, reason: &'static str) -> ParseError {
+        let before = &self.text.as_bytes()[..self.pos];
+        let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+
+        ParseError {
+            line: before.iter().filter(|&&b| b == b'\n').count() + 1,
+            // utf8 continuation bytes don't start a new char
+            column: before[line_start..].iter().filter(|&&b| b & 0b_1100_0000 != 0b_1000_0000).count() + 1,
+            reason,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{FromJson, FromJsonError, Json, Map};
+
+// https://tools.ietf.org/html/rfc6901
+// a pointer is a list of keys and array indices, each after a `/`, like `/players/2/name`. a key
+// with `/` or `~` in it writes them as `~1` and `~0`. the empty pointer is the whole value
+
+impl Json {
+    pub fn pointer(&self, pointer: &str) -> Option<&Json> {
+        self.walk(pointer).ok()
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Json> {
+        let mut json = self;
+
+        for (_, token) in tokens(pointer)? {
+            json = match json {
+                Json::Object(map) => map.get_mut(&token)?,
+                Json::Array(array) => array.get_mut(index(&token)?)?,
+                _ => return None,
+            };
+        }
+
+        Some(json)
+    }
+
+    pub fn at<T: FromJson>(&self, pointer: &str) -> Result<T, FromJsonError> {
+        // like `let x: f64 = json.at("/player/x")?`, and the error says which part was wrong
+        T::from_json(self.walk(pointer)?).map_err(|e| e.at_pointer(pointer))
+    }
+
+    pub fn string_at(&self, pointer: &str) -> Result<&str, FromJsonError> {
+        // these borrow instead of copying out like `at` does
+        self.walk(pointer)?.get_string().ok_or_else(|| FromJsonError::expected("a string").at_pointer(pointer))
+    }
+
+    pub fn array_at(&self, pointer: &str) -> Result<&[Json], FromJsonError> {
+        self.walk(pointer)?.get_array().ok_or_else(|| FromJsonError::expected("an array").at_pointer(pointer))
+    }
+
+    pub fn object_at(&self, pointer: &str) -> Result<&Map, FromJsonError> {
+        self.walk(pointer)?.get_object().ok_or_else(|| FromJsonError::expected("an object").at_pointer(pointer))
+    }
+
+    fn walk(&self, pointer: &str) -> Result<&Json, FromJsonError> {
+        // errors are about the deepest value we got to, so `/a/b` on `{"a":1}` says `/a` is wrong
+        let tokens = tokens(pointer).ok_or_else(|| FromJsonError::new(&format!("`{}` isn't a json pointer", pointer)))?;
+        let mut json = self;
+
+        for (start, token) in tokens {
+            let here = &pointer[..start];
+
+            json = match json {
+                Json::Object(map) => map.get(&token)
+                    .ok_or_else(|| FromJsonError::new(&format!("missing field `{}`", token)).at_pointer(here))?,
+                Json::Array(array) => index(&token).and_then(|i| array.get(i))
+                    .ok_or_else(|| FromJsonError::new(&format!("no element at index `{}`", token)).at_pointer(here))?,
+                _ => return Err(FromJsonError::expected("an object or an array").at_pointer(here)),
+            };
+        }
+
+        Ok(json)
+    }
+}
+
+fn tokens(pointer: &str) -> Option<Vec<(usize, String)>> {
+    // each token unescaped, with where its `/` is in the pointer
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+
+    if !pointer.starts_with('/') {
+        return None;
+    }
+
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    for token in pointer[1..].split('/') {
+        // a `~` has to be `~0` or `~1`
+        let mut escapes = token.split('~').skip(1);
+        if !escapes.all(|after| after.starts_with('0') || after.starts_with('1')) {
+            return None;
+        }
+
+        tokens.push((start, token.replace("~1", "/").replace("~0", "~")));
+        start += token.len() + 1;
+    }
+
+    Some(tokens)
+}
+
+fn index(token: &str) -> Option<usize> {
+    // no leading zeros or signs. `-` is the element after the last one, which never exists here
+    match token.as_bytes() {
+        [b'0'] => Some(0),
+        [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => token.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,469 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::{Json, Map, Number};
+use crate::parse::{ParseError, MAX_DEPTH};
+
+// reads json a piece at a time from a file or socket, without ever holding all of it. every call
+// to next gives the next event, like BeginObject, Key("kind"), String("gameState"), EndObject.
+// memory stays bounded: a fixed size buffer, one entry per open array or object, and strings no
+// longer than max_string_len. the input can be several values one after the other, like a log
+// with one per line, and the events end after the last one
+pub struct JsonReader<R: Read> {
+    reader: R,
+    buf: Box<[u8]>,
+    start: usize,
+    end: usize,
+    scopes: Vec<Scope>,
+    state: State,
+    max_string_len: usize,
+    line: usize,
+    column: usize,
+    failed: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Event {
+    BeginObject,
+    Key(String),
+    EndObject,
+    BeginArray,
+    EndArray,
+    Null,
+    Boolean(bool),
+    Number(Number),
+    String(String),
+}
+
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Invalid(ParseError),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Scope {
+    Array,
+    Object,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum State {
+    TopLevel, // a new value or the end of the input
+    Value, // after a `:`
+    ValueOrEnd, // after a `[`
+    KeyOrEnd, // after a `{`
+    CommaOrEnd, // after a value in an array or object, where a `,` goes straight on to the next one
+}
+
+const BUFFER_SIZE: usize = 8192;
+
+impl<R: Read> JsonReader<R> {
+    pub fn new(reader: R) -> JsonReader<R> {
+        JsonReader {
+            reader,
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            scopes: Vec::new(),
+            state: State::TopLevel,
+            max_string_len: 1 << 20,
+            line: 1,
+            column: 1,
+            failed: false,
+        }
+    }
+
+    pub fn set_max_string_len(&mut self, max_string_len: usize) {
+        // longer strings, keys and numbers are an error instead of being read into memory.
+        // a megabyte by default
+        self.max_string_len = max_string_len;
+    }
+
+    pub fn read_value(&mut self) -> Result<Option<Json>, ReadError> {
+        // the next whole value as a Json, for when the pieces are small but there are lots of
+        // them, like each element of a huge array. call it after a BeginArray, a Key, or at the top
+        // level. None is the end of the array or the input
+        let mut stack: Vec<(Json, Option<String>)> = Vec::new(); // each open container, and the key it's under
+        let mut key = None;
+
+        loop {
+            let event = match self.next().transpose()? {
+                Some(event) => event,
+                None if stack.is_empty() => return Ok(None),
+                None => unreachable!("the events can't end inside a value"),
+            };
+
+            let json = match event {
+                Event::BeginObject => { stack.push((Json::Object(Map::new()), key.take())); continue },
+                Event::BeginArray => { stack.push((Json::Array(Vec::new()), key.take())); continue },
+                Event::Key(k) => { key = Some(k); continue },
+                Event::EndArray | Event::EndObject => match stack.pop() {
+                    Some((json, k)) => { key = k; json },
+                    // the end of the array we were called in
+                    None => return Ok(None),
+                },
+                Event::Null => Json::Null,
+                Event::Boolean(b) => Json::Boolean(b),
+                Event::Number(n) => Json::Number(n),
+                Event::String(s) => Json::String(s),
+            };
+
+            match stack.last_mut() {
+                Some((Json::Array(array), _)) => array.push(json),
+                Some((Json::Object(object), _)) => { object.insert(key.take().expect("values in objects come after keys"), json); },
+                Some(_) => unreachable!("only arrays and objects are pushed"),
+                None => return Ok(Some(json)),
+            }
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, ReadError> {
+        self.skip_whitespace()?;
+
+        let next = self.peek()?;
+
+        let event = match self.state {
+            State::TopLevel if next.is_none() => return Ok(None),
+            State::TopLevel | State::Value => self.value()?,
+            State::ValueOrEnd if next == Some(b']') => self.end(Scope::Array)?,
+            State::ValueOrEnd => self.value()?,
+            State::KeyOrEnd if next == Some(b'}') => self.end(Scope::Object)?,
+            State::KeyOrEnd => self.key()?,
+            State::CommaOrEnd => match (next, self.scopes.last()) {
+                (Some(b','), Some(Scope::Array)) => {
+                    self.bump();
+                    self.skip_whitespace()?;
+                    self.value()?
+                },
+                (Some(b','), _) => {
+                    self.bump();
+                    self.skip_whitespace()?;
+                    self.key()?
+                },
+                (Some(b']'), Some(Scope::Array)) => self.end(Scope::Array)?,
+                (Some(b'}'), Some(Scope::Object)) => self.end(Scope::Object)?,
+                (Some(_), Some(Scope::Array)) => return Err(self.error("expected `,` or `]`")),
+                (Some(_), _) => return Err(self.error("expected `,` or `}`")),
+                (None, _) => return Err(self.error("unexpected end of input")),
+            },
+        };
+
+        Ok(Some(event))
+    }
+
+    fn value(&mut self) -> Result<Event, ReadError> {
+        let event = match self.peek()? {
+            Some(b'{') => {
+                self.enter(Scope::Object)?;
+                self.state = State::KeyOrEnd;
+                return Ok(Event::BeginObject);
+            },
+            Some(b'[') => {
+                self.enter(Scope::Array)?;
+                self.state = State::ValueOrEnd;
+                return Ok(Event::BeginArray);
+            },
+            Some(b'n') => { self.literal(b"null")?; Event::Null },
+            Some(b't') => { self.literal(b"true")?; Event::Boolean(true) },
+            Some(b'f') => { self.literal(b"false")?; Event::Boolean(false) },
+            Some(b'"') => Event::String(self.string()?),
+            Some(b'-') | Some(b'0'..=b'9') => Event::Number(self.number()?),
+            Some(_) => return Err(self.error("expected a value")),
+            None => return Err(self.error("unexpected end of input")),
+        };
+
+        self.state = self.after_value();
+        Ok(event)
+    }
+
+    fn key(&mut self) -> Result<Event, ReadError> {
+        match self.peek()? {
+            Some(b'"') => {},
+            Some(_) => return Err(self.error("expected a string key")),
+            None => return Err(self.error("unexpected end of input")),
+        }
+        let key = self.string()?;
+
+        self.skip_whitespace()?;
+        match self.peek()? {
+            Some(b':') => self.bump(),
+            Some(_) => return Err(self.error("expected `:`")),
+            None => return Err(self.error("unexpected end of input")),
+        }
+
+        self.state = State::Value;
+        Ok(Event::Key(key))
+    }
+
+    fn enter(&mut self, scope: Scope) -> Result<(), ReadError> {
+        if self.scopes.len() == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+
+        self.bump();
+        self.scopes.push(scope);
+        Ok(())
+    }
+
+    fn end(&mut self, scope: Scope) -> Result<Event, ReadError> {
+        self.bump();
+        self.scopes.pop();
+        self.state = self.after_value();
+
+        Ok(match scope {
+            Scope::Array => Event::EndArray,
+            Scope::Object => Event::EndObject,
+        })
+    }
+
+    fn after_value(&self) -> State {
+        if self.scopes.is_empty() { State::TopLevel } else { State::CommaOrEnd }
+    }
+
+    fn literal(&mut self, word: &[u8]) -> Result<(), ReadError> {
+        let start = self.here();
+
+        for &expected in word {
+            if self.peek()? != Some(expected) {
+                return Err(self.error_at(start, "expected a value"));
+            }
+            self.bump();
+        }
+
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<Number, ReadError> {
+        // -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?, the same as parse.rs
+        let mut text = String::new();
+
+        if self.peek()? == Some(b'-') { self.push(&mut text)? }
+
+        match self.peek()? {
+            Some(b'0') => {
+                self.push(&mut text)?;
+                if let Some(b'0'..=b'9') = self.peek()? {
+                    return Err(self.error("numbers can't have leading zeros"));
+                }
+            },
+            Some(b'1'..=b'9') => self.digits(&mut text)?,
+            _ => return Err(self.error("expected a digit")),
+        }
+
+        if self.peek()? == Some(b'.') {
+            self.push(&mut text)?;
+            self.digits(&mut text)?;
+        }
+
+        if let Some(b'e') | Some(b'E') = self.peek()? {
+            self.push(&mut text)?;
+            if let Some(b'+') | Some(b'-') = self.peek()? { self.push(&mut text)? }
+            self.digits(&mut text)?;
+        }
+
+        Ok(Number::from_literal(&text))
+    }
+
+    fn digits(&mut self, text: &mut String) -> Result<(), ReadError> {
+        // at least one
+        match self.peek()? {
+            Some(b'0'..=b'9') => {},
+            _ => return Err(self.error("expected a digit")),
+        }
+
+        while let Some(b'0'..=b'9') = self.peek()? { self.push(text)? }
+
+        Ok(())
+    }
+
+    fn push(&mut self, text: &mut String) -> Result<(), ReadError> {
+        // the next byte of a number, which is always ascii
+        if text.len() == self.max_string_len {
+            return Err(self.error("number is too long"));
+        }
+
+        text.push(self.peek()?.expect("only called after a peek") as char);
+        self.bump();
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<String, ReadError> {
+        let start = self.here();
+        self.bump(); // the opening quote
+
+        let mut bytes = Vec::new();
+
+        loop {
+            match self.peek()? {
+                Some(b'"') => {
+                    self.bump();
+                    break;
+                },
+                // like numbers, a string can be exactly max_string_len bytes long
+                Some(_) if bytes.len() >= self.max_string_len => return Err(self.error("string is too long")),
+                Some(b'\\') => {
+                    let escape_start = self.here();
+                    self.bump();
+                    let c = self.escape(escape_start)?;
+                    if bytes.len() + c.len_utf8() > self.max_string_len {
+                        return Err(self.error_at(escape_start, "string is too long"));
+                    }
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                Some(b) if b < 0x20 => return Err(self.error("control characters in strings must be escaped")),
+                Some(b) => {
+                    bytes.push(b);
+                    self.bump();
+                },
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+
+        String::from_utf8(bytes).map_err(|_| self.error_at(start, "strings must be utf8"))
+    }
+
+    fn escape(&mut self, start: (usize, usize)) -> Result<char, ReadError> {
+        let c = match self.peek()? {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\x08',
+            Some(b'f') => '\x0c',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.bump();
+                return self.unicode_escape(start);
+            },
+            Some(_) => return Err(self.error("invalid escape")),
+            None => return Err(self.error("unterminated string")),
+        };
+
+        self.bump();
+        Ok(c)
+    }
+
+    fn unicode_escape(&mut self, start: (usize, usize)) -> Result<char, ReadError> {
+        // surrogate errors point at the start of the escape, like parse.rs does
+        // anything outside the basic multilingual plane is written as a utf16 surrogate pair
+        let first = self.hex4()?;
+
+        let code_point = match first {
+            0xd800..=0xdbff => {
+                if self.peek()? != Some(b'\\') {
+                    return Err(self.error_at(start, "unpaired surrogate"));
+                }
+                self.bump();
+                if self.peek()? != Some(b'u') {
+                    return Err(self.error_at(start, "unpaired surrogate"));
+                }
+                self.bump();
+
+                match self.hex4()? {
+                    second @ 0xdc00..=0xdfff => 0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00),
+                    _ => return Err(self.error_at(start, "unpaired surrogate")),
+                }
+            },
+            0xdc00..=0xdfff => return Err(self.error_at(start, "unpaired surrogate")),
+            n => n,
+        };
+
+        Ok(std::char::from_u32(code_point).expect("surrogates were handled above"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, ReadError> {
+        let mut n = 0;
+
+        for _ in 0..4 {
+            let digit = match self.peek()? {
+                Some(b) => (b as char).to_digit(16),
+                None => return Err(self.error("unterminated string")),
+            };
+            n = n * 16 + digit.ok_or_else(|| self.error("expected a hex digit"))?;
+            self.bump();
+        }
+
+        Ok(n)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ReadError> {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek()? {
+            self.bump();
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, ReadError> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = loop {
+                match self.reader.read(&mut self.buf) {
+                    Ok(n) => break n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(ReadError::Io(e)),
+                }
+            };
+        }
+
+        Ok(self.buf[self.start..self.end].first().copied())
+    }
+
+    fn bump(&mut self) {
+        // steps past the byte we just peeked at
+        let b = self.buf[self.start];
+        self.start += 1;
+
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else if b & 0b_1100_0000 != 0b_1000_0000 {
+            // utf8 continuation bytes don't start a new char
+            self.column += 1;
+        }
+    }
+
+    fn here(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    fn error(&self, reason: &'static str) -> ReadError {
+        self.error_at(self.here(), reason)
+    }
+
+    fn error_at(&self, (line, column): (usize, usize), reason: &'static str) -> ReadError {
+        ReadError::Invalid(ParseError { line, column, reason })
+    }
+}
+
+impl<R: Read> Iterator for JsonReader<R> {
+    type Item = Result<Event, ReadError>;
+
+    fn next(&mut self) -> Option<Result<Event, ReadError>> {
+        // nothing after the first error, since we don't know where we are anymore
+        if self.failed { return None }
+
+        let result = self.next_event();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadError::Io(ref e) => write!(f, "{}", e),
+            ReadError::Invalid(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ReadError {}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> ReadError {
+        ReadError::Io(e)
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use crate::{FromJsonError, Json, Jsonable, Number};
+
+// what a value should look like, for checking messages from clients before handling them:
+//     Schema::object()
+//         .field("name", Schema::string().min_len(1).max_len(20))
+//         .field("seat", Schema::integer().min(0.0).max(3.0))
+//         .optional("cards", Schema::array(Schema::string()).max_len(13))
+// validate lists everything that's wrong instead of stopping at the first thing. objects can have
+// fields the schema doesn't mention. setting something that doesn't apply, like a min on a string,
+// is a bug and panics
+#[derive(Clone, Debug)]
+pub struct Schema {
+    kind: Kind,
+    nullable: bool,
+    one_of: Option<Vec<Json>>,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Any,
+    Null,
+    Bool,
+    Number,
+    Integer,
+    String,
+    Array(Box<Schema>),
+    Object(Vec<Field>),
+}
+
+#[derive(Clone, Debug)]
+struct Field {
+    key: String,
+    schema: Schema,
+    required: bool,
+}
+
+impl Schema {
+    fn new(kind: Kind) -> Schema {
+        Schema { kind, nullable: false, one_of: None, min: None, max: None, min_len: None, max_len: None }
+    }
+
+    pub fn any() -> Schema { Schema::new(Kind::Any) }
+    pub fn null() -> Schema { Schema::new(Kind::Null) }
+    pub fn bool() -> Schema { Schema::new(Kind::Bool) }
+    pub fn number() -> Schema { Schema::new(Kind::Number) }
+    pub fn integer() -> Schema { Schema::new(Kind::Integer) } // a whole number that fits in an i64 or u64
+    pub fn string() -> Schema { Schema::new(Kind::String) }
+    pub fn array(items: Schema) -> Schema { Schema::new(Kind::Array(Box::new(items))) }
+    pub fn object() -> Schema { Schema::new(Kind::Object(Vec::new())) }
+
+    pub fn field(self, key: &str, schema: Schema) -> Schema {
+        self.add_field(key, schema, true)
+    }
+
+    pub fn optional(self, key: &str, schema: Schema) -> Schema {
+        // can be left out. use nullable on the schema to allow null too
+        self.add_field(key, schema, false)
+    }
+
+    pub fn nullable(mut self) -> Schema {
+        self.nullable = true;
+        self
+    }
+
+    pub fn one_of<T: Jsonable + Clone>(mut self, values: &[T]) -> Schema {
+        // an enum, like Schema::string().one_of(&["red", "green"])
+        self.one_of = Some(values.iter().cloned().map(Jsonable::into_json).collect());
+        self
+    }
+
+    pub fn min(mut self, min: f64) -> Schema {
+        assert!(self.is_number(), "min and max are for numbers");
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(mut self, max: f64) -> Schema {
+        assert!(self.is_number(), "min and max are for numbers");
+        self.max = Some(max);
+        self
+    }
+
+    pub fn min_len(mut self, min_len: usize) -> Schema {
+        // characters in a string or elements in an array
+        assert!(self.has_len(), "min_len and max_len are for strings and arrays");
+        self.min_len = Some(min_len);
+        self
+    }
+
+    pub fn max_len(mut self, max_len: usize) -> Schema {
+        assert!(self.has_len(), "min_len and max_len are for strings and arrays");
+        self.max_len = Some(max_len);
+        self
+    }
+
+    pub fn validate(&self, json: &Json) -> Result<(), Vec<FromJsonError>> {
+        let mut violations = Vec::new();
+        self.check(json, "", &mut violations);
+
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+
+    fn add_field(mut self, key: &str, schema: Schema, required: bool) -> Schema {
+        match self.kind {
+            Kind::Object(ref mut fields) => fields.push(Field { key: key.to_string(), schema, required }),
+            _ => panic!("fields are for objects"),
+        }
+
+        self
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self.kind, Kind::Number | Kind::Integer)
+    }
+
+    fn has_len(&self) -> bool {
+        matches!(self.kind, Kind::String | Kind::Array(_))
+    }
+
+    fn check(&self, json: &Json, path: &str, violations: &mut Vec<FromJsonError>) {
+        // path is the json pointer to json, already escaped
+        let mut violation = |reason: String| violations.push(FromJsonError::new(&reason).at_pointer(path));
+
+        if self.nullable && *json == Json::Null {
+            return;
+        }
+
+        let len = match (&self.kind, json) {
+            (Kind::Any, _) | (Kind::Null, Json::Null) | (Kind::Bool, Json::Boolean(_)) => None,
+            (Kind::Number, Json::Number(n)) => { self.check_range(n, &mut violation); None },
+            (Kind::Integer, Json::Number(n)) if n.as_i64().is_some() || n.as_u64().is_some() => {
+                self.check_range(n, &mut violation);
+                None
+            },
+            (Kind::String, Json::String(s)) => Some(s.chars().count()),
+            (Kind::Array(_), Json::Array(array)) => Some(array.len()),
+            (Kind::Object(_), Json::Object(_)) => None,
+            (kind, _) => return violation(format!("expected {}", kind.describe(self.nullable))),
+        };
+
+        if let Some(len) = len {
+            let unit = if let Kind::String = self.kind { "characters" } else { "elements" };
+            if let Some(min) = self.min_len.filter(|&min| len < min) {
+                violation(format!("must have at least {} {}", min, unit));
+            }
+            if let Some(max) = self.max_len.filter(|&max| len > max) {
+                violation(format!("must have at most {} {}", max, unit));
+            }
+        }
+
+        if let Some(ref values) = self.one_of {
+            if !values.contains(json) {
+                let values: Vec<String> = values.iter().map(Json::to_string).collect();
+                violation(format!("must be one of {}", values.join(", ")));
+            }
+        }
+
+        match (&self.kind, json) {
+            (Kind::Array(items), Json::Array(array)) => {
+                for (i, elem) in array.iter().enumerate() {
+                    items.check(elem, &format!("{}/{}", path, i), violations);
+                }
+            },
+            (Kind::Object(fields), Json::Object(map)) => {
+                for field in fields {
+                    let here = format!("{}/{}", path, field.key.replace('~', "~0").replace('/', "~1"));
+                    match map.get(&field.key) {
+                        Some(value) => field.schema.check(value, &here, violations),
+                        None if field.required => {
+                            violations.push(FromJsonError::new(&format!("missing field `{}`", field.key)).at_pointer(path));
+                        },
+                        None => {},
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn check_range(&self, n: &Number, violation: &mut impl FnMut(String)) {
+        // numbers too big for an f64 are as good as infinite
+        let n = n.as_f64().unwrap_or_else(|| if n.to_string().starts_with('-') { f64::NEG_INFINITY } else { f64::INFINITY });
+        if let Some(min) = self.min.filter(|&min| n < min) {
+            violation(format!("must be at least {}", min));
+        }
+        if let Some(max) = self.max.filter(|&max| n > max) {
+            violation(format!("must be at most {}", max));
+        }
+    }
+}
+
+impl Kind {
+    fn describe(&self, nullable: bool) -> String {
+        let what = match *self {
+            Kind::Any => "anything",
+            Kind::Null => "null",
+            Kind::Bool => "a boolean",
+            Kind::Number => "a number",
+            Kind::Integer => "a whole number",
+            Kind::String => "a string",
+            Kind::Array(_) => "an array",
+            Kind::Object(_) => "an object",
+        };
+
+        if nullable { format!("{} or null", what) } else { what.to_string() }
+    }
+}
+
+// a schema for each kind of message, for the usual `{"kind": "join", ...}` messages:
+//     let schemas = MessageSchemas::new()
+//         .kind("join", Schema::object().field("gameId", Schema::string()))
+//         .kind("leave", Schema::object());
+//     match schemas.validate(&message)? { "join" => ..., "leave" => ..., _ => unreachable!() }
+#[derive(Clone, Debug, Default)]
+pub struct MessageSchemas {
+    kinds: HashMap<String, Schema>,
+}
+
+impl MessageSchemas {
+    pub fn new() -> MessageSchemas {
+        MessageSchemas { kinds: HashMap::new() }
+    }
+
+    pub fn kind(mut self, kind: &str, schema: Schema) -> MessageSchemas {
+        assert!(matches!(schema.kind, Kind::Object(_)), "messages are objects");
+        self.kinds.insert(kind.to_string(), schema);
+        self
+    }
+
+    pub fn validate<'a>(&self, message: &'a Json) -> Result<&'a str, Vec<FromJsonError>> {
+        // the kind, if it's one we know and the rest of the message matches its schema
+        let object = message.get_object().ok_or_else(|| vec![FromJsonError::expected("an object")])?;
+
+        let kind = match object.get("kind") {
+            Some(kind) => kind.get_string().ok_or_else(|| vec![FromJsonError::expected("a string").at_key("kind")])?,
+            None => return Err(vec![FromJsonError::new("missing field `kind`")]),
+        };
+
+        let schema = self.kinds.get(kind)
+            .ok_or_else(|| vec![FromJsonError::new(&format!("unknown kind `{}`", kind)).at_key("kind")])?;
+
+        schema.validate(message).map(|()| kind)
+    }
+}
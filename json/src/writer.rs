@@ -0,0 +1,138 @@
+use std::io::{self, Write};
+
+use crate::{Json, Number};
+use crate::encode::Escaped;
+
+// writes json straight to a socket, file or buffer as it's produced, instead of building a Json
+// and then a String. arrays and objects are opened and closed around their contents, and every
+// call can be chained:
+//     writer.begin_object()?.key("x")?.number(1.5)?.key("tags")?.begin_array()?.string("a")?.end_array()?.end_object()?;
+// the output is the same as Json's Display. calls in the wrong order, like a value in an object
+// without a key, are bugs and panic
+pub struct JsonWriter<W: Write> {
+    writer: W,
+    scopes: Vec<Scope>,
+    first: bool, // nothing has been written in the innermost array or object yet
+    after_key: bool,
+    done: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Scope {
+    Array,
+    Object,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W) -> JsonWriter<W> {
+        JsonWriter { writer, scopes: Vec::new(), first: true, after_key: false, done: false }
+    }
+
+    pub fn begin_object(&mut self) -> io::Result<&mut JsonWriter<W>> {
+        self.before_value()?;
+        self.writer.write_all(b"{")?;
+        self.enter(Scope::Object);
+        Ok(self)
+    }
+
+    pub fn key(&mut self, key: &str) -> io::Result<&mut JsonWriter<W>> {
+        assert_eq!(self.scopes.last(), Some(&Scope::Object), "keys only go in objects");
+        assert!(!self.after_key, "the last key is still waiting for its value");
+
+        if !self.first { self.writer.write_all(b",")? }
+        write!(self.writer, "{}:", Escaped(key))?;
+
+        self.first = false;
+        self.after_key = true;
+        Ok(self)
+    }
+
+    pub fn end_object(&mut self) -> io::Result<&mut JsonWriter<W>> {
+        assert!(!self.after_key, "the last key is still waiting for its value");
+        self.leave(Scope::Object);
+        self.writer.write_all(b"}")?;
+        Ok(self)
+    }
+
+    pub fn begin_array(&mut self) -> io::Result<&mut JsonWriter<W>> {
+        self.before_value()?;
+        self.writer.write_all(b"[")?;
+        self.enter(Scope::Array);
+        Ok(self)
+    }
+
+    pub fn end_array(&mut self) -> io::Result<&mut JsonWriter<W>> {
+        self.leave(Scope::Array);
+        self.writer.write_all(b"]")?;
+        Ok(self)
+    }
+
+    pub fn null(&mut self) -> io::Result<&mut JsonWriter<W>> {
+        self.before_value()?;
+        self.writer.write_all(b"null")?;
+        Ok(self)
+    }
+
+    pub fn bool(&mut self, b: bool) -> io::Result<&mut JsonWriter<W>> {
+        self.before_value()?;
+        write!(self.writer, "{}", b)?;
+        Ok(self)
+    }
+
+    pub fn number(&mut self, n: impl Into<Number>) -> io::Result<&mut JsonWriter<W>> {
+        // any integer exactly, or an f64
+        self.before_value()?;
+        write!(self.writer, "{}", n.into())?;
+        Ok(self)
+    }
+
+    pub fn string(&mut self, s: &str) -> io::Result<&mut JsonWriter<W>> {
+        self.before_value()?;
+        write!(self.writer, "{}", Escaped(s))?;
+        Ok(self)
+    }
+
+    pub fn json(&mut self, json: &Json) -> io::Result<&mut JsonWriter<W>> {
+        // for the parts that are already a Json, like something we keep around and send every time
+        self.before_value()?;
+        write!(self.writer, "{}", json)?;
+        Ok(self)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        // gives the writer back once the value is complete
+        assert!(self.done && self.scopes.is_empty(), "the value isn't finished yet");
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn before_value(&mut self) -> io::Result<()> {
+        match self.scopes.last() {
+            Some(Scope::Object) => {
+                assert!(self.after_key, "values in an object need a key first");
+                self.after_key = false;
+            },
+            Some(Scope::Array) => {
+                if !self.first { self.writer.write_all(b",")? }
+                self.first = false;
+            },
+            None => {
+                assert!(!self.done, "a json writer only writes one value");
+                self.done = true;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn enter(&mut self, scope: Scope) {
+        self.scopes.push(scope);
+        self.first = true;
+    }
+
+    fn leave(&mut self, scope: Scope) {
+        assert_eq!(self.scopes.pop(), Some(scope), "closed something that isn't open");
+        // whatever we were in now has at least this in it
+        self.first = false;
+    }
+}
@@ -0,0 +1,79 @@
+// Jsonable for the standard types, and the keys json! takes
+
+use std::collections::{BTreeMap, HashMap};
+
+use json::{json, jsons, Json, Jsonable};
+
+#[test]
+fn every_number_type_is_a_number() {
+    assert_eq!(json!(3u64), Json::Number(3.0.into()));
+    assert_eq!(json!(-3i8), Json::Number((-3.0).into()));
+    assert_eq!(json!(1.5f32), Json::Number(1.5.into()));
+    assert_eq!(jsons!([(1u16), (2i64), (3u128), (4isize)]), "[1,2,3,4]");
+}
+
+#[test]
+fn options_are_null_when_none() {
+    assert_eq!(json!(Some(1u32)), Json::Number(1.0.into()));
+    assert_eq!(json!(None::<String>), Json::Null);
+}
+
+#[test]
+fn sequences_are_arrays() {
+    let scores = vec![1u32, 2, 3];
+    assert_eq!(json!(&scores[..]).to_string(), "[1,2,3]");
+    assert_eq!(json!(scores).to_string(), "[1,2,3]");
+    assert_eq!(json!(["a", "b"]).to_string(), r#"["a","b"]"#);
+    assert_eq!(json!([[1u8, 2], [3, 4]]).to_string(), "[[1,2],[3,4]]");
+    assert_eq!(json!(vec![Some(true), None]).to_string(), "[true,null]");
+}
+
+#[test]
+fn fixed_size_arrays_are_arrays() {
+    let position = [1u8, 2];
+    assert_eq!(position.into_json().to_string(), "[1,2]");
+    assert_eq!([[1.5f64; 2]; 2].into_json().to_string(), "[[1.5,1.5],[1.5,1.5]]");
+    assert_eq!([Some("a"), None].into_json().to_string(), r#"["a",null]"#);
+    assert_eq!([0u32; 32].into_json().get_array().map(|array| array.len()), Some(32));
+    assert_eq!(<[String; 0]>::default().into_json().to_string(), "[]");
+}
+
+#[test]
+fn tuples_are_arrays() {
+    assert_eq!((1.5, -2.0).into_json().to_string(), "[1.5,-2]");
+    assert_eq!(("ethan", 3u8, true).into_json().to_string(), r#"["ethan",3,true]"#);
+}
+
+#[test]
+fn maps_are_objects() {
+    let mut scores = BTreeMap::new();
+    scores.insert("b".to_string(), 2u32);
+    scores.insert("a".to_string(), 1);
+    assert_eq!(json!(scores).to_string(), r#"{"a":1,"b":2}"#);
+
+    let mut names = HashMap::new();
+    names.insert("ethan".to_string(), vec!["red"]);
+    assert_eq!(json!(names).to_string(), r#"{"ethan":["red"]}"#);
+}
+
+#[test]
+fn references_are_copied() {
+    let name = String::from("ethan");
+    let position = (1.0, 2.0);
+    assert_eq!(jsons!({name: (&name), position: (&position)}), r#"{"name":"ethan","position":[1,2]}"#);
+}
+
+#[test]
+fn keys_can_be_strings_or_expressions() {
+    let id = 7;
+    let key = "dynamic";
+
+    let text = jsons!({
+        kind: "joined",
+        "game-id": id,
+        (key): true,
+        (format!("player{}", id)): null,
+    });
+
+    assert_eq!(text, r#"{"kind":"joined","game-id":7,"dynamic":true,"player7":null}"#);
+}
@@ -0,0 +1,96 @@
+// numbers kept exactly as they were written
+
+use json::{json, Json, Number};
+
+fn number(text: &str) -> Number {
+    match text.parse::<Json>().unwrap() {
+        Json::Number(n) => n,
+        other => panic!("{} parsed as {:?}", text, other),
+    }
+}
+
+#[test]
+fn literals_come_back_out_the_same() {
+    for text in ["0", "-0", "1.50", "1E2", "-2.5e-3", "9007199254740993", "18446744073709551615", "-9223372036854775808",
+                 "123456789012345678901234567890", "0.1000000000000000000000000001", "1e400"].iter() {
+        assert_eq!(number(text).to_string(), *text);
+    }
+}
+
+#[test]
+fn ids_past_two_to_the_fifty_three() {
+    // an f64 would turn this into ...992
+    let json: Json = r#"{"id":9007199254740993,"time":1602190000123}"#.parse().unwrap();
+    assert_eq!(json.at::<u64>("/id"), Ok(9_007_199_254_740_993));
+    assert_eq!(json.get_object().unwrap().get("time").unwrap().get_u64(), Some(1_602_190_000_123));
+    assert_eq!(json.to_string(), r#"{"id":9007199254740993,"time":1602190000123}"#);
+
+    assert_eq!(json!({id: (u64::MAX)}).to_string(), r#"{"id":18446744073709551615}"#);
+    assert_eq!(json!(i64::MIN).to_string(), "-9223372036854775808");
+    assert_eq!(json!(u128::MAX).to_string(), "340282366920938463463374607431768211455");
+}
+
+#[test]
+fn checked_conversions() {
+    assert_eq!(number("12").as_u64(), Some(12));
+    assert_eq!(number("12").as_i64(), Some(12));
+    assert_eq!(number("-12").as_u64(), None);
+    assert_eq!(number("-12").as_i64(), Some(-12));
+    assert_eq!(number("1.2e3").as_u64(), Some(1200));
+    assert_eq!(number("-0.0").as_i64(), Some(0));
+    assert_eq!(number("1.5").as_u64(), None);
+    assert_eq!(number("18446744073709551616").as_u64(), None);
+    assert_eq!(number("9223372036854775808").as_i64(), None);
+    assert_eq!(number("1e30").as_u64(), None);
+
+    assert_eq!(number("1.5").as_f64(), Some(1.5));
+    assert_eq!(number("1e400").as_f64(), None);
+    assert_eq!(number("-1e-400").as_f64(), Some(-0.0));
+
+    assert_eq!(Number::from(3.0).as_u64(), Some(3));
+    assert_eq!(Number::from(f64::NAN).as_f64(), None);
+    assert_eq!(Number::from(-1e19).as_i64(), None);
+}
+
+#[test]
+fn equal_when_they_are_the_same_value() {
+    assert_eq!(number("1"), number("1.0"));
+    assert_eq!(number("1.50"), Number::from(1.5));
+    assert_eq!(number("100"), number("1E2"));
+    assert_eq!(number("0.001"), number("1e-3"));
+    assert_eq!(number("-0"), number("0"));
+    assert_eq!(number("1e400"), number("10e399"));
+
+    assert_ne!(number("9007199254740993"), number("9007199254740992"));
+    assert_ne!(number("1"), number("-1"));
+    assert_ne!(number("0.1"), number("0.01"));
+    assert_ne!(Number::from(f64::NAN), Number::from(f64::NAN));
+}
+
+#[test]
+fn equal_however_they_were_made() {
+    // the same answers whether both sides were made the same way or not
+    assert_eq!(Number::from(7u8), Number::from(7u64));
+    assert_eq!(Number::from(-7i8), Number::from(-7i64));
+    assert_ne!(Number::from(7u8), Number::from(-7i8));
+    assert_eq!(Number::from(0.5), Number::from(0.5f32));
+    assert_eq!(Number::from(0.0), Number::from(-0.0));
+    assert_ne!(Number::from(0.1), Number::from(0.1f32));
+    assert_ne!(Number::from(f64::INFINITY), Number::from(f64::INFINITY));
+
+    assert_eq!(Number::from(7u8), Number::from(7.0));
+    assert_eq!(Number::from(-7i8), number("-7.0"));
+    assert_eq!(Number::from(u128::MAX), number("340282366920938463463374607431768211455"));
+    assert_eq!(Number::from(0.1), number("0.1"));
+    assert_ne!(Number::from(3u8), number("3.000000000000000000001"));
+}
+
+#[test]
+fn whole_numbers_check_their_range() {
+    assert_eq!(json!(255).at::<u8>(""), Ok(255));
+    assert!(json!(256).at::<u8>("").is_err());
+    assert!(json!(-1).at::<u32>("").is_err());
+    assert!(json!(2.5).at::<i64>("").is_err());
+    assert_eq!(json!(u64::MAX).at::<u64>(""), Ok(u64::MAX));
+    assert!("18446744073709551616".parse::<Json>().unwrap().at::<u64>("").is_err());
+}
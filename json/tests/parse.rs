@@ -0,0 +1,113 @@
+// https://tools.ietf.org/html/rfc8259
+
+use json::{Json, Map, ParseError};
+
+fn parse(text: &str) -> Result<Json, ParseError> {
+    text.parse()
+}
+
+fn error(text: &str) -> (usize, usize, &'static str) {
+    let e = parse(text).unwrap_err();
+    (e.line, e.column, e.reason)
+}
+
+#[test]
+fn parses_every_kind_of_value() {
+    let json = parse(r#" {"a": [1, -2.5e3, true, false, null], "b": {"c": "d"}, "e": []} "#).unwrap();
+
+    let mut inner = Map::new();
+    inner.insert("c".to_string(), Json::String("d".to_string()));
+
+    let mut expected = Map::new();
+    expected.insert("a".to_string(), Json::Array(vec![
+        Json::Number(1.0.into()), Json::Number((-2500.0).into()), Json::Boolean(true), Json::Boolean(false), Json::Null,
+    ]));
+    expected.insert("b".to_string(), Json::Object(inner));
+    expected.insert("e".to_string(), Json::Array(Vec::new()));
+
+    assert_eq!(json, Json::Object(expected));
+}
+
+#[test]
+fn parses_scalars_at_the_top_level() {
+    assert_eq!(parse("0").unwrap(), Json::Number(0.0.into()));
+    assert_eq!(parse("-0.5").unwrap(), Json::Number((-0.5).into()));
+    assert_eq!(parse("1E2").unwrap(), Json::Number(100.0.into()));
+    assert_eq!(parse("\"\"").unwrap(), Json::String(String::new()));
+    assert_eq!(parse("\ttrue\r\n").unwrap(), Json::Boolean(true));
+}
+
+#[test]
+fn decodes_escapes() {
+    assert_eq!(parse(r#""a\"b\\c\/d\b\f\n\r\t""#).unwrap(), Json::String("a\"b\\c/d\x08\x0c\n\r\t".to_string()));
+    assert_eq!(parse(r#""é中""#).unwrap(), Json::String("é中".to_string()));
+}
+
+#[test]
+fn decodes_surrogate_pairs_and_raw_utf8() {
+    assert_eq!(parse(r#""😀""#).unwrap(), Json::String("😀".to_string()));
+    assert_eq!(parse("\"😀 ok\"").unwrap(), Json::String("😀 ok".to_string()));
+}
+
+#[test]
+fn rejects_what_rust_parses_as_floats() {
+    for text in &["NaN", "inf", "-infinity", "+1", ".5", "1.", "01", "-", "1e", "0x10"] {
+        assert!(parse(text).is_err(), "{} should be invalid", text);
+    }
+}
+
+#[test]
+fn keeps_numbers_too_big_for_a_float() {
+    let json = parse("[1e400]").unwrap();
+    assert_eq!(json.to_string(), "[1e400]");
+    assert_eq!(json.get_array().unwrap()[0].get_number(), None);
+}
+
+#[test]
+fn rejects_lenient_syntax() {
+    for text in &["[1,]", "{\"a\":1,}", "[1 2]", "{a: 1}", "{'a': 1}", "'a'", "[", "{\"a\"}", "", "  ", "nul", "True", "1 2"] {
+        assert!(parse(text).is_err(), "{:?} should be invalid", text);
+    }
+}
+
+#[test]
+fn rejects_bad_strings() {
+    assert_eq!(error("\"tab\there\""), (1, 5, "control characters in strings must be escaped"));
+    assert_eq!(error(r#""\x""#), (1, 3, "invalid escape"));
+    assert_eq!(error(r#""\u12g4""#), (1, 6, "expected a hex digit"));
+    assert_eq!(error(r#""\ud83d""#), (1, 2, "unpaired surrogate"));
+    assert_eq!(error(r#""\ude00\ud83d""#), (1, 2, "unpaired surrogate"));
+    assert_eq!(error("\"never ends"), (1, 12, "unterminated string"));
+}
+
+#[test]
+fn reports_the_line_and_column() {
+    assert_eq!(error("{\n  \"a\": 1,\n  \"b\" 2\n}"), (3, 7, "expected `:`"));
+    assert_eq!(error("[\"é\", x]"), (1, 7, "expected a value"));
+    assert_eq!(error("{} {}"), (1, 4, "unexpected characters after the value"));
+    assert_eq!(error("[1, 2"), (1, 6, "unexpected end of input"));
+}
+
+#[test]
+fn limits_nesting() {
+    let ok = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    assert!(parse(&ok).is_ok());
+
+    let too_deep = format!("{}{}", "[".repeat(129), "]".repeat(129));
+    assert_eq!(error(&too_deep), (1, 129, "nested too deeply"));
+
+    // and isn't a stack overflow, however deep it goes
+    assert!(parse(&"{\"a\":".repeat(100_000)).is_err());
+}
+
+#[test]
+fn takes_linear_time() {
+    // the old parser rescanned every nested level, which made this quadratic
+    let wide = format!("[{}1]", "[1,2,{\"a\":[3]}],".repeat(200_000));
+    assert_eq!(parse(&wide).unwrap().get_array().unwrap().len(), 200_001);
+}
+
+#[test]
+fn errors_display_where_they_happened() {
+    assert_eq!(parse("[1,]").unwrap_err().to_string(), "expected a value at line 1, column 4");
+}
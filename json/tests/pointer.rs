@@ -0,0 +1,80 @@
+// json pointers, https://tools.ietf.org/html/rfc6901
+
+use json::{json, Json};
+
+fn example() -> Json {
+    // the example from section 5 of the rfc
+    r#"{
+        "foo": ["bar", "baz"],
+        "": 0,
+        "a/b": 1,
+        "c%d": 2,
+        "e^f": 3,
+        "g|h": 4,
+        "i\\j": 5,
+        "k\"l": 6,
+        " ": 7,
+        "m~n": 8
+    }"#.parse().unwrap()
+}
+
+#[test]
+fn the_rfc_examples() {
+    let json = example();
+
+    assert_eq!(json.pointer(""), Some(&json));
+    assert_eq!(json.pointer("/foo"), Some(&json!(["bar", "baz"])));
+    assert_eq!(json.pointer("/foo/0"), Some(&json!("bar")));
+
+    let expected = [("/", 0), ("/a~1b", 1), ("/c%d", 2), ("/e^f", 3), ("/g|h", 4), ("/i\\j", 5), ("/k\"l", 6), ("/ ", 7), ("/m~0n", 8)];
+    for &(pointer, n) in expected.iter() {
+        assert_eq!(json.pointer(pointer), Some(&Json::Number((n as f64).into())), "{}", pointer);
+    }
+}
+
+#[test]
+fn things_that_are_not_there() {
+    let json = example();
+
+    for pointer in ["/nope", "/foo/2", "/foo/-", "/foo/01", "/foo/+1", "/foo/0/x", "foo", "/m~2n", "/m~"].iter() {
+        assert_eq!(json.pointer(pointer), None, "{}", pointer);
+    }
+}
+
+#[test]
+fn changing_a_value() {
+    let mut json = json!({settings: {startSection: "1.1"}, players: [{x: 1}]});
+
+    *json.pointer_mut("/settings/startSection").unwrap() = json!("2.3");
+    *json.pointer_mut("/players/0/x").unwrap() = json!(5);
+    assert_eq!(json.pointer_mut("/players/1"), None);
+
+    assert_eq!(json.to_string(), r#"{"settings":{"startSection":"2.3"},"players":[{"x":5}]}"#);
+}
+
+#[test]
+fn typed_lookups() {
+    let json = json!({settings: {startSection: "1.1", rounds: 3}, players: [{name: "ethan"}]});
+
+    assert_eq!(json.string_at("/settings/startSection"), Ok("1.1"));
+    assert_eq!(json.at::<u8>("/settings/rounds"), Ok(3));
+    assert_eq!(json.at::<String>("/players/0/name"), Ok("ethan".to_string()));
+    assert_eq!(json.array_at("/players").map(|a| a.len()), Ok(1));
+    assert_eq!(json.object_at("/settings").map(|o| o.len()), Ok(2));
+}
+
+#[test]
+fn errors_say_which_part_was_wrong() {
+    let json = json!({settings: {startSection: 1, rounds: 3.5}, players: [{name: "ethan"}], "a/b": true});
+
+    let error = |result: Result<(), json::FromJsonError>| result.unwrap_err().to_string();
+
+    assert_eq!(error(json.string_at("/settings/startSection").map(drop)), "expected a string at /settings/startSection");
+    assert_eq!(error(json.string_at("/settings/endSection").map(drop)), "missing field `endSection` at /settings");
+    assert_eq!(error(json.at::<u8>("/settings/rounds").map(drop)), "expected a whole number that fits in u8 at /settings/rounds");
+    assert_eq!(error(json.at::<Vec<u8>>("/players").map(drop)), "expected a whole number that fits in u8 at /players/0");
+    assert_eq!(error(json.object_at("/players/3").map(drop)), "no element at index `3` at /players");
+    assert_eq!(error(json.object_at("/a~1b/c").map(drop)), "expected an object or an array at /a~1b");
+    assert_eq!(error(json.at::<bool>("/missing").map(drop)), "missing field `missing`");
+    assert_eq!(error(json.at::<bool>("settings").map(drop)), "`settings` isn't a json pointer");
+}
@@ -0,0 +1,82 @@
+// the pretty printer, and objects keeping their keys in order
+
+use json::{json, jsons, Json, Map};
+
+#[test]
+fn objects_keep_the_order_they_were_written_in() {
+    let text = jsons!({kind: "gameState", zebra: 1, apple: [true, null], middle: {b: 1, a: 2}});
+    assert_eq!(text, r#"{"kind":"gameState","zebra":1,"apple":[true,null],"middle":{"b":1,"a":2}}"#);
+}
+
+#[test]
+fn parsing_keeps_the_order_too() {
+    let text = r#"{"z":1,"y":{"b":2,"a":3},"x":[]}"#;
+    assert_eq!(text.parse::<Json>().unwrap().to_string(), text);
+}
+
+#[test]
+fn replacing_a_key_keeps_its_place() {
+    let mut map = Map::new();
+    map.insert("a".into(), json!(1));
+    map.insert("b".into(), json!(2));
+    assert_eq!(map.insert("a".into(), json!(3)), Some(json!(1)));
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+
+    assert_eq!(map.remove("a"), Some(json!(3)));
+    map.insert("c".into(), json!(4));
+    assert_eq!(map.get("b"), Some(&json!(2)));
+    assert_eq!(Json::Object(map).to_string(), r#"{"b":2,"c":4}"#);
+}
+
+#[test]
+fn objects_are_equal_in_any_order() {
+    assert_eq!(json!({a: 1, b: 2}), json!({b: 2, a: 1}));
+    assert_ne!(json!({a: 1, b: 2}), json!({a: 1}));
+}
+
+#[test]
+fn pretty_prints_with_two_spaces() {
+    let json = json!({kind: "lobby", users: ["ann", "bo"], empty: [], settings: {}, n: 1.5});
+    assert_eq!(json.pretty().to_string(), r#"{
+  "kind": "lobby",
+  "users": [
+    "ann",
+    "bo"
+  ],
+  "empty": [],
+  "settings": {},
+  "n": 1.5
+}"#);
+}
+
+#[test]
+fn indentation_is_configurable() {
+    let json = json!([{a: null}]);
+    assert_eq!(json.pretty().indent(4).to_string(), "[\n    {\n        \"a\": null\n    }\n]");
+    assert_eq!(json.pretty().indent(0).to_string(), "[\n{\n\"a\": null\n}\n]");
+}
+
+#[test]
+fn keys_can_be_sorted() {
+    let json = json!({b: {y: 1, x: 2}, a: true});
+    assert_eq!(json.pretty().sort_keys(true).to_string(), r#"{
+  "a": true,
+  "b": {
+    "x": 2,
+    "y": 1
+  }
+}"#);
+}
+
+#[test]
+fn scalars_look_the_same_as_compact() {
+    for json in &[json!(null), json!(true), json!(2.5), json!("a\"b")] {
+        assert_eq!(json.pretty().to_string(), json.to_string());
+    }
+}
+
+#[test]
+fn pretty_output_parses_back() {
+    let json = json!({kind: "x", nested: [[1, 2], {key: "with \"quotes\""}]});
+    assert_eq!(json.pretty().to_string().parse::<Json>().unwrap(), json);
+}
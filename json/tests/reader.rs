@@ -0,0 +1,163 @@
+// reading json a piece at a time
+
+use std::io::{self, Read};
+
+use json::{json, Event, Json, JsonReader, ReadError};
+
+// hands out a few bytes at a time, so every token gets split across reads somewhere
+struct Trickle<'a> {
+    bytes: &'a [u8],
+    step: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.step = self.step % 3 + 1;
+        let n = self.step.min(buf.len()).min(self.bytes.len());
+        buf[..n].copy_from_slice(&self.bytes[..n]);
+        self.bytes = &self.bytes[n..];
+        Ok(n)
+    }
+}
+
+fn reader(text: &str) -> JsonReader<Trickle<'_>> {
+    JsonReader::new(Trickle { bytes: text.as_bytes(), step: 0 })
+}
+
+fn events(text: &str) -> Result<Vec<Event>, ReadError> {
+    reader(text).collect()
+}
+
+fn error(text: &str) -> (usize, usize, &'static str) {
+    match events(text) {
+        Err(ReadError::Invalid(e)) => (e.line, e.column, e.reason),
+        other => panic!("{:?} read as {:?}", text, other),
+    }
+}
+
+#[test]
+fn every_kind_of_event() {
+    let events = events(r#" {"a": [1, -2.5e3, true, false, null], "b": {"c": "d\n"}, "e": []} "#).unwrap();
+
+    assert_eq!(events, vec![
+        Event::BeginObject,
+        Event::Key("a".into()),
+        Event::BeginArray,
+        Event::Number(1.0.into()), Event::Number((-2500.0).into()), Event::Boolean(true), Event::Boolean(false), Event::Null,
+        Event::EndArray,
+        Event::Key("b".into()),
+        Event::BeginObject, Event::Key("c".into()), Event::String("d\n".into()), Event::EndObject,
+        Event::Key("e".into()),
+        Event::BeginArray, Event::EndArray,
+        Event::EndObject,
+    ]);
+}
+
+#[test]
+fn reads_the_same_values_as_parse() {
+    let texts = [
+        r#"{"kind":"gameState","players":[{"x":1.5,"name":"ethan"},{"x":-0,"name":"\u00e9\ud83e\udd80"}]}"#,
+        r#"["raw é and 🦀 too", "\"\\\/\b\f\n\r\t", 1e-300, 123456789012, 0.5E+2]"#,
+        r#"  [ [ ] , { } , [ [ [ "deep" ] ] ] ]  "#,
+        "\"lonely\"",
+        "-12",
+    ];
+
+    for text in texts.iter() {
+        let expected: Json = text.parse().unwrap();
+        let mut reader = reader(text);
+        assert_eq!(reader.read_value().unwrap(), Some(expected), "{}", text);
+        assert_eq!(reader.read_value().unwrap(), None);
+    }
+}
+
+#[test]
+fn one_value_after_another() {
+    let log = "{\"kind\":\"join\",\"id\":1}\n{\"kind\":\"leave\",\"id\":1}\n\n7 \"x\"";
+    let mut reader = reader(log);
+
+    assert_eq!(reader.read_value().unwrap(), Some(json!({kind: "join", id: 1})));
+    assert_eq!(reader.read_value().unwrap(), Some(json!({kind: "leave", id: 1})));
+    assert_eq!(reader.read_value().unwrap(), Some(json!(7)));
+    assert_eq!(reader.read_value().unwrap(), Some(json!("x")));
+    assert_eq!(reader.read_value().unwrap(), None);
+}
+
+#[test]
+fn elements_of_a_big_array_one_at_a_time() {
+    let games: Vec<String> = (0..5000).map(|i| json!({id: i, moves: [1, 2, 3]}).to_string()).collect();
+    let text = format!(r#"{{"exported":"today","games":[{}]}}"#, games.join(","));
+
+    let mut reader = JsonReader::new(text.as_bytes());
+    assert_eq!(reader.next().unwrap().unwrap(), Event::BeginObject);
+    assert_eq!(reader.next().unwrap().unwrap(), Event::Key("exported".into()));
+    assert_eq!(reader.read_value().unwrap(), Some(json!("today")));
+    assert_eq!(reader.next().unwrap().unwrap(), Event::Key("games".into()));
+    assert_eq!(reader.next().unwrap().unwrap(), Event::BeginArray);
+
+    let mut count = 0;
+    while let Some(game) = reader.read_value().unwrap() {
+        assert_eq!(game.at::<usize>("/id"), Ok(count));
+        count += 1;
+    }
+
+    assert_eq!(count, 5000);
+    assert_eq!(reader.next().unwrap().unwrap(), Event::EndObject);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn errors_are_where_parse_puts_them() {
+    let texts = [
+        "[1 2]", "{\"a\" 1}", "{1: 2}", "[1,]", "{\"a\":1,}", "[01]", "[-]", "[1.]", "[1e]",
+        "[tru]", "\"\\x\"", "\"\\u12g4\"", "\"\\ud800\"", "\"\\udc00\\ud800\"", "\"a\tb\"", "\"abc",
+        "[1,\n  2,\n  nope]", "{\"é\": x}", "[",
+    ];
+
+    for text in texts.iter() {
+        let e = text.parse::<Json>().unwrap_err();
+        assert_eq!(error(text), (e.line, e.column, e.reason), "{}", text);
+    }
+}
+
+#[test]
+fn memory_stays_bounded() {
+    let mut reader = JsonReader::new(&b"[\"short\", \"much too long\"]"[..]);
+    reader.set_max_string_len(8);
+    let events: Vec<_> = reader.collect();
+
+    assert_eq!(events.len(), 3);
+    match events[2] {
+        Err(ReadError::Invalid(ref e)) => assert_eq!(e.reason, "string is too long"),
+        ref other => panic!("{:?}", other),
+    }
+
+    assert_eq!(error(&"[".repeat(200)), (1, 129, "nested too deeply"));
+}
+
+#[test]
+fn strings_and_numbers_can_be_exactly_the_limit() {
+    let read = |text: &str| {
+        let mut reader = JsonReader::new(text.as_bytes());
+        reader.set_max_string_len(4);
+        reader.collect::<Result<Vec<_>, _>>().map_err(|e| match e {
+            ReadError::Invalid(e) => e.reason,
+            other => panic!("{:?}", other),
+        })
+    };
+
+    assert_eq!(read(r#""abcd""#), Ok(vec![Event::String("abcd".to_string())]));
+    assert_eq!(read(r#""abcde""#), Err("string is too long"));
+    assert_eq!(read(r#""ab\u00e9""#), Ok(vec![Event::String("abé".to_string())]));
+    assert_eq!(read(r#""abc\u00e9""#), Err("string is too long"));
+    assert_eq!(read("1234").map(|events| events.len()), Ok(1));
+    assert_eq!(read("12345"), Err("number is too long"));
+}
+
+#[test]
+fn nothing_after_an_error() {
+    let mut reader = reader("[1, ?, 2]");
+    assert!(reader.by_ref().take(2).all(|event| event.is_ok()));
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+}
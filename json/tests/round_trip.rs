@@ -0,0 +1,91 @@
+// whatever we write, we can read back
+
+use json::{Json, Map};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const CASES: u64 = 2000;
+
+fn random_string(rng: &mut StdRng) -> String {
+    // heavy on the characters that need escaping, or that take two utf16 units
+    let len = rng.gen_range(0, 12);
+    (0..len).map(|_| match rng.gen_range(0, 6) {
+        0 => *['"', '\\', '/', '\n', '\r', '\t', '\x08', '\x0c', '\0', '\x1f', '\x7f'].get(rng.gen_range(0, 11)).unwrap(),
+        1 => *['😀', '𝄞', '🦀', '\u{10ffff}', '\u{fffd}', 'é', '中'].get(rng.gen_range(0, 7)).unwrap(),
+        2 => rng.gen::<char>(),
+        _ => rng.gen_range(b' ', b'~' + 1) as char,
+    }).collect()
+}
+
+fn random_number(rng: &mut StdRng) -> f64 {
+    match rng.gen_range(0, 4) {
+        0 => rng.gen_range(-1000, 1000) as f64,
+        1 => rng.gen::<f64>() * 10f64.powi(rng.gen_range(-20, 20)),
+        2 => -rng.gen::<f64>(),
+        _ => loop {
+            // any finite float at all, including subnormals and the extremes
+            let n = f64::from_bits(rng.gen());
+            if n.is_finite() { break n }
+        },
+    }
+}
+
+fn random_json(rng: &mut StdRng, depth: usize) -> Json {
+    let kinds = if depth == 0 { 4 } else { 6 };
+
+    match rng.gen_range(0, kinds) {
+        0 => Json::Null,
+        1 => Json::Boolean(rng.gen()),
+        2 => Json::Number(random_number(rng).into()),
+        3 => Json::String(random_string(rng)),
+        4 => Json::Array((0..rng.gen_range(0, 5)).map(|_| random_json(rng, depth - 1)).collect()),
+        _ => Json::Object((0..rng.gen_range(0, 5)).map(|_| (random_string(rng), random_json(rng, depth - 1))).collect()),
+    }
+}
+
+#[test]
+fn parse_undoes_display() {
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let json = random_json(&mut rng, 4);
+        let text = json.to_string();
+
+        match text.parse::<Json>() {
+            Ok(parsed) => assert_eq!(parsed, json, "seed {} wrote {}", seed, text),
+            Err(e) => panic!("seed {} wrote {}, which didn't parse: {}", seed, text, e),
+        }
+    }
+}
+
+#[test]
+fn display_is_plain_ascii() {
+    // everything else is escaped, so the output survives any transport
+    for seed in 0..CASES {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let text = random_json(&mut rng, 4).to_string();
+        assert!(text.bytes().all(|b| (b' '..0x7f).contains(&b)), "seed {} wrote {:?}", seed, text);
+    }
+}
+
+#[test]
+fn keys_are_escaped() {
+    // like a username with a quote in it
+    let mut map = Map::new();
+    map.insert("bob\"s \\ lobby".to_string(), Json::Null);
+    let json = Json::Object(map);
+
+    assert_eq!(json.to_string(), r#"{"bob\"s \\ lobby":null}"#);
+    assert_eq!(json.to_string().parse::<Json>().unwrap(), json);
+}
+
+#[test]
+fn characters_outside_the_basic_plane_are_surrogate_pairs() {
+    let json = Json::String("🦀".to_string());
+    assert_eq!(json.to_string(), r#""\uD83E\uDD80""#);
+    assert_eq!(json.to_string().parse::<Json>().unwrap(), json);
+}
+
+#[test]
+fn numbers_json_cant_hold_become_null() {
+    assert_eq!(Json::Array(vec![Json::Number(f64::NAN.into()), Json::Number(f64::INFINITY.into())]).to_string(), "[null,null]");
+}
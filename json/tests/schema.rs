@@ -0,0 +1,103 @@
+// checking messages against a schema before handling them
+
+use json::{json, Json, MessageSchemas, Schema};
+
+fn violations(schema: &Schema, json: &Json) -> Vec<String> {
+    match schema.validate(json) {
+        Ok(()) => Vec::new(),
+        Err(violations) => violations.iter().map(ToString::to_string).collect(),
+    }
+}
+
+fn player() -> Schema {
+    Schema::object()
+        .field("name", Schema::string().min_len(1).max_len(8))
+        .field("seat", Schema::integer().min(0.0).max(3.0))
+        .field("color", Schema::string().one_of(&["red", "green", "blue"]))
+        .optional("team", Schema::integer().nullable())
+}
+
+#[test]
+fn everything_that_matches() {
+    let schema = Schema::array(player()).min_len(1).max_len(4);
+    let players = json!([
+        {name: "ethan", seat: 0, color: "red"},
+        {name: "é🦀", seat: 3, color: "blue", team: null, extra: [1, 2]},
+        {name: "x", seat: 2.0, color: "green", team: 1},
+    ]);
+
+    assert_eq!(schema.validate(&players), Ok(()));
+    assert_eq!(Schema::any().validate(&json!({a: [null]})), Ok(()));
+    assert_eq!(Schema::number().min(-1.5).validate(&json!(-1.5)), Ok(()));
+    assert_eq!(Schema::integer().validate(&"18446744073709551615".parse().unwrap()), Ok(()));
+}
+
+#[test]
+fn every_violation_with_where_it_is() {
+    let schema = Schema::object()
+        .field("players", Schema::array(player()).max_len(2))
+        .field("started", Schema::bool());
+    let json = json!({
+        players: [
+            {name: "", seat: 4, color: "purple"},
+            {name: "much too long", seat: 1.5, color: "red", team: "a"},
+            7,
+        ],
+    });
+
+    assert_eq!(violations(&schema, &json), vec![
+        "must have at most 2 elements at /players",
+        "must have at least 1 characters at /players/0/name",
+        "must be at most 3 at /players/0/seat",
+        "must be one of \"red\", \"green\", \"blue\" at /players/0/color",
+        "must have at most 8 characters at /players/1/name",
+        "expected a whole number at /players/1/seat",
+        "expected a whole number or null at /players/1/team",
+        "expected an object at /players/2",
+        "missing field `started`",
+    ]);
+}
+
+#[test]
+fn keys_are_escaped_in_paths() {
+    let schema = Schema::object().field("a/b", Schema::object().field("c~d", Schema::null()));
+    assert_eq!(violations(&schema, &json!({"a/b": {"c~d": false}})), vec!["expected null at /a~1b/c~0d"]);
+    assert_eq!(violations(&schema, &json!({"a/b": {}})), vec!["missing field `c~d` at /a~1b"]);
+}
+
+#[test]
+fn numbers_out_of_range() {
+    let schema = Schema::number().min(0.0).max(1.0);
+    assert_eq!(violations(&schema, &json!(-0.5)), vec!["must be at least 0"]);
+    assert_eq!(violations(&schema, &"1e400".parse().unwrap()), vec!["must be at most 1"]);
+    assert_eq!(violations(&Schema::integer(), &"1e30".parse().unwrap()), vec!["expected a whole number"]);
+    assert_eq!(violations(&Schema::integer().one_of(&[1, 2]), &json!(3)), vec!["must be one of 1, 2"]);
+}
+
+#[test]
+fn messages_by_kind() {
+    let schemas = MessageSchemas::new()
+        .kind("join", Schema::object().field("gameId", Schema::string()))
+        .kind("play", Schema::object().field("index", Schema::integer().min(0.0)))
+        .kind("leave", Schema::object());
+
+    assert_eq!(schemas.validate(&json!({kind: "join", gameId: "apple-pie"})), Ok("join"));
+    assert_eq!(schemas.validate(&json!({kind: "leave"})), Ok("leave"));
+
+    let errors = |json: Json| -> Vec<String> {
+        schemas.validate(&json).unwrap_err().iter().map(ToString::to_string).collect()
+    };
+
+    assert_eq!(errors(json!({kind: "play", index: (-1)})), vec!["must be at least 0 at /index"]);
+    assert_eq!(errors(json!({kind: "join"})), vec!["missing field `gameId`"]);
+    assert_eq!(errors(json!({kind: "dance"})), vec!["unknown kind `dance` at /kind"]);
+    assert_eq!(errors(json!({kind: 3})), vec!["expected a string at /kind"]);
+    assert_eq!(errors(json!({gameId: "x"})), vec!["missing field `kind`"]);
+    assert_eq!(errors(json!(["join"])), vec!["expected an object"]);
+}
+
+#[test]
+#[should_panic(expected = "min and max are for numbers")]
+fn settings_that_do_not_apply() {
+    Schema::string().min(1.0);
+}
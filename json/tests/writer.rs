@@ -0,0 +1,80 @@
+// writing json as it's produced, without building a Json first
+
+use std::io;
+
+use json::{json, Json, JsonWriter};
+
+fn written(write: impl FnOnce(&mut JsonWriter<&mut Vec<u8>>) -> io::Result<()>) -> String {
+    let mut buffer = Vec::new();
+    let mut writer = JsonWriter::new(&mut buffer);
+    write(&mut writer).unwrap();
+    writer.finish().unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn the_same_as_display() {
+    let text = written(|w| {
+        w.begin_object()?
+            .key("kind")?.string("gameState")?
+            .key("time")?.number(1.5)?
+            .key("players")?.begin_array()?;
+
+        for (x, name) in [(1.0, "ethan"), (-2.0, "say \"hi\"\n")].iter() {
+            w.begin_object()?.key("x")?.number(*x)?.key("name")?.string(name)?.end_object()?;
+        }
+
+        w.end_array()?
+            .key("empty")?.begin_array()?.end_array()?
+            .key("nothing")?.begin_object()?.end_object()?
+            .key("flags")?.begin_array()?.bool(true)?.null()?.number(f64::NAN)?.end_array()?
+            .end_object()?;
+
+        Ok(())
+    });
+
+    let expected = json!({
+        kind: "gameState",
+        time: 1.5,
+        players: [{x: 1, name: "ethan"}, {x: (-2.0), name: "say \"hi\"\n"}],
+        empty: [],
+        nothing: {},
+        flags: [true, null, null],
+    });
+
+    assert_eq!(text, expected.to_string());
+    assert_eq!(text.parse::<Json>().unwrap(), expected);
+}
+
+#[test]
+fn trees_can_go_in_the_middle() {
+    let stars = json!([{x: 1, y: 2}]);
+    let text = written(|w| w.begin_array()?.json(&stars)?.string("\u{1F980}")?.end_array().map(drop));
+    assert_eq!(text, r#"[[{"x":1,"y":2}],"\uD83E\uDD80"]"#);
+}
+
+#[test]
+fn a_lone_value() {
+    assert_eq!(written(|w| w.string("hi").map(drop)), r#""hi""#);
+    assert_eq!(written(|w| w.number(3.0).map(drop)), "3");
+}
+
+#[test]
+#[should_panic(expected = "values in an object need a key first")]
+fn values_in_objects_need_keys() {
+    let _ = JsonWriter::new(io::sink()).begin_object().unwrap().number(1.0);
+}
+
+#[test]
+#[should_panic(expected = "closed something that isn't open")]
+fn closing_the_wrong_thing() {
+    let _ = JsonWriter::new(io::sink()).begin_array().unwrap().end_object();
+}
+
+#[test]
+#[should_panic(expected = "the value isn't finished yet")]
+fn finishing_early() {
+    let mut writer = JsonWriter::new(io::sink());
+    writer.begin_array().unwrap();
+    let _ = writer.finish();
+}
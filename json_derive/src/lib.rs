@@ -0,0 +1,389 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as Tokens;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DataEnum, DeriveInput, Error, Fields, FieldsNamed, Generics, Ident, Lit, Meta, NestedMeta, Type};
+
+// #[derive(Jsonable, FromJson)] for the json crate. structs with named fields become objects, and
+// a struct with one unnamed field is the same json as that field. enums are either all unit
+// variants, which become strings, or tagged with #[json(tag = "kind")], which puts the variant's
+// name in that key next to its fields. attributes:
+//     on the type: rename_all = "camelCase", tag = "kind"
+//     on a variant: rename = "...", rename_all = "..."
+//     on a field: rename = "...", default
+// a field of type Option<T> is left out when it's None, and None when it's missing or null
+
+#[proc_macro_derive(Jsonable, attributes(json))]
+pub fn derive_jsonable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    jsonable(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_json(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+fn jsonable(input: &DeriveInput) -> syn::Result<Tokens> {
+    let attrs = Attrs::parse(&input.attrs, &["rename_all", "tag"])?;
+    let name = &input.ident;
+
+    let body = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let fields = named_fields(fields, attrs.rename_all)?;
+                let values = fields.iter().map(|field| {
+                    let ident = &field.ident;
+                    quote!(self.#ident)
+                });
+                object_into_json(None, &fields, values)
+            },
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => quote!(::json::Jsonable::into_json(self.0)),
+            _ => return Err(Error::new_spanned(&input.ident, "only structs with named fields or a single unnamed field can be json")),
+        },
+        Data::Enum(ref data) => {
+            let variants = variants(data, &attrs)?;
+            let arms = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let json_name = &variant.json_name;
+
+                match attrs.tag {
+                    None => quote!(#name::#ident => ::json::Json::String(#json_name.to_string()),),
+                    Some(ref tag) => {
+                        let bindings = variant.fields.iter().map(|field| &field.ident);
+                        let values = variant.fields.iter().map(|field| {
+                            let ident = &field.ident;
+                            quote!(#ident)
+                        });
+                        let object = object_into_json(Some((tag, json_name)), &variant.fields, values);
+                        quote!(#name::#ident { #(#bindings),* } => { #object },)
+                    },
+                }
+            });
+
+            quote!(match self { #(#arms)* })
+        },
+        Data::Union(_) => return Err(Error::new_spanned(&input.ident, "unions can't be json")),
+    };
+
+    let generics = with_bound(&input.generics, quote!(::json::Jsonable));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::json::Jsonable for #name #type_generics #where_clause {
+            fn into_json(self) -> ::json::Json {
+                #body
+            }
+        }
+    })
+}
+
+fn object_into_json(tag: Option<(&String, &String)>, fields: &[Field], values: impl Iterator<Item=Tokens>) -> Tokens {
+    let tag = tag.map(|(key, value)| quote!(map.insert(#key.to_string(), ::json::Json::String(#value.to_string()));));
+
+    let inserts = fields.iter().zip(values).map(|(field, value)| {
+        let key = &field.key;
+        match field.kind {
+            FieldKind::Optional => quote! {
+                if let Some(value) = #value {
+                    map.insert(#key.to_string(), ::json::Jsonable::into_json(value));
+                }
+            },
+            FieldKind::Required | FieldKind::Default => quote! {
+                map.insert(#key.to_string(), ::json::Jsonable::into_json(#value));
+            },
+        }
+    });
+
+    quote! {
+        let mut map = ::json::Map::new();
+        #tag
+        #(#inserts)*
+        ::json::Json::Object(map)
+    }
+}
+
+fn from_json(input: &DeriveInput) -> syn::Result<Tokens> {
+    let attrs = Attrs::parse(&input.attrs, &["rename_all", "tag"])?;
+    let name = &input.ident;
+
+    let body = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let fields = named_fields(fields, attrs.rename_all)?;
+                let construct = construct(quote!(#name), &fields);
+                quote! {
+                    let object = ::json::derive_support::object(json)?;
+                    Ok(#construct)
+                }
+            },
+            Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => quote!(Ok(#name(::json::FromJson::from_json(json)?))),
+            _ => return Err(Error::new_spanned(&input.ident, "only structs with named fields or a single unnamed field can be json")),
+        },
+        Data::Enum(ref data) => {
+            let variants = variants(data, &attrs)?;
+
+            match attrs.tag {
+                None => {
+                    let arms = variants.iter().map(|variant| {
+                        let ident = &variant.ident;
+                        let json_name = &variant.json_name;
+                        quote!(Some(#json_name) => Ok(#name::#ident),)
+                    });
+
+                    quote! {
+                        match json.get_string() {
+                            #(#arms)*
+                            Some(other) => Err(::json::derive_support::unknown_variant(other, None)),
+                            None => Err(::json::FromJsonError::expected("a string")),
+                        }
+                    }
+                },
+                Some(ref tag) => {
+                    let arms = variants.iter().map(|variant| {
+                        let ident = &variant.ident;
+                        let json_name = &variant.json_name;
+                        let construct = construct(quote!(#name::#ident), &variant.fields);
+                        quote!(#json_name => Ok(#construct),)
+                    });
+
+                    quote! {
+                        let object = ::json::derive_support::object(json)?;
+                        match ::json::derive_support::tag(object, #tag)? {
+                            #(#arms)*
+                            other => Err(::json::derive_support::unknown_variant(other, Some(#tag))),
+                        }
+                    }
+                },
+            }
+        },
+        Data::Union(_) => return Err(Error::new_spanned(&input.ident, "unions can't be json")),
+    };
+
+    let generics = with_bound(&input.generics, quote!(::json::FromJson));
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::json::FromJson for #name #type_generics #where_clause {
+            fn from_json(json: &::json::Json) -> Result<Self, ::json::FromJsonError> {
+                #body
+            }
+        }
+    })
+}
+
+fn construct(path: Tokens, fields: &[Field]) -> Tokens {
+    // `path { field: ..., }` out of `object`
+    let fields = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let key = &field.key;
+        let function = match field.kind {
+            FieldKind::Required => quote!(field),
+            FieldKind::Optional => quote!(optional_field),
+            FieldKind::Default => quote!(default_field),
+        };
+        quote!(#ident: ::json::derive_support::#function(object, #key)?,)
+    });
+
+    quote!(#path { #(#fields)* })
+}
+
+fn with_bound(generics: &Generics, bound: Tokens) -> Generics {
+    // every type parameter has to be json too
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+struct Field {
+    ident: Ident,
+    key: String,
+    kind: FieldKind,
+}
+
+enum FieldKind {
+    Required,
+    Optional, // an Option, which can be left out
+    Default, // #[json(default)], filled in with Default::default() when it's missing
+}
+
+fn named_fields(fields: &FieldsNamed, rename_all: Option<Case>) -> syn::Result<Vec<Field>> {
+    fields.named.iter().map(|field| {
+        let attrs = Attrs::parse(&field.attrs, &["rename", "default"])?;
+        let ident = field.ident.clone().expect("these are named fields");
+
+        let key = match (attrs.rename, rename_all) {
+            (Some(rename), _) => rename,
+            (None, Some(case)) => case.apply(&unraw(&ident)),
+            (None, None) => unraw(&ident),
+        };
+
+        let kind = if attrs.default {
+            FieldKind::Default
+        } else if is_option(&field.ty) {
+            FieldKind::Optional
+        } else {
+            FieldKind::Required
+        };
+
+        Ok(Field { ident, key, kind })
+    }).collect()
+}
+
+fn is_option(ty: &Type) -> bool {
+    match *ty {
+        Type::Path(ref path) => path.qself.is_none() && path.path.segments.last().map_or(false, |segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+struct Variant {
+    ident: Ident,
+    json_name: String,
+    fields: Vec<Field>,
+}
+
+fn variants(data: &DataEnum, attrs: &Attrs) -> syn::Result<Vec<Variant>> {
+    data.variants.iter().map(|variant| {
+        let variant_attrs = Attrs::parse(&variant.attrs, &["rename", "rename_all"])?;
+        let ident = variant.ident.clone();
+
+        let json_name = match (variant_attrs.rename, attrs.rename_all) {
+            (Some(rename), _) => rename,
+            (None, Some(case)) => case.apply(&unraw(&ident)),
+            (None, None) => unraw(&ident),
+        };
+
+        let fields = match (&variant.fields, &attrs.tag) {
+            (Fields::Unit, _) => Vec::new(),
+            (Fields::Named(ref fields), Some(_)) => named_fields(fields, variant_attrs.rename_all)?,
+            (Fields::Named(_), None) => return Err(Error::new_spanned(variant, "enums with fields need #[json(tag = \"...\")]")),
+            (Fields::Unnamed(_), _) => return Err(Error::new_spanned(variant, "variants can only have named fields")),
+        };
+
+        Ok(Variant { ident, json_name, fields })
+    }).collect()
+}
+
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    rename_all: Option<Case>,
+    tag: Option<String>,
+    default: bool,
+}
+
+impl Attrs {
+    fn parse(attributes: &[Attribute], allowed: &[&str]) -> syn::Result<Attrs> {
+        let mut attrs = Attrs::default();
+
+        for attribute in attributes.iter().filter(|attribute| attribute.path.is_ident("json")) {
+            let list = match attribute.parse_meta()? {
+                Meta::List(list) => list,
+                other => return Err(Error::new_spanned(other, "expected #[json(...)]")),
+            };
+
+            for nested in list.nested {
+                let meta = match nested {
+                    NestedMeta::Meta(meta) => meta,
+                    other => return Err(Error::new_spanned(other, "unknown json attribute")),
+                };
+
+                let name = meta.path().get_ident().map(Ident::to_string).unwrap_or_default();
+                if !allowed.contains(&name.as_str()) {
+                    return Err(Error::new_spanned(meta.path(), format!("`{}` isn't allowed here", name)));
+                }
+
+                match meta {
+                    Meta::NameValue(ref pair) if name == "rename" => attrs.rename = Some(string(&pair.lit)?),
+                    Meta::NameValue(ref pair) if name == "tag" => attrs.tag = Some(string(&pair.lit)?),
+                    Meta::NameValue(ref pair) if name == "rename_all" => {
+                        let case = string(&pair.lit)?;
+                        attrs.rename_all = Some(Case::from_str(&case).ok_or_else(|| {
+                            Error::new_spanned(&pair.lit, "expected \"camelCase\", \"PascalCase\", \"snake_case\", \"SCREAMING_SNAKE_CASE\" or \"kebab-case\"")
+                        })?);
+                    },
+                    Meta::Path(_) if name == "default" => attrs.default = true,
+                    other => return Err(Error::new_spanned(other, format!("wrong form for `{}`", name))),
+                }
+            }
+        }
+
+        Ok(attrs)
+    }
+}
+
+fn string(lit: &Lit) -> syn::Result<String> {
+    match *lit {
+        Lit::Str(ref s) => Ok(s.value()),
+        _ => Err(Error::new_spanned(lit, "expected a string")),
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Case {
+    Camel,
+    Pascal,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+}
+
+impl Case {
+    fn from_str(case: &str) -> Option<Case> {
+        Some(match case {
+            "camelCase" => Case::Camel,
+            "PascalCase" => Case::Pascal,
+            "snake_case" => Case::Snake,
+            "SCREAMING_SNAKE_CASE" => Case::ScreamingSnake,
+            "kebab-case" => Case::Kebab,
+            _ => return None,
+        })
+    }
+
+    fn apply(self, ident: &str) -> String {
+        // works on both snake_case field names and PascalCase variant names
+        let words = words(ident);
+
+        match self {
+            Case::Camel => words.iter().enumerate().map(|(i, word)| if i == 0 { word.clone() } else { capitalize(word) }).collect(),
+            Case::Pascal => words.iter().map(|word| capitalize(word)).collect(),
+            Case::Snake => words.join("_"),
+            Case::ScreamingSnake => words.join("_").to_uppercase(),
+            Case::Kebab => words.join("-"),
+        }
+    }
+}
+
+fn unraw(ident: &Ident) -> String {
+    // `r#type` is named "type" in json
+    ident.to_string().trim_start_matches("r#").to_string()
+}
+
+fn words(ident: &str) -> Vec<String> {
+    // lowercase words, split on underscores and where a capital letter starts a new one
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for c in ident.chars() {
+        if (c == '_' || c.is_uppercase()) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c != '_' { word.extend(c.to_lowercase()) }
+    }
+    if !word.is_empty() { words.push(word) }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
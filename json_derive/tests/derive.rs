@@ -0,0 +1,144 @@
+// structs and enums going to json and back through the derives
+
+use json::{FromJson, Json, Jsonable};
+use json_derive::{FromJson, Jsonable};
+
+#[derive(Jsonable, FromJson, Clone, PartialEq, Debug)]
+#[json(rename_all = "camelCase")]
+struct Player {
+    player_name: String,
+    x: f64,
+    y: f64,
+    #[json(rename = "hp")]
+    health: i32,
+    #[json(default)]
+    kills: usize,
+    team: Option<String>,
+}
+
+#[derive(Jsonable, FromJson, PartialEq, Debug)]
+struct Game {
+    players: Vec<Player>,
+    state: GameState,
+    round: Round,
+}
+
+#[derive(Jsonable, FromJson, Clone, Copy, PartialEq, Debug)]
+#[json(rename_all = "kebab-case")]
+enum GameState {
+    Waiting,
+    InProgress,
+    #[json(rename = "done")]
+    Finished,
+}
+
+#[derive(Jsonable, FromJson, Clone, Copy, PartialEq, Debug)]
+struct Round(usize);
+
+#[derive(Jsonable, FromJson, PartialEq, Debug)]
+#[json(tag = "kind", rename_all = "camelCase")]
+enum Message {
+    Join { name: String },
+    #[json(rename_all = "camelCase")]
+    Move { player_id: usize, x: f64, y: f64 },
+    Chat { text: String, to: Option<String> },
+    Leave,
+}
+
+#[derive(Jsonable, FromJson, PartialEq, Debug)]
+struct Card {
+    r#type: String,
+    r#match: Option<u8>,
+}
+
+#[derive(Jsonable, FromJson, PartialEq, Debug)]
+#[json(rename_all = "SCREAMING_SNAKE_CASE")]
+struct Shouted {
+    r#type: String,
+}
+
+fn player() -> Player {
+    Player { player_name: "ethan".into(), x: 1.5, y: -2.0, health: 100, kills: 3, team: None }
+}
+
+#[test]
+fn structs_become_objects_with_renamed_keys() {
+    assert_eq!(player().into_json().to_string(), r#"{"playerName":"ethan","x":1.5,"y":-2,"hp":100,"kills":3}"#);
+}
+
+#[test]
+fn structs_come_back() {
+    let mut player = player();
+    player.team = Some("red".into());
+
+    assert_eq!(Player::from_json(&player.clone().into_json()), Ok(player));
+}
+
+#[test]
+fn missing_defaults_and_options_are_filled_in() {
+    let json: Json = r#"{"playerName":"ethan","x":1.5,"y":-2,"hp":100,"team":null}"#.parse().unwrap();
+    assert_eq!(Player::from_json(&json), Ok(Player { kills: 0, ..player() }));
+
+    let json: Json = r#"{"playerName":"ethan","x":1.5,"y":-2,"hp":100}"#.parse().unwrap();
+    assert_eq!(Player::from_json(&json), Ok(Player { kills: 0, ..player() }));
+}
+
+#[test]
+fn unit_enums_are_strings_and_newtypes_are_their_field() {
+    let game = Game { players: vec![player()], state: GameState::InProgress, round: Round(2) };
+    let json = game.into_json();
+
+    assert_eq!(json.get_object().unwrap().get("state"), Some(&Json::String("in-progress".into())));
+    assert_eq!(json.get_object().unwrap().get("round"), Some(&Json::Number(2.0.into())));
+    assert_eq!(GameState::from_json(&Json::String("done".into())), Ok(GameState::Finished));
+    assert_eq!(Game::from_json(&json).map(|game| game.round), Ok(Round(2)));
+}
+
+#[test]
+fn tagged_enums_put_the_variant_in_the_tag() {
+    let message = Message::Move { player_id: 4, x: 1.0, y: 2.0 };
+    assert_eq!(message.into_json().to_string(), r#"{"kind":"move","playerId":4,"x":1,"y":2}"#);
+    assert_eq!(Message::Leave.into_json().to_string(), r#"{"kind":"leave"}"#);
+
+    for text in [r#"{"kind":"join","name":"ethan"}"#, r#"{"text":"hi","kind":"chat"}"#, r#"{"kind":"leave"}"#].iter() {
+        let message = Message::from_json(&text.parse().unwrap()).unwrap();
+        assert!(Message::from_json(&message.into_json()).is_ok());
+    }
+
+    let json = r#"{"kind":"chat","text":"hi","to":"bob"}"#.parse().unwrap();
+    assert_eq!(Message::from_json(&json), Ok(Message::Chat { text: "hi".into(), to: Some("bob".into()) }));
+}
+
+#[test]
+fn raw_identifiers_lose_their_prefix() {
+    let card = Card { r#type: "spade".into(), r#match: Some(2) };
+    let json = card.into_json();
+    assert_eq!(json.to_string(), r#"{"type":"spade","match":2}"#);
+    assert_eq!(Card::from_json(&json), Ok(Card { r#type: "spade".into(), r#match: Some(2) }));
+
+    let shouted = Shouted { r#type: "spade".into() };
+    assert_eq!(shouted.into_json().to_string(), r#"{"TYPE":"spade"}"#);
+    assert_eq!(Shouted::from_json(&r#"{"TYPE":"club"}"#.parse().unwrap()), Ok(Shouted { r#type: "club".into() }));
+}
+
+#[test]
+fn errors_say_where_they_are() {
+    let json = r#"{"players":[{"playerName":"a","x":1,"y":2,"hp":3},{"playerName":"b","x":"1","y":2,"hp":3}],"state":"waiting","round":1}"#;
+    let error = Game::from_json(&json.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "expected a number at /players/1/x");
+
+    let error = Player::from_json(&r#"{"playerName":"a","x":1,"hp":3}"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "missing field `y`");
+
+    let error = Player::from_json(&r#"{"playerName":"a","x":1,"y":2,"hp":3.5}"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "expected a whole number that fits in i32 at /hp");
+
+    let error = Message::from_json(&r#"{"kind":"dance"}"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "unknown variant `dance` at /kind");
+
+    let error = Message::from_json(&r#"{"name":"ethan"}"#.parse().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "missing field `kind`");
+
+    let error = GameState::from_json(&Json::Number(1.0.into())).unwrap_err();
+    assert_eq!(error.to_string(), "expected a string");
+}
@@ -33,13 +33,21 @@ document.addEventListener("pointerlockchange", event => {
 
 
 var players = [];
+var myId = null;
 var socket = new WebSocket("ws://ethan.ws/arena");
 
 socket.onmessage = (msg) => {
     var data = JSON.parse(msg.data);
 
+    // the first message says which player we are, and every one after is everybody
+    if (!Array.isArray(data)) {
+        myId = data.id;
+        return;
+    }
+
     players = [];
     for (var player of data) {
+        if (player.id === myId) continue;
         players.push(new Circle(player.x, player.y, PLAYER_RADIUS, player.color));
     }
 };
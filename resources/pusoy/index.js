@@ -77,6 +77,14 @@ socket.onmessage = (event) => {
 
     } else if (data.kind == "hostAbandoned") {
 
+    } else if (data.kind == "error") {
+        // the server didn't like something we sent, like an empty username
+        console.log("server rejected our message: " + data.message);
+
+        if (data.violations && data.violations.some(v => v.endsWith("/username"))) {
+            document.getElementById("username").style.borderColor = "red";
+        }
+
     } else {
         console.log("unknown message kind "+data.kind);
     }
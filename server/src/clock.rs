@@ -0,0 +1,78 @@
+use std::cell::Cell;
+use std::sync::{Condvar, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// apps should ask this module what time it is instead of std, so that a TestHarness can stop the
+// clock and move it forward on its own schedule. outside of a harness this is just std's clock
+
+thread_local! {
+    static FROZEN_AT: Cell<Option<(Instant, SystemTime)>> = Cell::new(None);
+    static FREEZES: Cell<usize> = Cell::new(0);
+}
+
+pub fn now() -> Instant {
+    FROZEN_AT.with(|frozen| frozen.get())
+        .map(|(instant, _)| instant)
+        .unwrap_or_else(Instant::now)
+}
+
+pub fn system_now() -> SystemTime {
+    FROZEN_AT.with(|frozen| frozen.get())
+        .map(|(_, system)| system)
+        .unwrap_or_else(SystemTime::now)
+}
+
+pub fn unix_time_millis() -> u64 {
+    system_now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock read failed")
+        .as_millis() as u64
+}
+
+pub fn sleep(duration: Duration) {
+    // a frozen clock just moves forward instead
+    if FROZEN_AT.with(|frozen| frozen.get()).is_some() {
+        advance(duration);
+    } else {
+        thread::sleep(duration);
+    }
+}
+
+pub fn wait_while<'a, T>(condvar: &Condvar, guard: MutexGuard<'a, T>, timeout: Duration, mut condition: impl FnMut(&mut T) -> bool) -> MutexGuard<'a, T> {
+    // Condvar::wait_timeout_while on this clock. nothing can notify a thread that's frozen, so
+    // if the condition holds, the whole timeout passes
+    if FROZEN_AT.with(|frozen| frozen.get()).is_some() {
+        let mut guard = guard;
+        if condition(&mut guard) {
+            advance(timeout);
+        }
+        guard
+    } else {
+        condvar.wait_timeout_while(guard, timeout, condition).unwrap().0
+    }
+}
+
+pub fn freeze() {
+    // for tests. the clock is per thread, so this doesn't touch anything running elsewhere.
+    // freezes nest, so a harness made inside another one leaves its time alone
+    let freezes = FREEZES.with(|freezes| freezes.replace(freezes.get() + 1));
+    if freezes == 0 {
+        FROZEN_AT.with(|frozen| frozen.set(Some((Instant::now(), SystemTime::now()))));
+    }
+}
+
+pub fn advance(duration: Duration) {
+    FROZEN_AT.with(|frozen| {
+        let (instant, system) = frozen.get().expect("the clock can only be moved while frozen");
+        frozen.set(Some((instant + duration, system + duration)));
+    });
+}
+
+pub fn unfreeze() {
+    // the clock starts again once every freeze has been undone
+    let freezes = FREEZES.with(|freezes| freezes.replace(freezes.get().saturating_sub(1)));
+    if freezes <= 1 {
+        FROZEN_AT.with(|frozen| frozen.set(None));
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use web_socket::{Limits, Deflate};
+
+use crate::server::GlobalState;
+use crate::rate_limit::RateLimit;
+
+// everything the server knows about one web socket location
+pub struct Endpoint {
+    pub(crate) state: Arc<Mutex<dyn GlobalState>>,
+    pub(crate) rate_limit: Option<RateLimit>,
+    pub(crate) limits: Limits,
+    pub(crate) deflate: Option<Deflate>,
+    pub(crate) heartbeat: Option<(Duration, Duration)>,
+    subprotocols: Vec<String>,
+    allowed_origins: Option<Vec<String>>,
+}
+
+impl Endpoint {
+    pub fn new(state: Arc<Mutex<dyn GlobalState>>) -> Endpoint {
+        Endpoint { state, rate_limit: None, limits: Limits::default(), deflate: None, heartbeat: None, subprotocols: Vec::new(), allowed_origins: None }
+    }
+
+    pub fn rate_limit(&mut self, rate_limit: RateLimit) -> &mut Endpoint {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    pub fn limits(&mut self, limits: Limits) -> &mut Endpoint {
+        // how big a frame or message from a client can be before we hang up on them
+        self.limits = limits;
+        self
+    }
+
+    pub fn deflate(&mut self, deflate: Deflate) -> &mut Endpoint {
+        // compress messages with clients that support permessage-deflate
+        self.deflate = Some(deflate);
+        self
+    }
+
+    pub fn heartbeat(&mut self, interval: Duration, timeout: Duration) -> &mut Endpoint {
+        // ping every peer once per interval, and disconnect the ones that don't pong back in time.
+        // the round trips are reported to GlobalState::on_latency
+        assert!(timeout < interval, "the heartbeat timeout has to be shorter than the interval");
+        self.heartbeat = Some((interval, timeout));
+        self
+    }
+
+    pub fn subprotocols(&mut self, subprotocols: Vec<String>) -> &mut Endpoint {
+        // something like "tanks.json.v2", most preferred first. whichever one we pick is passed to
+        // GlobalState::new_peer
+        self.subprotocols = subprotocols;
+        self
+    }
+
+    pub fn choose_subprotocol(&self, offered: Option<&str>) -> Option<String> {
+        let offered: Vec<&str> = offered?.split(',').map(str::trim).collect();
+        self.subprotocols.iter().find(|s| offered.contains(&s.as_str())).cloned()
+    }
+
+    pub fn allowed_origins(&mut self, origins: Vec<String>) -> &mut Endpoint {
+        // something like "https://ethan.ws", compared against the browser's Origin header
+        self.allowed_origins = Some(origins);
+        self
+    }
+
+    pub(crate) fn origin_allowed(&self, origin: Option<&str>) -> bool {
+        match (&self.allowed_origins, origin) {
+            (None, _) => true,
+            // browsers always send an Origin, so a missing one can't be a hijacked page
+            (Some(_), None) => true,
+            (Some(allowed), Some(origin)) => allowed.iter().any(|a| a.eq_ignore_ascii_case(origin.trim())),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use http::{HttpRequest, RequestType, SetCookie};
+use web_socket::{Deflate, accept_key, is_valid_key};
+
+use crate::endpoint::Endpoint;
+use crate::http_handler::ERROR_404_RESPONSE;
+
+// https://tools.ietf.org/html/rfc6455#section-4.2.1
+
+const WEB_SOCKET_VERSION: &str = "13";
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HandshakeError {
+    NotFound,
+    BadRequest(&'static str),
+    UnsupportedVersion,
+    ForbiddenOrigin,
+}
+
+impl HandshakeError {
+    pub fn response(&self) -> Vec<u8> {
+        match *self {
+            HandshakeError::NotFound => ERROR_404_RESPONSE.to_vec(),
+            HandshakeError::BadRequest(reason) => plain_response("400 Bad Request", "", reason),
+            HandshakeError::UnsupportedVersion => plain_response("426 Upgrade Required", "Sec-WebSocket-Version: 13\r\n", "unsupported web socket version"),
+            HandshakeError::ForbiddenOrigin => plain_response("403 Forbidden", "", "origin not allowed"),
+        }
+    }
+}
+
+// a handshake we said yes to
+pub struct Accepted<'a> {
+    pub endpoint: &'a Endpoint,
+    accept_key: String,
+    extensions: Option<String>, // our Sec-WebSocket-Extensions response
+    pub deflate: Option<Deflate>,
+    pub subprotocol: Option<String>,
+}
+
+pub fn is_upgrade_request(request: &HttpRequest) -> bool {
+    // anything that looks like it wants a web socket gets validated as one, so that a
+    // half-correct handshake gets an error instead of a file. upgrades to other protocols, like
+    // h2c, are left alone and served over plain http
+    has_token(request.get_header_value("Upgrade"), "websocket") || request.get_header_value("Sec-WebSocket-Key").is_some()
+}
+
+pub fn accept<'a>(request: &HttpRequest, endpoint: Option<&'a Endpoint>) -> Result<Accepted<'a>, HandshakeError> {
+    let endpoint = endpoint.ok_or(HandshakeError::NotFound)?;
+
+    if request.request_type() != RequestType::Get {
+        return Err(HandshakeError::BadRequest("web socket handshakes must use GET"));
+    }
+
+    if request.get_header_value("Host").is_none() {
+        return Err(HandshakeError::BadRequest("missing Host header"));
+    }
+
+    if !has_token(request.get_header_value("Upgrade"), "websocket") {
+        return Err(HandshakeError::BadRequest("Upgrade header must include websocket"));
+    }
+
+    if !has_token(request.get_header_value("Connection"), "upgrade") {
+        return Err(HandshakeError::BadRequest("Connection header must include Upgrade"));
+    }
+
+    if request.get_header_value("Sec-WebSocket-Version").map(str::trim) != Some(WEB_SOCKET_VERSION) {
+        return Err(HandshakeError::UnsupportedVersion);
+    }
+
+    let key = request.get_header_value("Sec-WebSocket-Key")
+        .map(str::trim)
+        .ok_or(HandshakeError::BadRequest("missing Sec-WebSocket-Key header"))?;
+
+    if !is_valid_key(key) {
+        return Err(HandshakeError::BadRequest("Sec-WebSocket-Key must be 16 bytes of base64"));
+    }
+
+    if !endpoint.origin_allowed(request.get_header_value("Origin")) {
+        return Err(HandshakeError::ForbiddenOrigin);
+    }
+
+    // only compress if both sides want to
+    let negotiated = endpoint.deflate.as_ref().and_then(|deflate| {
+        deflate.negotiate(request.get_header_value("Sec-WebSocket-Extensions")?)
+    });
+    let (deflate, extensions) = match negotiated {
+        Some((deflate, extensions)) => (Some(deflate), Some(extensions)),
+        None => (None, None),
+    };
+
+    // if we don't support any of theirs, we answer without one and let the client decide whether
+    // to carry on
+    let subprotocol = endpoint.choose_subprotocol(request.get_header_value("Sec-WebSocket-Protocol"));
+
+    Ok(Accepted { endpoint, accept_key: accept_key(key), extensions, deflate, subprotocol })
+}
+
+pub fn switching_protocols_response(accepted: &Accepted, set_cookie: Option<&SetCookie>) -> String {
+    let mut extra_headers = String::new();
+    if let Some(ref subprotocol) = accepted.subprotocol {
+        extra_headers.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", subprotocol));
+    }
+    if let Some(ref extensions) = accepted.extensions {
+        extra_headers.push_str(&format!("Sec-WebSocket-Extensions: {}\r\n", extensions));
+    }
+    if let Some(cookie) = set_cookie {
+        extra_headers.push_str(&format!("Set-Cookie: {}\r\n", cookie));
+    }
+
+    format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n{}\r\n", accepted.accept_key, extra_headers)
+}
+
+fn has_token(header_value: Option<&str>, token: &str) -> bool {
+    // headers like `Connection: keep-alive, Upgrade` are comma separated lists
+    header_value
+        .map(|value| value.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+        .unwrap_or(false)
+}
+
+fn plain_response(status: &str, extra_headers: &str, body: &str) -> Vec<u8> {
+    format!("HTTP/1.1 {}\r\n{}Content-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}", status, extra_headers, body.len(), body).into_bytes()
+}
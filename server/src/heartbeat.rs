@@ -0,0 +1,147 @@
+use std::net::{TcpStream, Shutdown};
+use std::sync::{Arc, Mutex, Condvar};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use web_socket::{WebSocketWriter, SharedStream};
+
+use crate::clock;
+
+// pings a peer every so often, and hangs up if they stop answering. a phone that goes to sleep
+// doesn't close its connection, so without this it would never show up as disconnected. this part
+// only keeps time, so the server's thread and a TestHarness can both drive it
+pub(crate) struct Heartbeat {
+    interval: Duration,
+    timeout: Duration,
+    next: Next,
+    due: Instant,
+    pings_sent: u64,
+    waiting_on: Option<(u64, Instant)>, // the ping we haven't heard back about, and when we sent it
+}
+
+#[derive(Copy, Clone)]
+enum Next {
+    Ping,
+    Check,
+}
+
+pub(crate) enum Beat {
+    Ping([u8; 8]),
+    TimedOut,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration, timeout: Duration) -> Heartbeat {
+        Heartbeat {
+            interval,
+            timeout,
+            next: Next::Ping,
+            due: clock::now() + (interval - timeout),
+            pings_sent: 0,
+            waiting_on: None,
+        }
+    }
+
+    pub fn due(&self) -> Instant {
+        // when `beat` next has something to do
+        self.due
+    }
+
+    pub fn beat(&mut self) -> Option<Beat> {
+        let now = clock::now();
+        if now < self.due { return None }
+
+        match self.next {
+            Next::Ping => {
+                let ping_number = self.pings_sent;
+                self.pings_sent += 1;
+                self.waiting_on = Some((ping_number, now));
+                self.next = Next::Check;
+                self.due = now + self.timeout;
+                Some(Beat::Ping(ping_number.to_be_bytes()))
+            },
+            Next::Check if self.waiting_on.is_some() => Some(Beat::TimedOut),
+            Next::Check => {
+                self.next = Next::Ping;
+                self.due = now + (self.interval - self.timeout);
+                None
+            },
+        }
+    }
+
+    pub fn on_pong(&mut self, payload: &[u8]) -> Option<Duration> {
+        // the round trip time, if this pong answers our ping
+        match self.waiting_on {
+            Some((ping_number, sent_at)) if payload == ping_number.to_be_bytes() => {
+                self.waiting_on = None;
+                Some(clock::now() - sent_at)
+            },
+            _ => None,
+        }
+    }
+}
+
+// a heartbeat on its own thread, for a peer with a real socket
+pub(crate) struct HeartbeatThread {
+    shared: Mutex<Shared>,
+    wake: Condvar,
+}
+
+struct Shared {
+    heartbeat: Heartbeat,
+    stopped: bool,
+    timed_out: bool,
+}
+
+impl HeartbeatThread {
+    pub fn start(heartbeat: Heartbeat, mut writer: WebSocketWriter, tcp_stream: SharedStream<TcpStream>, thread_name: String) -> Arc<HeartbeatThread> {
+        let thread = Arc::new(HeartbeatThread {
+            shared: Mutex::new(Shared { heartbeat, stopped: false, timed_out: false }),
+            wake: Condvar::new(),
+        });
+
+        let thread_clone = Arc::clone(&thread);
+        thread::Builder::new().name(thread_name).spawn(move || {
+            let thread = thread_clone;
+
+            loop {
+                let shared = thread.shared.lock().unwrap();
+                let until_due = shared.heartbeat.due().saturating_duration_since(clock::now());
+                let mut shared = clock::wait_while(&thread.wake, shared, until_due, |shared| !shared.stopped);
+                if shared.stopped { break }
+
+                let beat = shared.heartbeat.beat();
+                if let Some(Beat::TimedOut) = beat {
+                    shared.timed_out = true;
+                }
+                drop(shared);
+
+                match beat {
+                    Some(Beat::Ping(payload)) => if writer.ping(&payload).is_err() { break },
+                    Some(Beat::TimedOut) => {
+                        // the listener's read fails once the socket is shut down, which ends the connection
+                        let _ = tcp_stream.get_ref().shutdown(Shutdown::Both);
+                        break;
+                    },
+                    None => {},
+                }
+            }
+        }).unwrap();
+
+        thread
+    }
+
+    pub fn on_pong(&self, payload: &[u8]) -> Option<Duration> {
+        self.shared.lock().unwrap().heartbeat.on_pong(payload)
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.shared.lock().unwrap().timed_out
+    }
+
+    pub fn stop(&self) {
+        // wakes the thread right away, so it lets go of the socket
+        self.shared.lock().unwrap().stopped = true;
+        self.wake.notify_one();
+    }
+}
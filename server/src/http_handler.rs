@@ -1,4 +1,4 @@
-use http::HttpRequest;
+use http::{HttpRequest, SetCookie};
 
 use std::path::{PathBuf};
 use std::io::{self, Write, ErrorKind};
@@ -6,13 +6,20 @@ use std::fs::{self};
 use std::net::TcpStream;
 
 // hardcoded error messages
-const ERROR_404_RESPONSE: &'static [u8] = b"HTTP/1.1 404 Page Not Found\r\n\r\n<!DOCTYPE html><html lang='en-US'><head><meta charset='UTF-8'><title>ethan.ws</title></head><body><h1>Error 404 - Page Not Found</h1></body></html>";
+pub const ERROR_404_RESPONSE: &'static [u8] = b"HTTP/1.1 404 Page Not Found\r\n\r\n<!DOCTYPE html><html lang='en-US'><head><meta charset='UTF-8'><title>ethan.ws</title></head><body><h1>Error 404 - Page Not Found</h1></body></html>";
 const ERROR_500_RESPONSE: &'static [u8] = b"HTTP/1.1 500 Internal Server Error\r\n\r\n<!DOCTYPE html><html lang='en-US'><head><meta charset='UTF-8'><title>ethan.ws</title></head><body><h1>Error 500 - Internal Server Error</h1></body></html>";
 
-pub fn send_resource(request: &HttpRequest, writer: &mut TcpStream, resources_root: &PathBuf) -> io::Result<()> {
+pub fn send_resource(request: &HttpRequest, writer: &mut TcpStream, resources_root: &PathBuf, set_cookie: impl FnOnce() -> Option<SetCookie>) -> io::Result<()> {
+    // set_cookie is only asked for when the resource is a page
     match get_data(request.resource_location(), resources_root) {
-        Ok(data) => {
-            writer.write_all(b"HTTP/1.1 200 OK\r\n\r\n")?;
+        Ok((path, data)) => {
+            writer.write_all(b"HTTP/1.1 200 OK\r\n")?;
+            let is_page = path.extension().map_or(false, |extension| extension == "html");
+            let cookie = if is_page { set_cookie() } else { None };
+            if let Some(cookie) = cookie {
+                write!(writer, "Set-Cookie: {}\r\n", cookie)?;
+            }
+            writer.write_all(b"\r\n")?;
             writer.write_all(&data)?;
         },
         Err(e) if e.kind() == ErrorKind::PermissionDenied || e.kind() == ErrorKind::NotFound =>
@@ -26,7 +33,7 @@ pub fn send_resource(request: &HttpRequest, writer: &mut TcpStream, resources_ro
 }
 
 
-fn get_data(request: &str, resources_root: &PathBuf) -> io::Result<Vec<u8>> {
+fn get_data(request: &str, resources_root: &PathBuf) -> io::Result<(PathBuf, Vec<u8>)> {
     let request =
         if request.starts_with("/") {
             &request[1..]
@@ -44,7 +51,8 @@ fn get_data(request: &str, resources_root: &PathBuf) -> io::Result<Vec<u8>> {
         return Err(io::ErrorKind::PermissionDenied.into());
     }
 
-    fs::read(path)
+    let data = fs::read(&path)?;
+    Ok((path, data))
 }
 
 fn is_to_resources_folder(path: &PathBuf, resources_root: &PathBuf) -> bool {
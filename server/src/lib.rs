@@ -1,7 +1,23 @@
 #![feature(try_trait, vec_remove_item, is_sorted)]
 
-mod util;
 mod http_handler;
 mod server;
+mod endpoint;
+mod rate_limit;
+pub mod handshake;
+mod session;
+mod heartbeat;
+pub mod message;
+pub mod clock;
+pub mod testing;
 
 pub use server::{Server, PeerId, Disconnect, GlobalState};
+pub use endpoint::Endpoint;
+pub use session::{SessionId, SessionStore};
+pub use testing::TestHarness;
+pub use message::{Request, Response, MessageField, DecodeError, decode_or_reply, validate_or_reply, send};
+pub use rate_limit::{RateLimit, RateLimitAction, RateLimitCounters, RateLimiter, Admit};
+
+// so request! and response! work in crates that don't depend on json themselves
+#[doc(hidden)]
+pub use json;
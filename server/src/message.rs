@@ -0,0 +1,248 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use json::{FromJson, FromJsonError, Json, Jsonable, MessageSchemas, ParseError};
+use web_socket::{WebSocketMessage, WebSocketWriter};
+
+use crate::server::Disconnect;
+
+// a message a client sends us, declared with `server::request!`
+pub trait Request: Sized {
+    fn decode(json: &Json) -> Result<Self, DecodeError>;
+
+    fn decode_message(message: &WebSocketMessage) -> Result<Self, DecodeError> {
+        let text = message.get_text().ok_or(DecodeError::NotText)?;
+        let json = Json::from_str(text).map_err(DecodeError::InvalidJson)?;
+        Self::decode(&json)
+    }
+}
+
+// a message we send a client, declared with `server::response!`
+pub trait Response {
+    fn encode(self) -> Json;
+}
+
+pub fn decode_or_reply<R: Request>(message: &WebSocketMessage, writer: &mut WebSocketWriter) -> Result<R, DecodeError> {
+    // tell the client what was wrong with their message, instead of leaving them guessing
+    R::decode_message(message).map_err(|e| {
+        let _ = writer.write_string(&e.to_json().to_string());
+        e
+    })
+}
+
+pub fn validate_or_reply(message: &WebSocketMessage, schemas: &MessageSchemas, writer: &mut WebSocketWriter) -> Result<Json, DecodeError> {
+    // for apps that handle the json themselves. checks the message against the schema for its kind
+    // before the handler sees it, and tells the client everything that was wrong
+    validate(message, schemas).map_err(|e| {
+        let _ = writer.write_string(&e.to_json().to_string());
+        e
+    })
+}
+
+pub fn validate(message: &WebSocketMessage, schemas: &MessageSchemas) -> Result<Json, DecodeError> {
+    let text = message.get_text().ok_or(DecodeError::NotText)?;
+    let json = Json::from_str(text).map_err(DecodeError::InvalidJson)?;
+    schemas.validate(&json).map_err(DecodeError::Invalid)?;
+    Ok(json)
+}
+
+pub fn send<R: Response>(writer: &mut WebSocketWriter, response: R) -> io::Result<()> {
+    writer.write_string(&response.encode().to_string())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DecodeError {
+    NotText,
+    InvalidJson(ParseError),
+    NotAnObject,
+    MissingKind,
+    UnknownKind(String),
+    MissingField { kind: &'static str, field: &'static str },
+    WrongType { kind: &'static str, field: &'static str, expected: &'static str },
+    Invalid(Vec<FromJsonError>),
+}
+
+impl DecodeError {
+    pub fn to_json(&self) -> Json {
+        match *self {
+            DecodeError::Invalid(ref violations) => object("error", vec![
+                ("message", Json::String(self.to_string())),
+                ("violations", Json::Array(violations.iter().map(|v| Json::String(v.to_string())).collect())),
+            ]),
+            _ => object("error", vec![("message", Json::String(self.to_string()))]),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::NotText => write!(f, "expected a text message"),
+            DecodeError::InvalidJson(ref e) => write!(f, "message is not valid json: {}", e),
+            DecodeError::NotAnObject => write!(f, "message must be a json object"),
+            DecodeError::MissingKind => write!(f, "message is missing a string `kind`"),
+            DecodeError::UnknownKind(ref kind) => write!(f, "unknown message kind `{}`", kind),
+            DecodeError::MissingField { kind, field } => write!(f, "`{}` message is missing `{}`", kind, field),
+            DecodeError::WrongType { kind, field, expected } => write!(f, "`{}` in `{}` message must be {}", field, kind, expected),
+            DecodeError::Invalid(ref violations) => {
+                let violations: Vec<String> = violations.iter().map(ToString::to_string).collect();
+                write!(f, "message is invalid: {}", violations.join("; "))
+            },
+        }
+    }
+}
+
+impl From<DecodeError> for Disconnect {
+    fn from(_: DecodeError) -> Disconnect { Disconnect }
+}
+
+// anything that can be a field of a request
+pub trait MessageField: Sized {
+    const EXPECTED: &'static str;
+
+    fn from_json(json: &Json) -> Option<Self>;
+
+    fn missing() -> Option<Self> { None }
+}
+
+impl MessageField for Json {
+    const EXPECTED: &'static str = "any json value";
+    fn from_json(json: &Json) -> Option<Json> { Some(json.clone()) }
+}
+
+impl MessageField for bool {
+    const EXPECTED: &'static str = "a boolean";
+    fn from_json(json: &Json) -> Option<bool> { json.get_bool() }
+}
+
+impl MessageField for f64 {
+    const EXPECTED: &'static str = "a number";
+    fn from_json(json: &Json) -> Option<f64> { json.get_number() }
+}
+
+impl MessageField for String {
+    const EXPECTED: &'static str = "a string";
+    fn from_json(json: &Json) -> Option<String> { json.get_string().map(String::from) }
+}
+
+macro_rules! whole_number_field {
+    ($($t:ty),*) => {
+        $(
+        impl MessageField for $t {
+            const EXPECTED: &'static str = "a whole number in range";
+
+            fn from_json(json: &Json) -> Option<$t> {
+                // exact, so ids past 2^53 don't get rounded
+                <$t as FromJson>::from_json(json).ok()
+            }
+        }
+        )*
+    };
+}
+
+whole_number_field!(u8, u16, u32, u64, usize, i32, i64);
+
+impl<T: MessageField> MessageField for Vec<T> {
+    const EXPECTED: &'static str = "an array";
+
+    fn from_json(json: &Json) -> Option<Vec<T>> {
+        json.get_array()?.iter().map(T::from_json).collect()
+    }
+}
+
+impl<T: MessageField> MessageField for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_json(json: &Json) -> Option<Option<T>> {
+        match *json {
+            Json::Null => Some(None),
+            _ => T::from_json(json).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Option<T>> { Some(None) }
+}
+
+// the rest of this module is used by the request! and response! macros
+
+#[doc(hidden)]
+pub fn kind(json: &Json) -> Result<&str, DecodeError> {
+    json.get_object()
+        .ok_or(DecodeError::NotAnObject)?
+        .get("kind")
+        .and_then(Json::get_string)
+        .ok_or(DecodeError::MissingKind)
+}
+
+#[doc(hidden)]
+pub fn field<T: MessageField>(json: &Json, kind: &'static str, field: &'static str) -> Result<T, DecodeError> {
+    match json.get_object().and_then(|o| o.get(field)) {
+        Some(value) => T::from_json(value).ok_or(DecodeError::WrongType { kind, field, expected: T::EXPECTED }),
+        None => T::missing().ok_or(DecodeError::MissingField { kind, field }),
+    }
+}
+
+#[doc(hidden)]
+pub fn into_json<T: Jsonable>(value: T) -> Json {
+    value.into_json()
+}
+
+#[doc(hidden)]
+pub fn object(kind: &str, fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(Some(("kind", Json::String(kind.to_string()))).into_iter()
+        .chain(fields)
+        .map(|(key, value)| (key.to_string(), value))
+        .collect())
+}
+
+#[macro_export]
+macro_rules! request {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident = $kind:literal { $($field:ident: $t:ty = $key:literal),* $(,)? }),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant { $($field: $t),* },)*
+        }
+
+        impl $crate::Request for $name {
+            fn decode(json: &$crate::json::Json) -> Result<$name, $crate::DecodeError> {
+                match $crate::message::kind(json)? {
+                    $($kind => Ok($name::$variant {
+                        $($field: $crate::message::field(json, $kind, $key)?,)*
+                    }),)*
+                    other => Err($crate::DecodeError::UnknownKind(other.to_string())),
+                }
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! response {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident = $kind:literal { $($field:ident: $t:ty = $key:literal),* $(,)? }),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant { $($field: $t),* },)*
+        }
+
+        impl $crate::Response for $name {
+            fn encode(self) -> $crate::json::Json {
+                match self {
+                    $($name::$variant { $($field),* } => $crate::message::object($kind, vec![
+                        $(($key, $crate::message::into_json($field)),)*
+                    ]),)*
+                }
+            }
+        }
+    };
+}
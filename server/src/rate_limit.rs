@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::clock;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RateLimitAction {
+    Drop, // the message is thrown away, the peer stays connected
+    Delay, // the peer's connection thread sleeps until it has a token again
+    Disconnect,
+}
+
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    per_second: f64,
+    burst: f64,
+    action: RateLimitAction,
+    counters: Arc<RateLimitCounters>,
+}
+
+impl RateLimit {
+    pub fn new(per_second: f64, burst: u32, action: RateLimitAction) -> RateLimit {
+        assert!(per_second > 0.0, "rate limit must allow some messages");
+        assert!(burst > 0, "burst must be at least one message");
+
+        RateLimit {
+            per_second,
+            burst: burst as f64,
+            action,
+            counters: Arc::new(RateLimitCounters::default()),
+        }
+    }
+
+    pub fn counters(&self) -> Arc<RateLimitCounters> {
+        Arc::clone(&self.counters)
+    }
+
+    pub fn limiter(&self) -> RateLimiter {
+        // every peer gets their own bucket, but all of them report to the same counters
+        RateLimiter {
+            limit: self.clone(),
+            tokens: self.burst,
+            last_refill: clock::now(),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct RateLimitCounters {
+    dropped: AtomicU64,
+    delayed: AtomicU64,
+    disconnected: AtomicU64,
+}
+
+impl RateLimitCounters {
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn delayed(&self) -> u64 {
+        self.delayed.load(Ordering::Relaxed)
+    }
+
+    pub fn disconnected(&self) -> u64 {
+        self.disconnected.load(Ordering::Relaxed)
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.dropped() + self.delayed() + self.disconnected()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Admit {
+    Accept,
+    Drop,
+    Disconnect,
+}
+
+// a token bucket: holds at most `burst` tokens, refills at `per_second`, and every message costs one
+pub struct RateLimiter {
+    limit: RateLimit,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn admit(&mut self) -> Admit {
+        match self.try_take() {
+            Ok(()) => Admit::Accept,
+            Err(wait) => match self.limit.action {
+                RateLimitAction::Drop => {
+                    self.limit.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    Admit::Drop
+                },
+                RateLimitAction::Delay => {
+                    self.limit.counters.delayed.fetch_add(1, Ordering::Relaxed);
+                    let mut wait = wait;
+                    loop {
+                        clock::sleep(wait);
+                        match self.try_take() {
+                            Ok(()) => break Admit::Accept,
+                            Err(still_waiting) => wait = still_waiting,
+                        }
+                    }
+                },
+                RateLimitAction::Disconnect => {
+                    self.limit.counters.disconnected.fetch_add(1, Ordering::Relaxed);
+                    Admit::Disconnect
+                },
+            },
+        }
+    }
+
+    fn try_take(&mut self) -> Result<(), Duration> {
+        self.refill();
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.limit.per_second))
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = clock::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst);
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::net::{TcpStream, TcpListener};
-use web_socket::{WebSocketMessage, WebSocketListener, WebSocketWriter};
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame, CloseCode, SharedStream};
 use std::io::{self, Write, Read};
 use std::sync::atomic::{self, AtomicU64};
 
@@ -9,17 +9,24 @@ use std::{thread};
 
 use std::option::NoneError;
 use http::HttpRequest;
-use crate::util::to_base64;
-use sha1::Sha1;
 use crate::http_handler::send_resource;
 use std::path::{PathBuf};
 use std::time::Duration;
 use std::hash::Hash;
+use crate::endpoint::Endpoint;
+use crate::rate_limit::Admit;
+use crate::handshake::{self, Accepted};
+use crate::session::{SessionStore, SessionId};
+use crate::heartbeat::{Heartbeat, HeartbeatThread};
+use crate::clock;
+
+const SESSION_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct Server {
     name: String,
-    map: HashMap<String, Arc<Mutex<dyn GlobalState>>>,
+    map: HashMap<String, Endpoint>,
     peer_id_generator: PeerIdGenerator,
+    sessions: SessionStore,
 
     resources_root: PathBuf,
     max_http_request_size: usize,
@@ -32,6 +39,7 @@ impl Server {
             name,
             map: HashMap::new(),
             peer_id_generator: PeerIdGenerator::new(),
+            sessions: SessionStore::new(),
             resources_root,
             max_http_request_size,
             period_length
@@ -55,15 +63,23 @@ impl Server {
 
         }).unwrap();
 
-        // our periodic loop
+        // our periodic loop. sessions last a day, so there's no hurry to notice which ones are over
+        let mut sessions_expired_at = clock::now();
         loop {
             arc.periodic();
+
+            if clock::now() - sessions_expired_at >= SESSION_EXPIRY_INTERVAL {
+                arc.sessions.expire_old();
+                sessions_expired_at = clock::now();
+            }
+
             thread::sleep(period_length);
         }
     }
 
-    pub fn web_socket_add(&mut self, location: String, global_state: Arc<Mutex<dyn GlobalState>>) {
-        self.map.insert(location, global_state);
+    pub fn web_socket_add(&mut self, location: String, global_state: Arc<Mutex<dyn GlobalState>>) -> &mut Endpoint {
+        self.map.insert(location.clone(), Endpoint::new(global_state));
+        self.map.get_mut(&location).unwrap()
     }
 
     fn handle_new_connection(self: &Arc<Server>, mut tcp_stream: TcpStream) {
@@ -81,50 +97,112 @@ impl Server {
 
     fn handle_request(&self, request: HttpRequest, mut tcp_stream: TcpStream, id: PeerId) {
         // check if we have a regular old http get or a websocket request
-        if let Some(sec_key) = request.get_header_value("Sec-WebSocket-Key") {
-            let mut hasher = Sha1::new();
-            hasher.update(sec_key.as_bytes());
-            hasher.update(b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"); // magic number
-            let digest = to_base64(&hasher.digest().bytes());
-            let response = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", digest);
-
-            if tcp_stream.write_all(response.as_bytes()).is_ok() {
-                self.on_new_web_socket_connection(request, tcp_stream, id);
+        if handshake::is_upgrade_request(&request) {
+            match handshake::accept(&request, self.map.get(request.resource_location())) {
+                Ok(accepted) => {
+                    let (session, set_cookie) = self.sessions.resume_or_start(&request);
+                    let response = handshake::switching_protocols_response(&accepted, set_cookie.as_ref());
+
+                    if tcp_stream.write_all(response.as_bytes()).is_ok() {
+                        self.on_new_web_socket_connection(&accepted, tcp_stream, id, session);
+                    }
+                },
+                Err(e) => {
+                    let _ = tcp_stream.write_all(&e.response());
+                },
             }
 
         } else {
-            // just a regular old http request!
-            let _ = send_resource(&request, &mut tcp_stream, &self.resources_root);
+            // just a regular old http request! pages get the session cookie now, so their web
+            // sockets already have it. scripts and images don't need one
+            let session_cookie = || self.sessions.resume_or_start(&request).1;
+            let _ = send_resource(&request, &mut tcp_stream, &self.resources_root, session_cookie);
         }
     }
 
-    fn on_new_web_socket_connection(&self, request: HttpRequest, tcp_stream: TcpStream, id: PeerId) {
-        if let Some(state) = self.map.get(request.resource_location()) {
-            state.lock().unwrap().new_peer(id, WebSocketWriter::new(tcp_stream.try_clone().unwrap()));
+    fn on_new_web_socket_connection(&self, accepted: &Accepted, tcp_stream: TcpStream, id: PeerId, session: SessionId) {
+        let endpoint = accepted.endpoint;
+        let state = &endpoint.state;
+        let mut rate_limiter = endpoint.rate_limit.as_ref().map(|r| r.limiter());
+
+        let tcp_stream = SharedStream::new(tcp_stream);
+        let (mut listener, mut writer) = web_socket::split(tcp_stream.clone());
+        listener.set_limits(endpoint.limits);
+        if let Some(deflate) = accepted.deflate {
+            writer.set_deflate(deflate);
+            listener.set_deflate(deflate);
+        }
+
+        let heartbeat = endpoint.heartbeat.map(|(interval, timeout)| {
+            let heartbeat = HeartbeatThread::start(Heartbeat::new(interval, timeout), writer.clone(), tcp_stream, format!("{}/{}_heartbeat", self.name, id.stringify()));
 
-            for message in WebSocketListener::new(tcp_stream) {
-                match state.lock().unwrap().on_message_receive(id, message) {
-                    Ok(()) => {},
-                    Err(Disconnect) => break,
+            let heartbeat_clone = Arc::clone(&heartbeat);
+            let state = Arc::clone(state);
+            listener.on_pong(move |payload| {
+                if let Some(round_trip) = heartbeat_clone.on_pong(payload) {
+                    state.lock().unwrap().on_latency(id, round_trip);
+                }
+            });
+
+            heartbeat
+        });
+
+        state.lock().unwrap().new_peer(id, session, writer, accepted.subprotocol.as_deref());
+
+        for message in listener.by_ref() {
+            if let Some(ref mut rate_limiter) = rate_limiter {
+                match rate_limiter.admit() {
+                    Admit::Accept => {},
+                    Admit::Drop => continue,
+                    Admit::Disconnect => {
+                        let _ = listener.close(CloseFrame::new(CloseCode::PolicyViolation, "rate limit exceeded"));
+                        break;
+                    },
                 }
             }
 
-            state.lock().unwrap().on_disconnect(id);
+            match state.lock().unwrap().on_message_receive(id, message) {
+                Ok(()) => {},
+                Err(Disconnect) => {
+                    let _ = listener.close(CloseFrame::new(CloseCode::Normal, ""));
+                    break;
+                },
+            }
         }
+
+        let timed_out = match heartbeat {
+            Some(heartbeat) => {
+                heartbeat.stop();
+                heartbeat.timed_out()
+            },
+            None => false,
+        };
+
+        let close_frame = if timed_out {
+            CloseFrame::new(CloseCode::Abnormal, "heartbeat timed out")
+        } else {
+            listener.close_frame().cloned()
+                .unwrap_or_else(|| CloseFrame::new(CloseCode::Abnormal, ""))
+        };
+
+        state.lock().unwrap().on_disconnect(id, &close_frame);
     }
 
     fn periodic(&self) {
-        for state in self.map.values() {
-            state.lock().unwrap().periodic();
+        for endpoint in self.map.values() {
+            endpoint.state.lock().unwrap().periodic();
         }
     }
 }
 
 pub trait GlobalState: Send {
-    fn new_peer(&mut self, id: PeerId, tcp_stream: WebSocketWriter);
+    fn new_peer(&mut self, id: PeerId, session: SessionId, tcp_stream: WebSocketWriter, subprotocol: Option<&str>);
     fn on_message_receive(&mut self, id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect>;
-    fn on_disconnect(&mut self, id: PeerId);
+    fn on_disconnect(&mut self, id: PeerId, close_frame: &CloseFrame);
     fn periodic(&mut self);
+
+    // how long the peer took to answer our last heartbeat ping, for games that show lag
+    fn on_latency(&mut self, _id: PeerId, _round_trip: Duration) {}
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -139,7 +217,7 @@ impl From<NoneError> for Disconnect {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct PeerId(u64);
+pub struct PeerId(pub(crate) u64);
 
 impl PeerId {
     fn stringify(&self) -> String {
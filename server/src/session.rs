@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::sync::atomic::{self, AtomicU64};
+use std::time::{Duration, Instant};
+
+use http::{HttpRequest, SetCookie, SameSite};
+use rand::random;
+
+use crate::clock;
+
+const SESSION_COOKIE_NAME: &str = "session";
+const SESSION_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_SESSIONS: usize = 100_000;
+
+// stays the same for a browser across page refreshes and reconnects, unlike a PeerId
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SessionId(pub(crate) u64);
+
+struct Session {
+    id: SessionId,
+    last_seen: Instant,
+}
+
+pub struct SessionStore {
+    sessions: Mutex<Sessions>,
+    next_id: AtomicU64,
+    max_sessions: usize,
+}
+
+#[derive(Default)]
+struct Sessions {
+    by_token: HashMap<String, Session>,
+    // oldest first, so evicting and expiring don't have to look at every session. the id breaks
+    // ties between sessions seen at the same instant
+    by_last_seen: BTreeMap<(Instant, SessionId), String>,
+}
+
+impl Sessions {
+    fn oldest(&self) -> Option<(Instant, SessionId)> {
+        self.by_last_seen.keys().next().copied()
+    }
+
+    fn remove_oldest(&mut self) {
+        if let Some(oldest) = self.oldest() {
+            let token = self.by_last_seen.remove(&oldest).unwrap();
+            self.by_token.remove(&token);
+        }
+    }
+}
+
+impl SessionStore {
+    pub fn new() -> SessionStore {
+        SessionStore::with_max_sessions(MAX_SESSIONS)
+    }
+
+    pub fn with_max_sessions(max_sessions: usize) -> SessionStore {
+        // past this many, starting a session forgets the one that's gone longest without being seen
+        assert!(max_sessions > 0, "there has to be room for at least one session");
+
+        SessionStore {
+            sessions: Mutex::new(Sessions::default()),
+            next_id: AtomicU64::new(0),
+            max_sessions,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().by_token.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn resume_or_start(&self, request: &HttpRequest) -> (SessionId, Option<SetCookie>) {
+        // returns a cookie to send back if the browser didn't already have a session with us
+        let now = clock::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        let sessions = &mut *sessions;
+
+        if let Some(token) = request.get_cookie(SESSION_COOKIE_NAME) {
+            if let Some(session) = sessions.by_token.get_mut(token) {
+                sessions.by_last_seen.remove(&(session.last_seen, session.id));
+                sessions.by_last_seen.insert((now, session.id), token.to_string());
+                session.last_seen = now;
+                return (session.id, None);
+            }
+        }
+
+        let id = SessionId(self.next_id.fetch_add(1, atomic::Ordering::Relaxed));
+
+        // the token is what the browser has to prove it owns, so it's random instead of the id
+        let token = format!("{:016x}{:016x}", random::<u64>(), random::<u64>());
+
+        let cookie = SetCookie::new(SESSION_COOKIE_NAME.into(), token.clone())
+            .path("/".into())
+            .max_age(SESSION_LIFETIME.as_secs())
+            .http_only()
+            .same_site(SameSite::Lax);
+
+        if sessions.by_token.len() >= self.max_sessions {
+            sessions.remove_oldest();
+        }
+
+        sessions.by_last_seen.insert((now, id), token.clone());
+        sessions.by_token.insert(token, Session { id, last_seen: now });
+
+        (id, Some(cookie))
+    }
+
+    pub fn expire_old(&self) {
+        let now = clock::now();
+        let mut sessions = self.sessions.lock().unwrap();
+
+        while let Some((last_seen, _)) = sessions.oldest() {
+            if now.duration_since(last_seen) < SESSION_LIFETIME { break }
+            sessions.remove_oldest();
+        }
+    }
+}
+
+impl Default for SessionStore {
+    fn default() -> SessionStore {
+        SessionStore::new()
+    }
+}
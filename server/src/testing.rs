@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use json::Json;
+use web_socket::{WebSocketMessage, WebSocketListener, WebSocketWriter, CloseFrame, CloseCode};
+
+use crate::clock;
+use crate::heartbeat::{Heartbeat, Beat};
+use crate::server::{GlobalState, PeerId, Disconnect};
+use crate::session::SessionId;
+
+// drives a GlobalState the way the server would, but without any sockets or threads, so apps can
+// be tested with plain #[test] functions. the clock in `server::clock` is frozen for as long as
+// the harness is alive, and only moves when `advance` is called
+pub struct TestHarness<S: GlobalState> {
+    state: S,
+    peers: HashMap<PeerId, Peer>,
+    next_peer_id: u64,
+    next_session_id: u64,
+    heartbeat: Option<(Duration, Duration)>,
+}
+
+struct Peer {
+    session: SessionId,
+    sink: Sink,
+    read_up_to: usize,
+    heartbeat: Option<Heartbeat>,
+    last_ping: Option<[u8; 8]>,
+}
+
+impl<S: GlobalState> TestHarness<S> {
+    pub fn new(state: S) -> TestHarness<S> {
+        clock::freeze();
+
+        TestHarness {
+            state,
+            peers: HashMap::new(),
+            next_peer_id: 0,
+            next_session_id: 0,
+            heartbeat: None,
+        }
+    }
+
+    pub fn heartbeat(&mut self, interval: Duration, timeout: Duration) -> &mut TestHarness<S> {
+        // peers that connect from now on get pinged like an Endpoint::heartbeat would, as the
+        // clock moves forward
+        assert!(timeout < interval, "the heartbeat timeout has to be shorter than the interval");
+        self.heartbeat = Some((interval, timeout));
+        self
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+
+    pub fn connect(&mut self) -> PeerId {
+        // a brand new browser
+        let session = self.new_session();
+        self.add_peer(session, None)
+    }
+
+    pub fn connect_with_subprotocol(&mut self, subprotocol: &str) -> PeerId {
+        // a brand new browser, and the subprotocol the server picked for it
+        let session = self.new_session();
+        self.add_peer(session, Some(subprotocol))
+    }
+
+    pub fn reconnect(&mut self, session: SessionId) -> PeerId {
+        // a browser we've seen before, like after a page refresh
+        self.add_peer(session, None)
+    }
+
+    pub fn session(&self, id: PeerId) -> SessionId {
+        self.peer(id).session
+    }
+
+    pub fn is_connected(&self, id: PeerId) -> bool {
+        self.peers.contains_key(&id)
+    }
+
+    pub fn send_text(&mut self, id: PeerId, text: &str) -> Result<(), Disconnect> {
+        self.send(id, WebSocketMessage::Text(text.to_string()))
+    }
+
+    pub fn send_json(&mut self, id: PeerId, json: &Json) -> Result<(), Disconnect> {
+        self.send_text(id, &json.to_string())
+    }
+
+    pub fn send(&mut self, id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect> {
+        assert!(self.is_connected(id), "{:?} isn't connected", id);
+
+        let result = self.state.on_message_receive(id, message);
+
+        if result.is_err() {
+            // the server hangs up on peers that return Disconnect
+            self.disconnect_with(id, CloseFrame::new(CloseCode::Normal, ""));
+        }
+
+        result
+    }
+
+    pub fn disconnect(&mut self, id: PeerId) {
+        // what a browser does when its tab is closed or refreshed
+        self.disconnect_with(id, CloseFrame::new(CloseCode::GoingAway, ""));
+    }
+
+    pub fn disconnect_with(&mut self, id: PeerId, close_frame: CloseFrame) {
+        if self.peers.remove(&id).is_some() {
+            self.state.on_disconnect(id, &close_frame);
+        }
+    }
+
+    pub fn pong(&mut self, id: PeerId) {
+        // the peer answers the last heartbeat ping it got, the way a browser does on its own
+        let payload = self.peer(id).last_ping.expect("that peer hasn't been pinged");
+        self.pong_with(id, &payload);
+    }
+
+    pub fn pong_with(&mut self, id: PeerId, payload: &[u8]) {
+        let peer = self.peers.get_mut(&id).expect("that peer was never connected, or already left");
+
+        if let Some(round_trip) = peer.heartbeat.as_mut().and_then(|heartbeat| heartbeat.on_pong(payload)) {
+            self.state.on_latency(id, round_trip);
+        }
+    }
+
+    pub fn periodic(&mut self) {
+        self.state.periodic();
+    }
+
+    pub fn advance(&mut self, duration: Duration) {
+        // heartbeats go off at the right moments along the way
+        let until = clock::now() + duration;
+
+        loop {
+            let next_due = self.peers.values()
+                .filter_map(|peer| peer.heartbeat.as_ref())
+                .map(Heartbeat::due)
+                .min()
+                .filter(|&due| due <= until);
+
+            match next_due {
+                Some(due) => {
+                    clock::advance(due.saturating_duration_since(clock::now()));
+                    self.beat_hearts();
+                },
+                None => break,
+            }
+        }
+
+        clock::advance(until - clock::now());
+    }
+
+    pub fn advance_periodic(&mut self, duration: Duration, period_length: Duration) {
+        // what the server's periodic loop would have done over that much time
+        let mut elapsed = Duration::from_secs(0);
+        while elapsed + period_length <= duration {
+            self.advance(period_length);
+            self.periodic();
+            elapsed += period_length;
+        }
+        self.advance(duration - elapsed);
+    }
+
+    pub fn received_text(&mut self, id: PeerId) -> Vec<String> {
+        // every text message sent to this peer since we last checked
+        let peer = self.peers.get_mut(&id).expect("that peer was never connected, or already left");
+        let bytes = peer.sink.0.lock().unwrap();
+        let messages = read_server_frames(&bytes[peer.read_up_to..]);
+        peer.read_up_to = bytes.len();
+
+        messages
+    }
+
+    pub fn received(&mut self, id: PeerId) -> Vec<Json> {
+        self.received_text(id).iter()
+            .map(|text| Json::from_str(text).unwrap_or_else(|_| panic!("{:?} was sent invalid json: {}", id, text)))
+            .collect()
+    }
+
+    pub fn received_kinds(&mut self, id: PeerId) -> Vec<String> {
+        self.received(id).iter()
+            .map(|json| json.get_object().and_then(|o| o.get("kind")).and_then(Json::get_string).unwrap_or("").to_string())
+            .collect()
+    }
+
+    fn beat_hearts(&mut self) {
+        let mut timed_out = Vec::new();
+
+        for (&id, peer) in self.peers.iter_mut() {
+            while let Some(beat) = peer.heartbeat.as_mut().and_then(Heartbeat::beat) {
+                match beat {
+                    Beat::Ping(payload) => {
+                        let _ = WebSocketWriter::new(peer.sink.clone()).ping(&payload);
+                        peer.last_ping = Some(payload);
+                    },
+                    Beat::TimedOut => {
+                        timed_out.push(id);
+                        break;
+                    },
+                }
+            }
+        }
+
+        for id in timed_out {
+            self.disconnect_with(id, CloseFrame::new(CloseCode::Abnormal, "heartbeat timed out"));
+        }
+    }
+
+    fn new_session(&mut self) -> SessionId {
+        let session = SessionId(self.next_session_id);
+        self.next_session_id += 1;
+        session
+    }
+
+    fn add_peer(&mut self, session: SessionId, subprotocol: Option<&str>) -> PeerId {
+        let id = PeerId(self.next_peer_id);
+        self.next_peer_id += 1;
+
+        let sink = Sink::default();
+        let heartbeat = self.heartbeat.map(|(interval, timeout)| Heartbeat::new(interval, timeout));
+        self.peers.insert(id, Peer { session, sink: sink.clone(), read_up_to: 0, heartbeat, last_ping: None });
+        self.state.new_peer(id, session, WebSocketWriter::new(sink), subprotocol);
+
+        id
+    }
+
+    fn peer(&self, id: PeerId) -> &Peer {
+        self.peers.get(&id).expect("that peer was never connected, or already left")
+    }
+}
+
+impl<S: GlobalState> Drop for TestHarness<S> {
+    fn drop(&mut self) {
+        clock::unfreeze();
+    }
+}
+
+#[derive(Clone, Default)]
+struct Sink(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn read_server_frames(bytes: &[u8]) -> Vec<String> {
+    // the text messages in what the server wrote, read the way a browser would. the harness only
+    // looks between calls into the app, so the bytes always end on a whole message
+    let mut listener = WebSocketListener::new(bytes, WebSocketWriter::client(io::sink()));
+
+    listener.by_ref()
+        .filter_map(|message| match message {
+            WebSocketMessage::Text(text) => Some(text),
+            // binary messages aren't something apps send as json, so we skip them
+            WebSocketMessage::Binary(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,172 @@
+// telling web socket handshakes from plain requests, turning away bad ones, and picking a
+// subprotocol from the client's Sec-WebSocket-Protocol header
+
+use std::sync::{Arc, Mutex};
+
+use http::HttpRequest;
+use server::handshake::{accept, is_upgrade_request, switching_protocols_response, HandshakeError};
+use server::{Disconnect, Endpoint, GlobalState, PeerId, SessionId};
+use web_socket::{CloseFrame, WebSocketMessage, WebSocketWriter};
+
+struct Nobody;
+
+impl GlobalState for Nobody {
+    fn new_peer(&mut self, _id: PeerId, _session: SessionId, _writer: WebSocketWriter, _subprotocol: Option<&str>) {}
+    fn on_message_receive(&mut self, _id: PeerId, _message: WebSocketMessage) -> Result<(), Disconnect> { Ok(()) }
+    fn on_disconnect(&mut self, _id: PeerId, _close_frame: &CloseFrame) {}
+    fn periodic(&mut self) {}
+}
+
+fn endpoint(subprotocols: &[&str]) -> Endpoint {
+    let mut endpoint = Endpoint::new(Arc::new(Mutex::new(Nobody)));
+    endpoint.subprotocols(subprotocols.iter().map(|s| s.to_string()).collect());
+    endpoint
+}
+
+const HANDSHAKE: [(&str, &str); 5] = [
+    ("Host", "ethan.ws"),
+    ("Upgrade", "websocket"),
+    ("Connection", "Upgrade"),
+    ("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ=="),
+    ("Sec-WebSocket-Version", "13"),
+];
+
+fn request(method: &str, headers: &[(&str, &str)]) -> HttpRequest {
+    let headers: String = headers.iter().map(|&(name, value)| format!("{}: {}\r\n", name, value)).collect();
+    format!("{} /tanks HTTP/1.1\r\n{}\r\n", method, headers).parse().unwrap()
+}
+
+fn handshake_with(name: &str, value: Option<&str>) -> HttpRequest {
+    // the usual handshake, with one header changed, added, or left out
+    let mut headers: Vec<(&str, &str)> = HANDSHAKE.iter().filter(|&&(n, _)| n != name).cloned().collect();
+    if let Some(value) = value {
+        headers.push((name, value));
+    }
+    request("GET", &headers)
+}
+
+fn upgrade_request(protocol_header: Option<&str>) -> HttpRequest {
+    handshake_with("Sec-WebSocket-Protocol", protocol_header)
+}
+
+fn rejection(request: &HttpRequest, endpoint: Option<&Endpoint>) -> (HandshakeError, String) {
+    // and the status line it gets answered with
+    let error = accept(request, endpoint).err().expect("the handshake should have been rejected");
+    let response = String::from_utf8(error.response()).unwrap();
+    (error, response.lines().next().unwrap().to_string())
+}
+
+#[test]
+fn our_preference_wins() {
+    let endpoint = endpoint(&["tanks.json.v2", "tanks.json.v1"]);
+    assert_eq!(endpoint.choose_subprotocol(Some("tanks.json.v1, tanks.json.v2")), Some("tanks.json.v2".to_string()));
+    assert_eq!(endpoint.choose_subprotocol(Some("chat,tanks.json.v1")), Some("tanks.json.v1".to_string()));
+    assert_eq!(endpoint.choose_subprotocol(Some(" tanks.json.v1 ")), Some("tanks.json.v1".to_string()));
+}
+
+#[test]
+fn nothing_in_common() {
+    assert_eq!(endpoint(&[]).choose_subprotocol(Some("tanks.json.v2")), None);
+
+    let endpoint = endpoint(&["tanks.json.v2"]);
+    assert_eq!(endpoint.choose_subprotocol(Some("chat, tanks.json.v1")), None);
+    assert_eq!(endpoint.choose_subprotocol(Some("")), None);
+    assert_eq!(endpoint.choose_subprotocol(None), None);
+}
+
+#[test]
+fn response_names_the_chosen_subprotocol() {
+    let endpoint = endpoint(&["tanks.json.v2", "tanks.json.v1"]);
+
+    let accepted = accept(&upgrade_request(Some("tanks.json.v1, tanks.json.v2")), Some(&endpoint)).unwrap();
+    assert_eq!(accepted.subprotocol.as_deref(), Some("tanks.json.v2"));
+    let response = switching_protocols_response(&accepted, None);
+    assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(response.contains("\r\nSec-WebSocket-Protocol: tanks.json.v2\r\n"));
+    assert!(response.contains("\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+}
+
+#[test]
+fn response_leaves_the_header_out_without_a_match() {
+    let endpoint = endpoint(&["tanks.json.v2"]);
+
+    for header in [None, Some("chat")].iter() {
+        let accepted = accept(&upgrade_request(*header), Some(&endpoint)).unwrap();
+        assert_eq!(accepted.subprotocol, None);
+        assert!(!switching_protocols_response(&accepted, None).contains("Sec-WebSocket-Protocol"));
+    }
+}
+
+#[test]
+fn only_web_socket_upgrades_are_handshakes() {
+    assert!(is_upgrade_request(&request("GET", &HANDSHAKE)));
+    assert!(is_upgrade_request(&handshake_with("Upgrade", Some("WebSocket"))));
+    assert!(is_upgrade_request(&handshake_with("Upgrade", None)));
+    assert!(is_upgrade_request(&request("GET", &[("Host", "ethan.ws"), ("Upgrade", "websocket")])));
+
+    assert!(!is_upgrade_request(&request("GET", &[("Host", "ethan.ws")])));
+    assert!(!is_upgrade_request(&request("GET", &[("Host", "ethan.ws"), ("Upgrade", "h2c"), ("Connection", "Upgrade, HTTP2-Settings")])));
+    assert!(!is_upgrade_request(&request("GET", &[("Host", "ethan.ws"), ("Upgrade", "TLS/1.0"), ("Connection", "Upgrade")])));
+}
+
+#[test]
+fn unknown_endpoints_are_not_found() {
+    let (error, status) = rejection(&request("GET", &HANDSHAKE), None);
+    assert_eq!(error, HandshakeError::NotFound);
+    assert_eq!(status, "HTTP/1.1 404 Page Not Found");
+}
+
+#[test]
+fn malformed_handshakes_are_bad_requests() {
+    let endpoint = endpoint(&[]);
+    let bad = [
+        (request("POST", &HANDSHAKE), "web socket handshakes must use GET"),
+        (handshake_with("Host", None), "missing Host header"),
+        (handshake_with("Upgrade", None), "Upgrade header must include websocket"),
+        (handshake_with("Upgrade", Some("h2c")), "Upgrade header must include websocket"),
+        (handshake_with("Connection", None), "Connection header must include Upgrade"),
+        (handshake_with("Connection", Some("keep-alive")), "Connection header must include Upgrade"),
+        (handshake_with("Sec-WebSocket-Key", None), "missing Sec-WebSocket-Key header"),
+        (handshake_with("Sec-WebSocket-Key", Some("dGhlIHNhbXBsZQ==")), "Sec-WebSocket-Key must be 16 bytes of base64"),
+        (handshake_with("Sec-WebSocket-Key", Some("not base64 at all!!!!!!!")), "Sec-WebSocket-Key must be 16 bytes of base64"),
+    ];
+
+    for (request, reason) in bad.iter() {
+        let (error, status) = rejection(request, Some(&endpoint));
+        assert_eq!(error, HandshakeError::BadRequest(*reason));
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    }
+
+    // a Connection list with Upgrade somewhere in it is fine
+    assert!(accept(&handshake_with("Connection", Some("keep-alive, Upgrade")), Some(&endpoint)).is_ok());
+}
+
+#[test]
+fn other_versions_need_an_upgrade() {
+    let endpoint = endpoint(&[]);
+
+    for version in [Some("8"), Some("14"), None].iter() {
+        let (error, status) = rejection(&handshake_with("Sec-WebSocket-Version", *version), Some(&endpoint));
+        assert_eq!(error, HandshakeError::UnsupportedVersion);
+        assert_eq!(status, "HTTP/1.1 426 Upgrade Required");
+    }
+
+    let response = String::from_utf8(HandshakeError::UnsupportedVersion.response()).unwrap();
+    assert!(response.contains("\r\nSec-WebSocket-Version: 13\r\n"));
+}
+
+#[test]
+fn origins_can_be_limited() {
+    let mut endpoint = endpoint(&[]);
+    endpoint.allowed_origins(vec!["https://ethan.ws".to_string()]);
+
+    let (error, status) = rejection(&handshake_with("Origin", Some("https://evil.example")), Some(&endpoint));
+    assert_eq!(error, HandshakeError::ForbiddenOrigin);
+    assert_eq!(status, "HTTP/1.1 403 Forbidden");
+
+    assert!(accept(&handshake_with("Origin", Some("https://ethan.ws")), Some(&endpoint)).is_ok());
+
+    // browsers always send one, so a missing Origin can't be a hijacked page
+    assert!(accept(&handshake_with("Origin", None), Some(&endpoint)).is_ok());
+}
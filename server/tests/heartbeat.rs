@@ -0,0 +1,87 @@
+// pinging peers every so often, and hanging up on the ones that stop answering
+
+use std::time::Duration;
+
+use server::{Disconnect, GlobalState, PeerId, SessionId, TestHarness};
+use web_socket::{CloseCode, CloseFrame, WebSocketMessage, WebSocketWriter};
+
+const INTERVAL: Duration = Duration::from_secs(30);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Lag {
+    latencies: Vec<(PeerId, Duration)>,
+    disconnects: Vec<(PeerId, CloseFrame)>,
+}
+
+impl GlobalState for Lag {
+    fn new_peer(&mut self, _id: PeerId, _session: SessionId, _writer: WebSocketWriter, _subprotocol: Option<&str>) {}
+    fn on_message_receive(&mut self, _id: PeerId, _message: WebSocketMessage) -> Result<(), Disconnect> { Ok(()) }
+    fn periodic(&mut self) {}
+
+    fn on_disconnect(&mut self, id: PeerId, close_frame: &CloseFrame) {
+        self.disconnects.push((id, close_frame.clone()));
+    }
+
+    fn on_latency(&mut self, id: PeerId, round_trip: Duration) {
+        self.latencies.push((id, round_trip));
+    }
+}
+
+fn harness() -> TestHarness<Lag> {
+    let mut harness = TestHarness::new(Lag::default());
+    harness.heartbeat(INTERVAL, TIMEOUT);
+    harness
+}
+
+#[test]
+fn a_matching_pong_reports_the_round_trip() {
+    let mut harness = harness();
+    let peer = harness.connect();
+
+    // the first ping goes out a timeout before the first interval is up
+    harness.advance(INTERVAL - TIMEOUT);
+    harness.advance(Duration::from_millis(250));
+    harness.pong(peer);
+    assert_eq!(harness.state().latencies, vec![(peer, Duration::from_millis(250))]);
+
+    // answering twice, or with the wrong payload, doesn't count
+    harness.pong(peer);
+    harness.pong_with(peer, b"nonsense");
+    assert_eq!(harness.state().latencies.len(), 1);
+
+    // and a peer that keeps answering stays
+    for _ in 0..10 {
+        harness.advance(INTERVAL);
+        harness.pong(peer);
+    }
+    assert_eq!(harness.state().latencies.len(), 11);
+    assert!(harness.is_connected(peer));
+    assert!(harness.state().disconnects.is_empty());
+}
+
+#[test]
+fn a_missed_pong_disconnects() {
+    let mut harness = harness();
+    let answers = harness.connect();
+    let sleeps = harness.connect();
+
+    harness.advance(INTERVAL - TIMEOUT);
+    harness.pong(answers);
+    harness.advance(TIMEOUT - Duration::from_millis(1));
+    assert!(harness.is_connected(sleeps));
+
+    harness.advance(Duration::from_millis(1));
+    assert!(!harness.is_connected(sleeps));
+    assert!(harness.is_connected(answers));
+    assert_eq!(harness.state().disconnects, vec![(sleeps, CloseFrame::new(CloseCode::Abnormal, "heartbeat timed out"))]);
+}
+
+#[test]
+fn pings_are_not_messages() {
+    let mut harness = harness();
+    let peer = harness.connect();
+
+    harness.advance(INTERVAL - TIMEOUT);
+    assert_eq!(harness.received_text(peer), Vec::<String>::new());
+}
@@ -0,0 +1,93 @@
+// messages declared with request! and response!, and what clients are told when theirs are wrong
+
+use json::{json, MessageSchemas, Schema};
+use server::{validate_or_reply, DecodeError, Request, Response};
+use web_socket::{WebSocketMessage, WebSocketWriter};
+
+server::request! {
+    #[derive(PartialEq, Debug)]
+    enum GameRequest {
+        Join = "join" { game_id: String = "gameId", seat: Option<u8> = "seat" },
+        Play = "play" { cards: Vec<u32> = "cards" },
+        Leave = "leave" {},
+    }
+}
+
+server::response! {
+    enum GameResponse {
+        Joined = "joined" { players: Vec<String> = "players", host: bool = "isHost" },
+        Left = "left" {},
+    }
+}
+
+fn decode(text: &str) -> Result<GameRequest, DecodeError> {
+    GameRequest::decode_message(&WebSocketMessage::Text(text.to_string()))
+}
+
+fn decode_binary() -> Result<GameRequest, DecodeError> {
+    GameRequest::decode_message(&WebSocketMessage::Binary(b"{}".to_vec()))
+}
+
+fn error_message(text: &str) -> String {
+    decode(text).unwrap_err().to_string()
+}
+
+#[test]
+fn requests_decode() {
+    assert_eq!(decode(r#"{"kind": "join", "gameId": "apple-pie", "seat": 2}"#), Ok(GameRequest::Join {
+        game_id: "apple-pie".to_string(),
+        seat: Some(2),
+    }));
+    assert_eq!(decode(r#"{"kind": "join", "gameId": "apple-pie", "seat": null}"#), Ok(GameRequest::Join {
+        game_id: "apple-pie".to_string(),
+        seat: None,
+    }));
+    assert_eq!(decode(r#"{"kind": "join", "gameId": "apple-pie"}"#), Ok(GameRequest::Join {
+        game_id: "apple-pie".to_string(),
+        seat: None,
+    }));
+    assert_eq!(decode(r#"{"kind": "play", "cards": [0, 51], "extra": true}"#), Ok(GameRequest::Play { cards: vec![0, 51] }));
+    assert_eq!(GameRequest::decode(&json!({kind: "leave"})), Ok(GameRequest::Leave {}));
+}
+
+#[test]
+fn responses_encode() {
+    let joined = GameResponse::Joined { players: vec!["ethan".to_string()], host: true };
+    assert_eq!(joined.encode(), json!({kind: "joined", players: ["ethan"], isHost: true}));
+    assert_eq!(GameResponse::Left {}.encode(), json!({kind: "left"}));
+}
+
+#[test]
+fn what_was_wrong() {
+    assert_eq!(decode_binary(), Err(DecodeError::NotText));
+    assert!(error_message("{\"kind\": ").starts_with("message is not valid json: "));
+    assert_eq!(error_message("[]"), "message must be a json object");
+    assert_eq!(error_message(r#"{"gameId": "apple-pie"}"#), "message is missing a string `kind`");
+    assert_eq!(error_message(r#"{"kind": 7}"#), "message is missing a string `kind`");
+    assert_eq!(error_message(r#"{"kind": "dance"}"#), "unknown message kind `dance`");
+    assert_eq!(error_message(r#"{"kind": "join"}"#), "`join` message is missing `gameId`");
+    assert_eq!(error_message(r#"{"kind": "join", "gameId": 3}"#), "`gameId` in `join` message must be a string");
+    assert_eq!(error_message(r#"{"kind": "join", "gameId": "a", "seat": 256}"#), "`seat` in `join` message must be a whole number in range");
+    assert_eq!(error_message(r#"{"kind": "play", "cards": [1, "2"]}"#), "`cards` in `play` message must be an array");
+}
+
+#[test]
+fn errors_as_json() {
+    let error = decode(r#"{"kind": "dance"}"#).unwrap_err();
+    assert_eq!(error.to_json(), json!({kind: "error", message: "unknown message kind `dance`"}));
+
+    let schemas = MessageSchemas::new()
+        .kind("join", Schema::object().field("gameId", Schema::string().min_len(1)));
+    let mut writer = WebSocketWriter::new(Vec::new());
+    let error = validate_or_reply(&WebSocketMessage::Text(r#"{"kind": "join", "gameId": ""}"#.to_string()), &schemas, &mut writer).unwrap_err();
+
+    assert_eq!(error.to_string(), "message is invalid: must have at least 1 characters at /gameId");
+    assert_eq!(error.to_json(), json!({
+        kind: "error",
+        message: "message is invalid: must have at least 1 characters at /gameId",
+        violations: ["must have at least 1 characters at /gameId"],
+    }));
+
+    let valid = validate_or_reply(&WebSocketMessage::Text(r#"{"kind": "join", "gameId": "a"}"#.to_string()), &schemas, &mut writer);
+    assert_eq!(valid, Ok(json!({kind: "join", gameId: "a"})));
+}
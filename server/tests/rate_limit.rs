@@ -0,0 +1,87 @@
+// token buckets, run against a frozen clock
+
+use std::time::Duration;
+
+use server::{clock, Admit, RateLimit, RateLimitAction};
+
+fn admit_all(limit: &RateLimit, messages: usize) -> Vec<Admit> {
+    let mut limiter = limit.limiter();
+    (0..messages).map(|_| limiter.admit()).collect()
+}
+
+#[test]
+fn burst_then_drop() {
+    clock::freeze();
+    let limit = RateLimit::new(2.0, 3, RateLimitAction::Drop);
+
+    assert_eq!(admit_all(&limit, 5), vec![Admit::Accept, Admit::Accept, Admit::Accept, Admit::Drop, Admit::Drop]);
+    assert_eq!(limit.counters().dropped(), 2);
+    assert_eq!(limit.counters().rejected(), 2);
+    assert_eq!(limit.counters().disconnected(), 0);
+    clock::unfreeze();
+}
+
+#[test]
+fn every_peer_gets_a_full_bucket() {
+    clock::freeze();
+    let limit = RateLimit::new(1.0, 2, RateLimitAction::Drop);
+
+    assert_eq!(admit_all(&limit, 3), vec![Admit::Accept, Admit::Accept, Admit::Drop]);
+    assert_eq!(admit_all(&limit, 3), vec![Admit::Accept, Admit::Accept, Admit::Drop]);
+    assert_eq!(limit.counters().dropped(), 2);
+    clock::unfreeze();
+}
+
+#[test]
+fn refills_over_time() {
+    clock::freeze();
+    let limit = RateLimit::new(2.0, 3, RateLimitAction::Drop);
+    let mut limiter = limit.limiter();
+    for _ in 0..3 {
+        assert_eq!(limiter.admit(), Admit::Accept);
+    }
+    assert_eq!(limiter.admit(), Admit::Drop);
+
+    // half a token isn't enough
+    clock::advance(Duration::from_millis(250));
+    assert_eq!(limiter.admit(), Admit::Drop);
+
+    clock::advance(Duration::from_millis(250));
+    assert_eq!(limiter.admit(), Admit::Accept);
+    assert_eq!(limiter.admit(), Admit::Drop);
+
+    // never more than the burst, however long it's been
+    clock::advance(Duration::from_secs(60));
+    for _ in 0..3 {
+        assert_eq!(limiter.admit(), Admit::Accept);
+    }
+    assert_eq!(limiter.admit(), Admit::Drop);
+    assert_eq!(limit.counters().dropped(), 4);
+    clock::unfreeze();
+}
+
+#[test]
+fn disconnect_once_the_burst_is_gone() {
+    clock::freeze();
+    let limit = RateLimit::new(1.0, 2, RateLimitAction::Disconnect);
+
+    assert_eq!(admit_all(&limit, 3), vec![Admit::Accept, Admit::Accept, Admit::Disconnect]);
+    assert_eq!(limit.counters().disconnected(), 1);
+    assert_eq!(limit.counters().dropped(), 0);
+    clock::unfreeze();
+}
+
+#[test]
+fn delay_waits_for_a_token() {
+    clock::freeze();
+    let limit = RateLimit::new(4.0, 1, RateLimitAction::Delay);
+    let mut limiter = limit.limiter();
+    let start = clock::now();
+
+    assert_eq!(limiter.admit(), Admit::Accept);
+    assert_eq!(limiter.admit(), Admit::Accept);
+    assert_eq!(limiter.admit(), Admit::Accept);
+    assert_eq!(clock::now() - start, Duration::from_millis(500));
+    assert_eq!(limit.counters().delayed(), 2);
+    clock::unfreeze();
+}
@@ -0,0 +1,111 @@
+// sessions that follow a browser across page loads and reconnects by a cookie
+
+use std::time::Duration;
+
+use http::HttpRequest;
+use server::{clock, SessionId, SessionStore};
+
+fn request(cookie: Option<&str>) -> HttpRequest {
+    let cookie = cookie.map(|c| format!("Cookie: {}\r\n", c)).unwrap_or_default();
+    format!("GET / HTTP/1.1\r\nHost: ethan.ws\r\n{}\r\n", cookie).parse().unwrap()
+}
+
+fn start(store: &SessionStore) -> (SessionId, String) {
+    // and the Cookie header the browser would send from now on
+    let (id, set_cookie) = store.resume_or_start(&request(None));
+    let set_cookie = set_cookie.expect("a new session sets a cookie").to_string();
+    (id, set_cookie.split(';').next().unwrap().to_string())
+}
+
+fn resume(store: &SessionStore, cookie: &str) -> Option<SessionId> {
+    // None if the browser had to be given a new session
+    match store.resume_or_start(&request(Some(cookie))) {
+        (id, None) => Some(id),
+        (_, Some(_)) => None,
+    }
+}
+
+#[test]
+fn new_browsers_get_a_cookie() {
+    clock::freeze();
+    let store = SessionStore::new();
+
+    let (first, set_cookie) = store.resume_or_start(&request(None));
+    let set_cookie = set_cookie.unwrap().to_string();
+    assert!(set_cookie.starts_with("session="));
+    assert!(set_cookie.ends_with("; Path=/; Max-Age=86400; HttpOnly; SameSite=Lax"));
+
+    let (second, other_cookie) = store.resume_or_start(&request(Some("theme=dark")));
+    assert_ne!(first, second);
+    assert_ne!(other_cookie.unwrap().to_string(), set_cookie);
+    assert_eq!(store.len(), 2);
+    clock::unfreeze();
+}
+
+#[test]
+fn the_cookie_resumes_the_session() {
+    clock::freeze();
+    let store = SessionStore::new();
+    let (id, cookie) = start(&store);
+
+    assert_eq!(resume(&store, &cookie), Some(id));
+    assert_eq!(resume(&store, &format!("theme=dark; {}", cookie)), Some(id));
+    assert_eq!(resume(&store, "session=0123456789abcdef0123456789abcdef"), None);
+    assert_eq!(store.len(), 2);
+    clock::unfreeze();
+}
+
+#[test]
+fn sessions_expire_a_day_after_they_were_last_seen() {
+    clock::freeze();
+    let store = SessionStore::new();
+    let (idle, idle_cookie) = start(&store);
+    let (active, active_cookie) = start(&store);
+
+    clock::advance(Duration::from_secs(23 * 60 * 60));
+    assert_eq!(resume(&store, &active_cookie), Some(active));
+
+    clock::advance(Duration::from_secs(2 * 60 * 60));
+    store.expire_old();
+    assert_eq!(store.len(), 1);
+    assert_eq!(resume(&store, &active_cookie), Some(active));
+
+    let (new, _) = store.resume_or_start(&request(Some(&idle_cookie)));
+    assert_ne!(new, idle);
+    clock::unfreeze();
+}
+
+#[test]
+fn a_full_store_forgets_the_longest_unseen() {
+    clock::freeze();
+    let store = SessionStore::with_max_sessions(2);
+    let (first, first_cookie) = start(&store);
+    clock::advance(Duration::from_secs(1));
+    let (_, second_cookie) = start(&store);
+    clock::advance(Duration::from_secs(1));
+    assert_eq!(resume(&store, &first_cookie), Some(first));
+
+    clock::advance(Duration::from_secs(1));
+    let (third, third_cookie) = start(&store);
+    assert_eq!(store.len(), 2);
+    assert_eq!(resume(&store, &first_cookie), Some(first));
+    assert_eq!(resume(&store, &third_cookie), Some(third));
+    assert_eq!(resume(&store, &second_cookie), None);
+    clock::unfreeze();
+}
+
+#[test]
+fn sessions_seen_at_the_same_time_are_forgotten_in_the_order_they_started() {
+    clock::freeze();
+    let store = SessionStore::with_max_sessions(2);
+    let (_, first_cookie) = start(&store);
+    let (second, second_cookie) = start(&store);
+    let (third, third_cookie) = start(&store);
+
+    assert_eq!(resume(&store, &third_cookie), Some(third));
+    assert_eq!(resume(&store, &second_cookie), Some(second));
+    assert_eq!(resume(&store, &first_cookie), None);
+    assert_eq!(resume(&store, &third_cookie), Some(third));
+    assert_eq!(store.len(), 2);
+    clock::unfreeze();
+}
@@ -0,0 +1,37 @@
+// the TestHarness itself, and the clock it stops
+
+use std::time::Duration;
+
+use server::{clock, Disconnect, GlobalState, PeerId, SessionId, TestHarness};
+use web_socket::{CloseFrame, WebSocketMessage, WebSocketWriter};
+
+struct Nobody;
+
+impl GlobalState for Nobody {
+    fn new_peer(&mut self, _id: PeerId, _session: SessionId, _writer: WebSocketWriter, _subprotocol: Option<&str>) {}
+    fn on_message_receive(&mut self, _id: PeerId, _message: WebSocketMessage) -> Result<(), Disconnect> { Ok(()) }
+    fn on_disconnect(&mut self, _id: PeerId, _close_frame: &CloseFrame) {}
+    fn periodic(&mut self) {}
+}
+
+#[test]
+fn harnesses_nest_on_one_thread() {
+    let mut outer = TestHarness::new(Nobody);
+    let start = clock::now();
+    outer.advance(Duration::from_secs(5));
+
+    {
+        let mut inner = TestHarness::new(Nobody);
+        assert_eq!(clock::now(), start + Duration::from_secs(5));
+        inner.advance(Duration::from_secs(1));
+    }
+
+    // the outer harness still has the clock stopped where the inner one left it
+    assert_eq!(clock::now(), start + Duration::from_secs(6));
+    outer.advance(Duration::from_secs(1));
+    assert_eq!(clock::now(), start + Duration::from_secs(7));
+
+    // and once it's gone, it's back to real time, which hasn't gotten that far
+    drop(outer);
+    assert!(clock::now() < start + Duration::from_secs(5));
+}
@@ -0,0 +1,100 @@
+use crate::util::FrameKind;
+use crate::listener::{WebSocketMessage, Frame};
+use crate::close::{CloseFrame, CloseCode};
+use crate::deflate::Inflater;
+
+// the most we'll accept from a peer, so a length header can't make us allocate without bound
+#[derive(Copy, Clone, Debug)]
+pub struct Limits {
+    pub max_frame_size: usize,
+    pub max_message_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits { max_frame_size: 1 << 24, max_message_size: 1 << 24 }
+    }
+}
+
+// turns frames into messages. control frames can show up in the middle of a fragmented message,
+// so they're handed back right away instead of being mixed into the message
+pub enum Received {
+    Message(WebSocketMessage),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(CloseFrame),
+    Nothing, // part of a message that isn't finished yet
+}
+
+#[derive(Default)]
+pub struct MessageAssembler {
+    pub limits: Limits,
+    pub inflater: Option<Inflater>, // set once permessage-deflate is negotiated
+    partial: Option<(FrameKind, bool, Vec<u8>)>, // the message's kind, whether it's compressed, and what we have so far
+}
+
+impl MessageAssembler {
+    pub fn push(&mut self, frame: Frame) -> Result<Received, CloseFrame> {
+        // an error is the close frame we should answer with
+        let Frame { is_last_frame, compressed, frame_kind, payload } = frame;
+
+        if compressed {
+            match frame_kind {
+                FrameKind::Text | FrameKind::Binary if self.inflater.is_some() => {},
+                FrameKind::Text | FrameKind::Binary => return Err(CloseFrame::new(CloseCode::ProtocolError, "reserved bits must be 0")),
+                _ => return Err(CloseFrame::new(CloseCode::ProtocolError, "only the first frame of a data message can be compressed")),
+            }
+        }
+
+        match frame_kind {
+            FrameKind::Ping => return Ok(Received::Ping(payload)),
+            FrameKind::Pong => return Ok(Received::Pong(payload)),
+            FrameKind::Close => return CloseFrame::from_payload(&payload).map(Received::Close),
+            FrameKind::Text | FrameKind::Binary => {
+                if self.partial.is_some() {
+                    return Err(CloseFrame::new(CloseCode::ProtocolError, "new message started before the last one finished"));
+                }
+                self.check_size(payload.len())?;
+                self.partial = Some((frame_kind, compressed, payload));
+            },
+            FrameKind::Continue => {
+                let so_far = match self.partial {
+                    Some((_, _, ref message)) => message.len(),
+                    None => return Err(CloseFrame::new(CloseCode::ProtocolError, "continuation frame without a message to continue")),
+                };
+                self.check_size(so_far + payload.len())?;
+                if let Some((_, _, ref mut message)) = self.partial {
+                    message.extend_from_slice(&payload);
+                }
+            },
+        }
+
+        if !is_last_frame { return Ok(Received::Nothing) }
+
+        let (frame_kind, message) = match self.partial.take() {
+            Some((frame_kind, false, message)) => (frame_kind, message),
+            Some((frame_kind, true, message)) => {
+                let inflater = self.inflater.as_mut().expect("checked when the message started");
+                (frame_kind, inflater.inflate(&message, self.limits.max_message_size)?)
+            },
+            None => unreachable!("data frames always leave a partial message"),
+        };
+
+        Ok(match frame_kind {
+            FrameKind::Text => {
+                let text = String::from_utf8(message)
+                    .map_err(|_| CloseFrame::new(CloseCode::InvalidPayload, "text message isn't utf8"))?;
+                Received::Message(WebSocketMessage::Text(text))
+            },
+            _ => Received::Message(WebSocketMessage::Binary(message)),
+        })
+    }
+
+    fn check_size(&self, message_len: usize) -> Result<(), CloseFrame> {
+        if message_len > self.limits.max_message_size {
+            Err(CloseFrame::new(CloseCode::TooBig, "message too big"))
+        } else {
+            Ok(())
+        }
+    }
+}
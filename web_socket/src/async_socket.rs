@@ -0,0 +1,192 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+
+use crate::assembler::{MessageAssembler, Received, Limits};
+use crate::close::{CloseFrame, CloseCode};
+use crate::deflate::Deflate;
+use crate::listener::{decode_frame, WebSocketMessage};
+use crate::util::{FrameKind, Role};
+use crate::writer::FrameEncoder;
+
+const READ_CHUNK_SIZE: usize = 8192;
+
+type PongHandler = Box<dyn FnMut(&[u8])+Send>;
+
+// a listener and writer in one, for a connection that's finished its handshake inside an async
+// runtime. it's a Stream of what the peer sends and a Sink for what we send. pings get answered
+// while the stream is being polled, so something should always be polling it
+pub struct AsyncWebSocket<S> {
+    stream: S,
+    assembler: MessageAssembler,
+    encoder: FrameEncoder,
+    on_pong: Option<PongHandler>,
+    read_buf: Vec<u8>, // never more than a frame and a chunk, since frames are checked against the limit first
+    write_buf: Vec<u8>, // frames the stream hasn't taken yet
+    close_frame: Option<CloseFrame>,
+    sent_close: bool,
+}
+
+impl<S: AsyncRead+AsyncWrite+Unpin> AsyncWebSocket<S> {
+    pub fn new(stream: S) -> AsyncWebSocket<S> {
+        // the server side of the connection, like `split`
+        AsyncWebSocket {
+            stream,
+            assembler: MessageAssembler::default(),
+            encoder: FrameEncoder::new(Role::Server),
+            on_pong: None,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            close_frame: None,
+            sent_close: false,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.assembler.limits = limits;
+    }
+
+    pub fn set_max_frame_size(&mut self, max_frame_size: Option<usize>) {
+        // for what we send. messages longer than this get split into several frames
+        self.encoder.set_max_frame_size(max_frame_size);
+    }
+
+    pub fn set_deflate(&mut self, deflate: Deflate) {
+        // call once permessage-deflate has been negotiated, before reading or writing anything
+        self.assembler.inflater = Some(deflate.inflater(self.encoder.role));
+        self.encoder.set_deflate(deflate);
+    }
+
+    pub fn on_pong(&mut self, on_pong: impl FnMut(&[u8])+Send+'static) {
+        self.on_pong = Some(Box::new(on_pong));
+    }
+
+    pub fn close_frame(&self) -> Option<&CloseFrame> {
+        // why the connection ended, once the stream has
+        self.close_frame.as_ref()
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    fn queue_control(&mut self, payload: &[u8], frame_kind: FrameKind) {
+        self.encoder.control(&mut self.write_buf, payload, frame_kind).expect("writing to a vec can't fail");
+    }
+
+    fn queue_close(&mut self, frame: &CloseFrame) {
+        if self.sent_close { return }
+        self.sent_close = true;
+        self.queue_control(&frame.to_payload(), FrameKind::Close);
+    }
+
+    fn close_abnormally(&mut self) {
+        self.close_frame.get_or_insert_with(|| CloseFrame::new(CloseCode::Abnormal, ""));
+    }
+
+    fn poll_write_buf(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while !self.write_buf.is_empty() {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.write_buf))?;
+            if written == 0 { return Poll::Ready(Err(io::ErrorKind::WriteZero.into())) }
+            self.write_buf.drain(..written);
+        }
+
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_fill_read_buf(&mut self, cx: &mut Context) -> Poll<io::Result<usize>> {
+        let old_len = self.read_buf.len();
+        self.read_buf.resize(old_len + READ_CHUNK_SIZE, 0);
+
+        let result = Pin::new(&mut self.stream).poll_read(cx, &mut self.read_buf[old_len..]);
+
+        let read = match result { Poll::Ready(Ok(read)) => read, _ => 0 };
+        self.read_buf.truncate(old_len + read);
+        result
+    }
+}
+
+impl<S: AsyncRead+AsyncWrite+Unpin> Stream for AsyncWebSocket<S> {
+    type Item = WebSocketMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<WebSocketMessage>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.close_frame.is_some() {
+                // send the close frame we owe them, if we do, before ending
+                return this.poll_write_buf(cx).map(|_| None);
+            }
+
+            // pongs go out whenever the stream will take them
+            if let Poll::Ready(Err(_)) = this.poll_write_buf(cx) {
+                this.close_abnormally();
+                continue;
+            }
+
+            let frame = match decode_frame(&mut this.read_buf, this.assembler.limits.max_frame_size, this.encoder.role) {
+                Ok(Some(frame)) => Ok(frame),
+                Ok(None) => {
+                    match ready!(this.poll_fill_read_buf(cx)) {
+                        Ok(0) | Err(_) => this.close_abnormally(),
+                        Ok(_) => {},
+                    }
+                    continue;
+                },
+                Err(protocol_error) => Err(protocol_error),
+            };
+
+            match frame.and_then(|frame| this.assembler.push(frame)) {
+                Ok(Received::Message(message)) => return Poll::Ready(Some(message)),
+                Ok(Received::Nothing) => {},
+                Ok(Received::Pong(payload)) => {
+                    if let Some(ref mut on_pong) = this.on_pong { on_pong(&payload) }
+                },
+                Ok(Received::Ping(payload)) => this.queue_control(&payload, FrameKind::Pong),
+                Ok(Received::Close(frame)) | Err(frame) => {
+                    // echoing their close frame finishes the closing handshake, and sending our own
+                    // starts it when they broke the rules
+                    this.queue_close(&frame);
+                    this.close_frame = Some(frame);
+                },
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead+AsyncWrite+Unpin> Sink<WebSocketMessage> for AsyncWebSocket<S> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        // one message at a time, so a slow peer holds up the sender instead of filling our memory
+        self.get_mut().poll_write_buf(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: WebSocketMessage) -> io::Result<()> {
+        let this = self.get_mut();
+        if this.sent_close {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "web socket was closed"));
+        }
+
+        match message {
+            WebSocketMessage::Text(text) => this.encoder.message(&mut this.write_buf, text.as_bytes(), FrameKind::Text),
+            WebSocketMessage::Binary(bytes) => this.encoder.message(&mut this.write_buf, &bytes, FrameKind::Binary),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().poll_write_buf(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        // sends a normal close frame if we haven't sent one, then closes the stream
+        let this = self.get_mut();
+        this.queue_close(&CloseFrame::new(CloseCode::Normal, ""));
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.stream).poll_close(cx)
+    }
+}
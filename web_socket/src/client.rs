@@ -0,0 +1,147 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use crate::deflate::Deflate;
+use crate::handshake::{accept_key, new_key};
+use crate::listener::WebSocketListener;
+use crate::stream::SharedStream;
+use crate::util::Role;
+use crate::writer::WebSocketWriter;
+
+// https://tools.ietf.org/html/rfc6455#section-4.1
+
+const MAX_RESPONSE_SIZE: usize = 8192;
+
+// the other end of our server, for bots and tests that need to speak real web sockets
+pub struct WebSocketClient {
+    host: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    deflate: Option<Deflate>,
+}
+
+impl WebSocketClient {
+    pub fn new(host: &str, path: &str) -> WebSocketClient {
+        // host is something like "localhost:8080", and path something like "/pusoy"
+        WebSocketClient { host: host.to_string(), path: path.to_string(), headers: Vec::new(), deflate: None }
+    }
+
+    pub fn header(&mut self, name: &str, value: &str) -> &mut WebSocketClient {
+        // for things like Origin or Cookie
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn deflate(&mut self, deflate: Deflate) -> &mut WebSocketClient {
+        // offer permessage-deflate, which the server may or may not take us up on
+        self.deflate = Some(deflate);
+        self
+    }
+
+    pub fn connect(&self) -> Result<(WebSocketListener<SharedStream<TcpStream>>, WebSocketWriter), ClientError> {
+        let tcp_stream = TcpStream::connect(&self.host)?;
+        self.handshake(SharedStream::new(tcp_stream))
+    }
+
+    pub fn handshake<S>(&self, mut stream: S) -> Result<(WebSocketListener<S>, WebSocketWriter), ClientError>
+        where S: Read+Write+Clone+Send+'static
+    {
+        // for when you already have a connection to the server, like one wrapped in tls
+        let key = new_key();
+
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n",
+            self.path, self.host, key,
+        );
+        if let Some(ref deflate) = self.deflate {
+            request.push_str(&format!("Sec-WebSocket-Extensions: {}\r\n", deflate.offer()));
+        }
+        for (name, value) in &self.headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+
+        stream.write_all(request.as_bytes())?;
+
+        let response = read_response(&mut stream)?;
+        let mut lines = response.split("\r\n");
+
+        let status_line = lines.next().unwrap_or("");
+        if status_line.split(' ').nth(1) != Some("101") {
+            return Err(ClientError::Rejected(status_line.to_string()));
+        }
+
+        let headers: Vec<(&str, &str)> = lines
+            .filter_map(|line| {
+                let colon = line.find(':')?;
+                Some((line[..colon].trim(), line[colon+1..].trim()))
+            })
+            .collect();
+        let header = |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|&(_, value)| value);
+
+        if !header("Upgrade").map_or(false, |value| value.eq_ignore_ascii_case("websocket")) {
+            return Err(ClientError::BadResponse("Upgrade header must be websocket"));
+        }
+        if !header("Connection").map_or(false, |value| value.split(',').any(|t| t.trim().eq_ignore_ascii_case("upgrade"))) {
+            return Err(ClientError::BadResponse("Connection header must include Upgrade"));
+        }
+        if header("Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+            return Err(ClientError::BadResponse("wrong Sec-WebSocket-Accept"));
+        }
+
+        let deflate = match header("Sec-WebSocket-Extensions") {
+            // the server can only agree to extensions we offered
+            Some(extensions) if self.deflate.is_some() => Some(Deflate::from_response(extensions)
+                .ok_or(ClientError::BadResponse("server agreed to an extension we didn't offer"))?),
+            Some(_) => return Err(ClientError::BadResponse("server agreed to an extension we didn't offer")),
+            None => None,
+        };
+
+        let mut writer = WebSocketWriter::with_role(stream.clone(), Role::Client);
+        let mut listener = WebSocketListener::new(stream, writer.clone());
+        if let Some(deflate) = deflate {
+            writer.set_deflate(deflate);
+            listener.set_deflate(deflate);
+        }
+
+        Ok((listener, writer))
+    }
+}
+
+fn read_response(stream: &mut impl Read) -> Result<String, ClientError> {
+    // a byte at a time, so we don't read past the headers into the server's first frames
+    let mut response = Vec::new();
+    let mut byte = [0u8; 1];
+
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() == MAX_RESPONSE_SIZE {
+            return Err(ClientError::BadResponse("response headers too long"));
+        }
+        stream.read_exact(&mut byte)?;
+        response.push(byte[0]);
+    }
+
+    String::from_utf8(response).map_err(|_| ClientError::BadResponse("response headers aren't utf8"))
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    Rejected(String), // the server answered with something other than 101, like "HTTP/1.1 404 Not Found"
+    BadResponse(&'static str),
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> ClientError { ClientError::Io(e) }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ClientError::Io(ref e) => write!(f, "{}", e),
+            ClientError::Rejected(ref status_line) => write!(f, "server rejected the handshake: {}", status_line),
+            ClientError::BadResponse(reason) => write!(f, "invalid handshake response: {}", reason),
+        }
+    }
+}
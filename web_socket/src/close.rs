@@ -0,0 +1,122 @@
+use std::fmt;
+
+// https://tools.ietf.org/html/rfc6455#section-7.4
+
+const MAX_REASON_LEN: usize = 123; // control frames can't be longer than 125 bytes, and the code takes 2
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CloseCode {
+    Normal,
+    GoingAway,
+    ProtocolError,
+    Unsupported,
+    NoStatus, // never sent, just what we report when a close frame was empty
+    Abnormal, // never sent, just what we report when the connection dropped without a close frame
+    InvalidPayload,
+    PolicyViolation,
+    TooBig,
+    MandatoryExtension,
+    InternalError,
+    Other(u16),
+}
+
+impl CloseCode {
+    pub fn from_u16(code: u16) -> CloseCode {
+        match code {
+            1000 => CloseCode::Normal,
+            1001 => CloseCode::GoingAway,
+            1002 => CloseCode::ProtocolError,
+            1003 => CloseCode::Unsupported,
+            1005 => CloseCode::NoStatus,
+            1006 => CloseCode::Abnormal,
+            1007 => CloseCode::InvalidPayload,
+            1008 => CloseCode::PolicyViolation,
+            1009 => CloseCode::TooBig,
+            1010 => CloseCode::MandatoryExtension,
+            1011 => CloseCode::InternalError,
+            n => CloseCode::Other(n),
+        }
+    }
+
+    fn allowed_on_wire(self) -> bool {
+        // the codes a peer may actually put in a close frame
+        match self {
+            CloseCode::NoStatus | CloseCode::Abnormal => false,
+            CloseCode::Other(n) => (1012..=1014).contains(&n) || (3000..=4999).contains(&n),
+            _ => true,
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::Unsupported => 1003,
+            CloseCode::NoStatus => 1005,
+            CloseCode::Abnormal => 1006,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::TooBig => 1009,
+            CloseCode::MandatoryExtension => 1010,
+            CloseCode::InternalError => 1011,
+            CloseCode::Other(n) => n,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CloseFrame {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    pub fn new(code: CloseCode, reason: &str) -> CloseFrame {
+        let mut end = reason.len().min(MAX_REASON_LEN);
+        while !reason.is_char_boundary(end) { end -= 1 }
+
+        CloseFrame { code, reason: reason[..end].to_string() }
+    }
+
+    pub(crate) fn from_payload(payload: &[u8]) -> Result<CloseFrame, CloseFrame> {
+        // an error is the close frame we should answer with
+        match *payload {
+            [] => Ok(CloseFrame::new(CloseCode::NoStatus, "")),
+            [_] => Err(CloseFrame::new(CloseCode::ProtocolError, "close payload can't be a single byte")),
+            [a, b, ref reason @ ..] => {
+                let code = CloseCode::from_u16(u16::from_be_bytes([a, b]));
+                if !code.allowed_on_wire() {
+                    return Err(CloseFrame::new(CloseCode::ProtocolError, "invalid close code"));
+                }
+
+                let reason = String::from_utf8(reason.to_vec())
+                    .map_err(|_| CloseFrame::new(CloseCode::InvalidPayload, "close reason isn't utf8"))?;
+
+                Ok(CloseFrame { code, reason })
+            },
+        }
+    }
+
+    pub(crate) fn to_payload(&self) -> Vec<u8> {
+        match self.code {
+            // these two aren't allowed on the wire, so they turn into an empty close frame
+            CloseCode::NoStatus | CloseCode::Abnormal => Vec::new(),
+            code => {
+                let mut payload = code.to_u16().to_be_bytes().to_vec();
+                payload.extend_from_slice(self.reason.as_bytes());
+                payload
+            },
+        }
+    }
+}
+
+impl fmt::Display for CloseFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.reason.is_empty() {
+            write!(f, "{}", self.code.to_u16())
+        } else {
+            write!(f, "{} ({})", self.code.to_u16(), self.reason)
+        }
+    }
+}
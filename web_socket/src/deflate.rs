@@ -0,0 +1,209 @@
+use flate2::{Compress, Decompress, Compression, FlushCompress, FlushDecompress, Status};
+
+use crate::close::{CloseFrame, CloseCode};
+use crate::util::Role;
+
+// https://tools.ietf.org/html/rfc7692
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+const SYNC_FLUSH_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+const MAX_WINDOW_BITS: u8 = 15; // the only window our deflate implementation supports
+
+// the permessage-deflate parameters on one connection. as a server setting, these are what we
+// ask for. a client can ask for either of them too, and we always agree
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Deflate {
+    // start every message we send with a fresh compressor. costs compression ratio, but we don't
+    // have to keep a 32kb window around for each peer between messages
+    pub server_no_context_takeover: bool,
+    // ask the client to do the same for the messages it sends
+    pub client_no_context_takeover: bool,
+}
+
+impl Deflate {
+    pub fn negotiate(&self, offers: &str) -> Option<(Deflate, String)> {
+        // `offers` is the client's Sec-WebSocket-Extensions header. returns what we agreed on and
+        // the header we should respond with, or None if there was no offer we could accept
+        offers.split(',').find_map(|offer| self.accept_offer(offer))
+    }
+
+    pub(crate) fn offer(&self) -> String {
+        // what a client puts in its Sec-WebSocket-Extensions header
+        let mut offer = EXTENSION_NAME.to_string();
+        if self.server_no_context_takeover { offer.push_str("; server_no_context_takeover") }
+        if self.client_no_context_takeover { offer.push_str("; client_no_context_takeover") }
+        offer
+    }
+
+    pub(crate) fn from_response(response: &str) -> Option<Deflate> {
+        // what the server agreed to, from its Sec-WebSocket-Extensions header. we never ask for a
+        // smaller client window, so a response that sets one is invalid
+        let mut params = response.split(';').map(str::trim);
+        if params.next()? != EXTENSION_NAME { return None }
+
+        let mut agreed = Deflate::default();
+        let mut seen = Vec::new();
+
+        for param in params {
+            let (name, value) = match param.find('=') {
+                Some(i) => (param[..i].trim(), Some(param[i+1..].trim().trim_matches('"'))),
+                None => (param, None),
+            };
+
+            if seen.contains(&name) { return None }
+            seen.push(name);
+
+            match (name, value) {
+                ("server_no_context_takeover", None) => agreed.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => agreed.client_no_context_takeover = true,
+                // we inflate any window size
+                ("server_max_window_bits", Some(bits)) => { window_bits(bits)?; },
+                _ => return None,
+            }
+        }
+
+        Some(agreed)
+    }
+
+    pub(crate) fn deflater(&self, role: Role) -> Deflater {
+        match role {
+            Role::Server => Deflater::new(self.server_no_context_takeover),
+            Role::Client => Deflater::new(self.client_no_context_takeover),
+        }
+    }
+
+    pub(crate) fn inflater(&self, role: Role) -> Inflater {
+        match role {
+            Role::Server => Inflater::new(self.client_no_context_takeover),
+            Role::Client => Inflater::new(self.server_no_context_takeover),
+        }
+    }
+
+    fn accept_offer(&self, offer: &str) -> Option<(Deflate, String)> {
+        let mut params = offer.split(';').map(str::trim);
+        if params.next()? != EXTENSION_NAME { return None }
+
+        let mut agreed = *self;
+        let mut server_max_window_bits = false;
+        let mut seen = Vec::new();
+
+        for param in params {
+            let (name, value) = match param.find('=') {
+                Some(i) => (param[..i].trim(), Some(param[i+1..].trim().trim_matches('"'))),
+                None => (param, None),
+            };
+
+            // a parameter showing up twice makes the whole offer invalid
+            if seen.contains(&name) { return None }
+            seen.push(name);
+
+            match (name, value) {
+                ("server_no_context_takeover", None) => agreed.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => agreed.client_no_context_takeover = true,
+                ("server_max_window_bits", Some(bits)) => {
+                    // we can't shrink our window, so we can only take this offer if it doesn't ask us to
+                    if window_bits(bits)? != MAX_WINDOW_BITS { return None }
+                    server_max_window_bits = true;
+                },
+                ("client_max_window_bits", bits) => {
+                    // they're just telling us they could use a smaller window. we inflate any size
+                    if let Some(bits) = bits { window_bits(bits)?; }
+                },
+                _ => return None,
+            }
+        }
+
+        let mut response = EXTENSION_NAME.to_string();
+        if agreed.server_no_context_takeover { response.push_str("; server_no_context_takeover") }
+        if agreed.client_no_context_takeover { response.push_str("; client_no_context_takeover") }
+        if server_max_window_bits { response.push_str(&format!("; server_max_window_bits={}", MAX_WINDOW_BITS)) }
+
+        Some((agreed, response))
+    }
+}
+
+fn window_bits(bits: &str) -> Option<u8> {
+    match bits.parse() {
+        Ok(bits @ 8..=15) => Some(bits),
+        _ => None,
+    }
+}
+
+pub struct Deflater {
+    compress: Compress,
+    no_context_takeover: bool,
+}
+
+impl Deflater {
+    pub fn new(no_context_takeover: bool) -> Deflater {
+        Deflater { compress: Compress::new(Compression::default(), false), no_context_takeover }
+    }
+
+    pub fn no_context_takeover(&self) -> bool {
+        self.no_context_takeover
+    }
+
+    pub fn deflate(&mut self, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(payload.len() / 2 + 64);
+        let mut consumed = 0;
+
+        loop {
+            if out.capacity() - out.len() < 64 { out.reserve(out.capacity()) }
+
+            let before = self.compress.total_in();
+            self.compress.compress_vec(&payload[consumed..], &mut out, FlushCompress::Sync)
+                .expect("compressing into a buffer can't fail");
+            consumed += (self.compress.total_in() - before) as usize;
+
+            // the flush is only done once it had room to spare
+            if consumed == payload.len() && out.len() < out.capacity() { break }
+        }
+
+        // a sync flush always ends with an empty block, which the peer adds back before inflating
+        if out.ends_with(&SYNC_FLUSH_TAIL) { out.truncate(out.len() - SYNC_FLUSH_TAIL.len()) }
+
+        if self.no_context_takeover { self.compress.reset() }
+
+        out
+    }
+}
+
+pub struct Inflater {
+    decompress: Decompress,
+    no_context_takeover: bool,
+}
+
+impl Inflater {
+    pub fn new(no_context_takeover: bool) -> Inflater {
+        Inflater { decompress: Decompress::new(false), no_context_takeover }
+    }
+
+    pub fn inflate(&mut self, payload: &[u8], max_len: usize) -> Result<Vec<u8>, CloseFrame> {
+        // max_len keeps a small message from inflating into something huge
+        let mut input = payload.to_vec();
+        input.extend_from_slice(&SYNC_FLUSH_TAIL);
+
+        let mut out = Vec::with_capacity((payload.len() * 4).min(max_len) + 64);
+        let mut consumed = 0;
+
+        loop {
+            if out.capacity() - out.len() < 64 { out.reserve(out.capacity()) }
+
+            let (before_in, before_out) = (self.decompress.total_in(), self.decompress.total_out());
+            let status = self.decompress.decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|_| CloseFrame::new(CloseCode::InvalidPayload, "invalid compressed data"))?;
+            consumed += (self.decompress.total_in() - before_in) as usize;
+
+            if out.len() > max_len {
+                return Err(CloseFrame::new(CloseCode::TooBig, "message too big"));
+            }
+
+            let made_progress = self.decompress.total_in() != before_in || self.decompress.total_out() != before_out;
+            if status == Status::StreamEnd || (consumed == input.len() && out.len() < out.capacity()) || !made_progress { break }
+        }
+
+        if self.no_context_takeover { self.decompress.reset(false) }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,29 @@
+use sha1::Sha1;
+use rand::Rng;
+
+use crate::util::{to_base64, from_base64};
+
+// https://tools.ietf.org/html/rfc6455#section-4.2.2
+
+const MAGIC_NUMBER: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub fn accept_key(key: &str) -> String {
+    // what the server answers a Sec-WebSocket-Key with, proving it speaks web sockets
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(MAGIC_NUMBER);
+    to_base64(&hasher.digest().bytes())
+}
+
+pub fn is_valid_key(key: &str) -> bool {
+    // a Sec-WebSocket-Key has to be 16 bytes of base64
+    match from_base64(key) {
+        Some(nonce) => nonce.len() == 16,
+        None => false,
+    }
+}
+
+pub(crate) fn new_key() -> String {
+    let nonce: [u8; 16] = rand::thread_rng().gen();
+    to_base64(&nonce)
+}
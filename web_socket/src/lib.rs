@@ -1,11 +1,27 @@
 mod listener;
 mod writer;
 mod util;
+mod close;
+mod assembler;
+mod deflate;
+mod handshake;
+mod client;
+mod stream;
+#[cfg(feature = "async")]
+mod async_socket;
 
 // https://tools.ietf.org/html/rfc6455
 // https://developer.mozilla.org/en-US/docs/Web/API/WebSockets_API/Writing_WebSocket_servers
 
 pub use listener::{WebSocketMessage, WebSocketListener};
-pub use writer::WebSocketWriter;
+pub use writer::{WebSocketWriter, PreparedMessage};
+pub use close::{CloseFrame, CloseCode};
+pub use assembler::Limits;
+pub use deflate::Deflate;
+pub use handshake::{accept_key, is_valid_key};
+pub use client::{WebSocketClient, ClientError};
+pub use stream::{SharedStream, split};
+#[cfg(feature = "async")]
+pub use async_socket::AsyncWebSocket;
 
 
@@ -1,42 +1,108 @@
 use std::io::{Read, BufReader};
-use std::net::TcpStream;
 
-use crate::util::{FrameKind};
-use crate::writer::write_frame;
+use crate::util::{FrameKind, Role};
+use crate::writer::WebSocketWriter;
+use crate::close::{CloseFrame, CloseCode};
+use crate::assembler::{MessageAssembler, Received, Limits};
+use crate::deflate::Deflate;
 use std::io;
 
-pub struct WebSocketListener {
-    reader: BufReader<TcpStream>,
+type PongHandler = Box<dyn FnMut(&[u8])+Send>;
+
+pub struct WebSocketListener<R> {
+    reader: BufReader<R>,
+    writer: WebSocketWriter, // for answering pings and close frames
+    assembler: MessageAssembler,
+    on_pong: Option<PongHandler>,
+    close_frame: Option<CloseFrame>,
 }
 
-impl WebSocketListener {
-    pub fn new(tcp_stream: TcpStream) -> WebSocketListener {
-        WebSocketListener { reader: BufReader::new(tcp_stream) }
+impl<R: Read> WebSocketListener<R> {
+    pub fn new(reader: R, writer: WebSocketWriter) -> WebSocketListener<R> {
+        // the writer should go to the same peer, and is usually a clone of the one the app gets
+        WebSocketListener {
+            reader: BufReader::new(reader),
+            writer,
+            assembler: MessageAssembler::default(),
+            on_pong: None,
+            close_frame: None,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.assembler.limits = limits;
+    }
+
+    pub fn set_deflate(&mut self, deflate: Deflate) {
+        // call once permessage-deflate has been negotiated, before reading anything
+        self.assembler.inflater = Some(deflate.inflater(self.writer.role()));
+    }
+
+    pub fn on_pong(&mut self, on_pong: impl FnMut(&[u8])+Send+'static) {
+        // called with the payload of every pong, which is how you find out a ping was answered
+        self.on_pong = Some(Box::new(on_pong));
+    }
+
+    pub fn close_frame(&self) -> Option<&CloseFrame> {
+        // why the connection ended, once it has
+        self.close_frame.as_ref()
+    }
+
+    pub fn close(&mut self, frame: CloseFrame) -> io::Result<()> {
+        // start the closing handshake from our side. the listener won't yield any more messages
+        if self.close_frame.is_some() { return Ok(()) }
+
+        let result = self.writer.close(frame.code, &frame.reason);
+        self.close_frame = Some(frame);
+        result
+    }
+
+    fn close_abnormally(&mut self) -> Option<WebSocketMessage> {
+        self.close_frame.get_or_insert_with(|| CloseFrame::new(CloseCode::Abnormal, ""));
+        None
     }
 }
 
-impl Iterator for WebSocketListener {
+impl<R: Read> Iterator for WebSocketListener<R> {
     type Item = WebSocketMessage;
 
     fn next(&mut self) -> Option<WebSocketMessage> {
+        if self.close_frame.is_some() { return None }
+
         loop {
-            let (message, kind) = read_next_message(&mut self.reader).ok()?;
-
-            match kind {
-                FrameKind::Binary => break Some(WebSocketMessage::Binary(message)),
-                FrameKind::Text => break Some(WebSocketMessage::Text(String::from_utf8_lossy(&message).into())),
-                FrameKind::Continue => break None,
-                FrameKind::Close => break None,
-                FrameKind::Ping => {
-                    write_frame(self.reader.get_mut(), &message, FrameKind::Pong).ok()?;
-                    continue
+            let frame = match read_next_frame(&mut self.reader, self.assembler.limits.max_frame_size, self.writer.role()) {
+                Ok(frame) => Ok(frame),
+                Err(ReadError::Protocol(close_frame)) => Err(close_frame),
+                Err(ReadError::Io) => break self.close_abnormally(),
+            };
+
+            match frame.and_then(|frame| self.assembler.push(frame)) {
+                Ok(Received::Message(message)) => break Some(message),
+                Ok(Received::Nothing) => continue,
+                Ok(Received::Pong(payload)) => {
+                    if let Some(ref mut on_pong) = self.on_pong { on_pong(&payload) }
+                },
+                Ok(Received::Ping(payload)) => {
+                    if self.writer.pong(&payload).is_err() {
+                        break self.close_abnormally();
+                    }
+                },
+                Ok(Received::Close(frame)) => {
+                    // echo their close frame back, which finishes the closing handshake
+                    let _ = self.writer.close(frame.code, &frame.reason);
+                    self.close_frame = Some(frame);
+                    break None
+                },
+                Err(protocol_error) => {
+                    let _ = self.close(protocol_error);
+                    break None
                 },
-                FrameKind::Pong => continue,
             }
         }
     }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum WebSocketMessage {
     Text(String),
     Binary(Vec<u8>),
@@ -51,82 +117,157 @@ impl WebSocketMessage {
     }
 }
 
-fn read_next_message(reader: &mut impl Read) -> io::Result<(Vec<u8>, FrameKind)> {
-    // blocks the current thread until we receive a full message from the client
+enum ReadError {
+    Io, // the connection dropped, or the peer stopped partway through a frame
+    Protocol(CloseFrame), // the peer broke the rules, and this is what we should close with
+}
+
+impl From<io::Error> for ReadError {
+    fn from(_: io::Error) -> ReadError { ReadError::Io }
+}
 
-    let mut buf = Vec::new();
+impl From<CloseFrame> for ReadError {
+    fn from(close_frame: CloseFrame) -> ReadError { ReadError::Protocol(close_frame) }
+}
 
-    let Frame { mut is_last_frame, frame_kind } = read_next_frame(reader, &mut buf)?;
+fn protocol_error(reason: &str) -> CloseFrame {
+    CloseFrame::new(CloseCode::ProtocolError, reason)
+}
 
-    while !is_last_frame {
-        let Frame { is_last_frame: last, .. } = read_next_frame(reader, &mut buf)?;
+fn read_next_frame(reader: &mut impl Read, max_frame_size: usize, role: Role) -> Result<Frame, ReadError> {
+    // blocks the current thread until we receive a full frame from the other end
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header)?;
+    let start = FrameStart::parse(header, role)?;
 
-        is_last_frame = last;
-    }
+    let mut len = [0u8; 8];
+    let len = &mut len[..start.len_bytes()];
+    reader.read_exact(len)?;
+    let payload_len = start.payload_len(len, max_frame_size)?;
 
-    Ok((buf, frame_kind))
-}
+    let mut masking_key = [0u8; 4];
+    if start.is_masked { reader.read_exact(&mut masking_key)? }
 
+    let mut payload = Vec::new();
+    append_payload(reader, payload_len, masking_key, &mut payload)?;
 
-fn read_next_frame(reader: &mut impl Read, buf: &mut Vec<u8>) -> io::Result<Frame> {
-    // read the first byte from the stream, which tells us if this was the message's last frame and
-    // what kind of frame it was
-    let mut first_byte = [0u8; 1];
-    reader.read_exact(&mut first_byte)?;
-    let [first_byte] = first_byte;
+    Ok(start.into_frame(payload))
+}
 
-    let is_last_frame = (first_byte >> 7) == 1;
-    let frame_kind = FrameKind::from_opcode(first_byte & 0b1111)?;
+#[cfg(feature = "async")]
+pub(crate) fn decode_frame(buf: &mut Vec<u8>, max_frame_size: usize, role: Role) -> Result<Option<Frame>, CloseFrame> {
+    // the same as read_next_frame, for bytes that show up whenever they like. takes a frame off
+    // the front of buf if a whole one is there
+    if buf.len() < 2 { return Ok(None) }
+    let start = FrameStart::parse([buf[0], buf[1]], role)?;
 
-    // extract our payload
-    let payload_length = get_payload_len(reader)?;
+    let len_end = 2 + start.len_bytes();
+    if buf.len() < len_end { return Ok(None) }
+    let payload_len = start.payload_len(&buf[2..len_end], max_frame_size)?;
+
+    let header_len = len_end + if start.is_masked { 4 } else { 0 };
+    if buf.len() < header_len + payload_len { return Ok(None) }
 
     let mut masking_key = [0u8; 4];
-    reader.read_exact(&mut masking_key)?;
+    if start.is_masked { masking_key.copy_from_slice(&buf[len_end..header_len]) }
 
-    append_payload(reader, payload_length, masking_key, buf)?;
+    let mut payload: Vec<u8> = buf.drain(..header_len + payload_len).skip(header_len).collect();
+    unmask(&mut payload, masking_key);
 
-    Ok(Frame { is_last_frame, frame_kind })
+    Ok(Some(start.into_frame(payload)))
 }
 
-fn get_payload_len(reader: &mut impl Read) -> io::Result<usize> {
-    let mut heuristic_byte = [0u8; 1];
-    reader.read_exact(&mut heuristic_byte)?;
-    let [heuristic_byte] = heuristic_byte;
-
-    match heuristic_byte & 0b_0111_1111 {
-        n @ 0..=125 => Ok(n as usize),
-        126 => {
-            let mut len = [0u8; 2];
-            reader.read_exact(&mut len)?;
-            Ok(u16::from_be_bytes(len) as usize)
-        },
-        127 => {
-            let mut len = [0u8; 8];
-            reader.read_exact(&mut len)?;
-            Ok(u64::from_be_bytes(len) as usize)
-        },
-        _ => unreachable!("larger values prevented by bit mask"),
+// the part of a frame's header before its length, which is enough to tell if the frame is allowed
+struct FrameStart {
+    is_last_frame: bool,
+    compressed: bool,
+    frame_kind: FrameKind,
+    is_masked: bool,
+    short_len: u8,
+}
+
+impl FrameStart {
+    fn parse([first_byte, second_byte]: [u8; 2], role: Role) -> Result<FrameStart, CloseFrame> {
+        // the first byte tells us if this was the message's last frame and what kind of frame it was.
+        // the second one has the mask bit and the start of the length
+        let is_last_frame = (first_byte >> 7) == 1;
+        // rsv1 is permessage-deflate's, and whether it's allowed is up to the assembler
+        let compressed = (first_byte >> 6) & 1 == 1;
+        if first_byte & 0b_0011_0000 != 0 {
+            return Err(protocol_error("reserved bits must be 0"));
+        }
+        let frame_kind = FrameKind::from_opcode(first_byte & 0b1111)
+            .ok_or_else(|| protocol_error("unknown opcode"))?;
+
+        let is_masked = (second_byte >> 7) == 1;
+        match role {
+            Role::Server if !is_masked => return Err(protocol_error("frames from the client must be masked")),
+            Role::Client if is_masked => return Err(protocol_error("frames from the server must not be masked")),
+            _ => {},
+        }
+
+        Ok(FrameStart { is_last_frame, compressed, frame_kind, is_masked, short_len: second_byte & 0b_0111_1111 })
+    }
+
+    fn len_bytes(&self) -> usize {
+        // how many bytes of length come after the first two
+        match self.short_len {
+            126 => 2,
+            127 => 8,
+            _ => 0,
+        }
+    }
+
+    fn payload_len(&self, len_bytes: &[u8], max_frame_size: usize) -> Result<usize, CloseFrame> {
+        let payload_len = match *len_bytes {
+            [] => self.short_len as u64,
+            [a, b] => u16::from_be_bytes([a, b]) as u64,
+            [a, b, c, d, e, f, g, h] => {
+                let len = u64::from_be_bytes([a, b, c, d, e, f, g, h]);
+                if len >> 63 == 1 { return Err(protocol_error("most significant bit of the length must be 0")) }
+                len
+            },
+            _ => unreachable!("len_bytes says how many there are"),
+        };
+
+        if self.frame_kind.is_control() && (!self.is_last_frame || payload_len > 125) {
+            return Err(protocol_error("control frames must be unfragmented and at most 125 bytes"));
+        }
+        if payload_len > max_frame_size as u64 {
+            return Err(CloseFrame::new(CloseCode::TooBig, "frame too big"));
+        }
+
+        Ok(payload_len as usize)
+    }
+
+    fn into_frame(self, payload: Vec<u8>) -> Frame {
+        Frame { is_last_frame: self.is_last_frame, compressed: self.compressed, frame_kind: self.frame_kind, payload }
     }
 }
 
 fn append_payload(reader: &mut impl Read, payload_len: usize, masking_key: [u8; 4], buf: &mut Vec<u8>) -> io::Result<()> {
+    // grow as the bytes actually arrive, instead of trusting the length up front
     let old_len = buf.len();
-    buf.resize(old_len+payload_len, 0);
-    let read_into = &mut buf[old_len..];
+    reader.take(payload_len as u64).read_to_end(buf)?;
+    if buf.len() - old_len != payload_len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
 
-    reader.read_exact(read_into)?;
+    unmask(&mut buf[old_len..], masking_key);
 
-    // unmask
-    for (i, byte) in read_into.iter_mut().enumerate() {
+    Ok(())
+}
+
+fn unmask(payload: &mut [u8], masking_key: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
         *byte ^= masking_key[i % 4];
     }
-
-    Ok(())
 }
 
 #[derive(Debug)]
-struct Frame {
-    is_last_frame: bool,
-    frame_kind: FrameKind,
+pub struct Frame {
+    pub is_last_frame: bool,
+    pub compressed: bool,
+    pub frame_kind: FrameKind,
+    pub payload: Vec<u8>,
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+use crate::listener::WebSocketListener;
+use crate::writer::WebSocketWriter;
+
+// a stream that can be read and written from two threads at once, like a TcpStream or a
+// UnixStream, which both work through a shared reference. clones are the same stream
+pub struct SharedStream<S>(Arc<S>);
+
+impl<S> SharedStream<S> {
+    pub fn new(stream: S) -> SharedStream<S> {
+        SharedStream(Arc::new(stream))
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S> Clone for SharedStream<S> {
+    fn clone(&self) -> SharedStream<S> {
+        SharedStream(Arc::clone(&self.0))
+    }
+}
+
+impl<S> Read for SharedStream<S> where for<'a> &'a S: Read {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self.0).read(buf)
+    }
+}
+
+impl<S> Write for SharedStream<S> where for<'a> &'a S: Write {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.0).flush()
+    }
+}
+
+pub fn split<S: Read+Write+Clone+Send+'static>(stream: S) -> (WebSocketListener<S>, WebSocketWriter) {
+    // the server side of a connection that just finished its handshake
+    let writer = WebSocketWriter::new(stream.clone());
+    (WebSocketListener::new(stream, writer.clone()), writer)
+}
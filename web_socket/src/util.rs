@@ -1,5 +1,3 @@
-use std::io;
-
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FrameKind {
     Continue = 0x0,
@@ -11,15 +9,100 @@ pub enum FrameKind {
 }
 
 impl FrameKind {
-    pub fn from_opcode(opcode: u8) -> io::Result<FrameKind> {
-        Ok(match opcode {
+    pub fn from_opcode(opcode: u8) -> Option<FrameKind> {
+        Some(match opcode {
             0x0 => FrameKind::Continue,
             0x1 => FrameKind::Text,
             0x2 => FrameKind::Binary,
             0x8 => FrameKind::Close,
             0x9 => FrameKind::Ping,
             0xA => FrameKind::Pong,
-            _ => return Err(io::Error::from(io::ErrorKind::InvalidData)),
+            _ => return None,
         })
     }
+
+    pub fn is_control(self) -> bool {
+        self as u8 >= 0x8
+    }
+}
+
+// which end of the connection we are. clients mask every frame they send, and servers never do
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Role {
+    Server,
+    Client,
+}
+
+pub(crate) fn to_base64(bytes: &[u8]) -> String {
+    // sets of three bytes are converted into sets of 3 base 64 characters
+    let mut ret = String::with_capacity(4*((bytes.len() + 2) / 3));
+
+    for array in bytes.chunks(3) {
+        let sextets = match *array {
+            [a, b, c] => [Some(a>>2),  Some((a&0b11) << 4 | b >> 4), Some((b&0b1111) << 2 | c>>6), Some(c&0b111111)],
+            [a, b] =>         [Some(a>>2), Some((a&0b11) << 4 | b >> 4), Some((b&0b1111) << 2),        None],
+            [a] =>                [Some(a>>2), Some((a&0b11) << 4),          None,                         None],
+            _ => unreachable!(),
+        };
+
+        for &maybe_byte in sextets.iter() {
+            ret.push(match maybe_byte {
+                Some(byte) => to_base64_char(byte),
+                None => '=',
+            });
+        }
+    }
+
+    ret
+}
+
+fn to_base64_char(n: u8) -> char {
+    match n {
+        0..=25 =>  (n + b'A') as char,
+        26..=51 => (n - 26 + b'a') as char,
+        52..=61 => (n - 52 + b'0') as char,
+        62 => '+',
+        63 => '/',
+        _ => panic!("{} is not in the range [0, 63]", n),
+    }
+}
+
+pub(crate) fn from_base64(string: &str) -> Option<Vec<u8>> {
+    // the inverse of to_base64, and just as strict: padding is required
+    if string.len() % 4 != 0 { return None }
+
+    let mut ret = Vec::with_capacity(3*string.len()/4);
+
+    let quads = string.as_bytes().chunks(4);
+    let last_quad = quads.len().saturating_sub(1);
+
+    for (i, quad) in quads.enumerate() {
+        let sextets = match *quad {
+            _ if i != last_quad && quad.contains(&b'=') => return None,
+            [a, b, b'=', b'='] => [Some(from_base64_char(a)?), Some(from_base64_char(b)?), None, None],
+            [a, b, c, b'='] => [Some(from_base64_char(a)?), Some(from_base64_char(b)?), Some(from_base64_char(c)?), None],
+            [a, b, c, d] => [Some(from_base64_char(a)?), Some(from_base64_char(b)?), Some(from_base64_char(c)?), Some(from_base64_char(d)?)],
+            _ => unreachable!(),
+        };
+
+        match sextets {
+            [Some(a), Some(b), Some(c), Some(d)] => ret.extend_from_slice(&[a<<2 | b>>4, (b&0b1111) << 4 | c>>2, (c&0b11) << 6 | d]),
+            [Some(a), Some(b), Some(c), None] if c&0b11 == 0 => ret.extend_from_slice(&[a<<2 | b>>4, (b&0b1111) << 4 | c>>2]),
+            [Some(a), Some(b), None, None] if b&0b1111 == 0 => ret.push(a<<2 | b>>4),
+            _ => return None,
+        }
+    }
+
+    Some(ret)
+}
+
+fn from_base64_char(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
 }
@@ -1,47 +1,313 @@
-use std::net::TcpStream;
 use std::io;
-use crate::util::{FrameKind};
+use crate::util::{FrameKind, Role};
+use crate::close::{CloseFrame, CloseCode};
+use crate::deflate::{Deflate, Deflater};
+use rand::Rng;
 use std::io::{Write, BufWriter};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 
-#[derive(Debug)]
+// clones write to the same connection, and take turns so their frames never interleave
+#[derive(Clone)]
 pub struct WebSocketWriter {
-    writer: BufWriter<TcpStream>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    writer: BufWriter<Box<dyn Write+Send>>,
+    encoder: FrameEncoder,
+    closed: bool,
+    text_buffer: Vec<u8>, // kept between calls to write_text_with, so it's only allocated once
 }
 
 impl WebSocketWriter {
-    pub fn new(tcp_stream: TcpStream) -> WebSocketWriter {
-        WebSocketWriter { writer: BufWriter::new(tcp_stream) }
+    pub fn new(writer: impl Write+Send+'static) -> WebSocketWriter {
+        // usually a socket, but anything works, like a buffer in a test
+        WebSocketWriter::with_role(writer, Role::Server)
+    }
+
+    pub fn client(writer: impl Write+Send+'static) -> WebSocketWriter {
+        // the other end of the connection, whose frames are masked. a listener made with one
+        // reads what a server sent
+        WebSocketWriter::with_role(writer, Role::Client)
+    }
+
+    pub(crate) fn with_role(writer: impl Write+Send+'static, role: Role) -> WebSocketWriter {
+        let inner = Inner { writer: BufWriter::new(Box::new(writer)), encoder: FrameEncoder::new(role), closed: false, text_buffer: Vec::new() };
+        WebSocketWriter { inner: Arc::new(Mutex::new(inner)) }
+    }
+
+    pub fn set_max_frame_size(&mut self, max_frame_size: Option<usize>) {
+        // messages longer than this get split into several frames. None sends everything in one
+        self.inner().encoder.set_max_frame_size(max_frame_size);
+    }
+
+    pub fn set_deflate(&mut self, deflate: Deflate) {
+        // call once permessage-deflate has been negotiated, and every message after is compressed
+        self.inner().encoder.set_deflate(deflate);
     }
 
     pub fn write_string(&mut self, string: &str) -> io::Result<()> {
-        write_frame(&mut self.writer, string.as_bytes(), FrameKind::Text)
+        let Inner { writer, encoder, closed, .. } = &mut *self.inner();
+        check_open(*closed)?;
+        encoder.message(writer, string.as_bytes(), FrameKind::Text)
     }
 
     pub fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
-        write_frame(&mut self.writer, bytes, FrameKind::Binary)
+        let Inner { writer, encoder, closed, .. } = &mut *self.inner();
+        check_open(*closed)?;
+        encoder.message(writer, bytes, FrameKind::Binary)
+    }
+
+    pub fn write_text_with(&mut self, fill: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> io::Result<()> {
+        // fill writes the message into a buffer this writer reuses, like with a json::JsonWriter,
+        // instead of making a new String for every message. it has to be utf8
+        let mut buffer = std::mem::take(&mut self.inner().text_buffer);
+        buffer.clear();
+
+        // without holding the lock, in case fill uses this writer too
+        let result = fill(&mut buffer).and_then(|()| {
+            std::str::from_utf8(&buffer).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let Inner { writer, encoder, closed, .. } = &mut *self.inner();
+            check_open(*closed)?;
+            encoder.message(writer, &buffer, FrameKind::Text)
+        });
+
+        self.inner().text_buffer = buffer;
+        result
+    }
+
+    pub fn write_prepared(&mut self, message: &PreparedMessage) -> io::Result<()> {
+        // for sending the same message to lots of peers, without encoding it again for each one
+        let Inner { writer, encoder, closed, .. } = &mut *self.inner();
+        check_open(*closed)?;
+        encoder.prepared(writer, message)
+    }
+
+    pub fn ping(&mut self, payload: &[u8]) -> io::Result<()> {
+        // the peer answers with a pong carrying the same payload
+        assert!(payload.len() <= 125, "control frames can't be longer than 125 bytes");
+        let inner = &mut *self.inner();
+        check_open(inner.closed)?;
+        inner.encoder.control(&mut inner.writer, payload, FrameKind::Ping)
+    }
+
+    pub(crate) fn pong(&mut self, payload: &[u8]) -> io::Result<()> {
+        let inner = &mut *self.inner();
+        check_open(inner.closed)?;
+        inner.encoder.control(&mut inner.writer, payload, FrameKind::Pong)
+    }
+
+    pub fn close(&mut self, code: CloseCode, reason: &str) -> io::Result<()> {
+        // the peer answers with their own close frame, which ends their listener
+        let inner = &mut *self.inner();
+        check_open(inner.closed)?;
+        inner.closed = true;
+        inner.encoder.control(&mut inner.writer, &CloseFrame::new(code, reason).to_payload(), FrameKind::Close)
     }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner().closed
+    }
+
+    pub(crate) fn role(&self) -> Role {
+        self.inner().encoder.role
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        // a clone that panicked partway through a frame already broke the connection for everyone,
+        // so there's nothing to protect by refusing to write
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn check_open(closed: bool) -> io::Result<()> {
+    if closed {
+        Err(io::Error::new(io::ErrorKind::NotConnected, "web socket was closed"))
+    } else {
+        Ok(())
+    }
+}
+
+// turns messages into frames for one connection. it doesn't own the connection, so the blocking
+// writer and the async one can both use it
+pub(crate) struct FrameEncoder {
+    pub role: Role,
+    max_frame_size: Option<usize>,
+    deflater: Option<Deflater>,
 }
 
-pub fn write_frame(writer: &mut impl Write, payload: &[u8], frame_kind: FrameKind) -> io::Result<()> {
-    writer.write_all(&[0b_1000_0000 | frame_kind as u8])?;
+impl FrameEncoder {
+    pub fn new(role: Role) -> FrameEncoder {
+        FrameEncoder { role, max_frame_size: None, deflater: None }
+    }
+
+    pub fn set_max_frame_size(&mut self, max_frame_size: Option<usize>) {
+        assert_ne!(max_frame_size, Some(0), "frames have to hold at least one byte");
+        self.max_frame_size = max_frame_size;
+    }
 
-    write_len_header(payload.len(), writer)?;
+    pub fn set_deflate(&mut self, deflate: Deflate) {
+        self.deflater = Some(deflate.deflater(self.role));
+    }
+
+    pub fn message(&mut self, writer: &mut impl Write, payload: &[u8], frame_kind: FrameKind) -> io::Result<()> {
+        match self.deflater {
+            Some(ref mut deflater) => write_message(writer, &deflater.deflate(payload), frame_kind, true, self.max_frame_size, self.role),
+            None => write_message(writer, payload, frame_kind, false, self.max_frame_size, self.role),
+        }
+    }
 
-    writer.write_all(payload)?;
+    pub fn prepared(&mut self, writer: &mut impl Write, message: &PreparedMessage) -> io::Result<()> {
+        let frame = match (&self.deflater, &message.compressed) {
+            // a deflater that keeps its context expects the peer's window to hold everything it
+            // compressed, so a message compressed somewhere else has to go through it instead
+            (Some(deflater), Some(_)) if !deflater.no_context_takeover() => None,
+            (Some(_), Some(compressed)) => Some(compressed),
+            _ => Some(&message.frame),
+        };
+
+        match frame {
+            // clients mask every frame differently, and a frame that's too long has to be split
+            Some(frame) if self.role == Role::Server && self.max_frame_size.map_or(true, |max| frame.payload_len() <= max) => {
+                writer.write_all(&frame.bytes)?;
+                writer.flush()
+            },
+            _ => self.message(writer, message.frame.payload(), message.frame_kind),
+        }
+    }
+
+    pub fn control(&self, writer: &mut impl Write, payload: &[u8], frame_kind: FrameKind) -> io::Result<()> {
+        write_frame(writer, payload, frame_kind, self.role)
+    }
+}
+
+impl fmt::Debug for WebSocketWriter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebSocketWriter").finish()
+    }
+}
+
+// a message that's already been turned into a frame, so it can be written to many peers. the
+// frame is only ever as a server would send it, so a client writer still encodes it itself
+pub struct PreparedMessage {
+    frame_kind: FrameKind,
+    frame: EncodedFrame,
+    compressed: Option<EncodedFrame>, // compressed on its own, for peers that agreed to no context takeover
+}
+
+struct EncodedFrame {
+    bytes: Vec<u8>,
+    header_len: usize,
+}
+
+impl PreparedMessage {
+    pub fn text(string: &str, compress: bool) -> PreparedMessage {
+        PreparedMessage::new(string.as_bytes(), FrameKind::Text, compress)
+    }
+
+    pub fn binary(bytes: &[u8], compress: bool) -> PreparedMessage {
+        PreparedMessage::new(bytes, FrameKind::Binary, compress)
+    }
+
+    fn new(payload: &[u8], frame_kind: FrameKind, compress: bool) -> PreparedMessage {
+        let compressed = if compress {
+            Some(EncodedFrame::new(&Deflater::new(true).deflate(payload), frame_kind, true))
+        } else {
+            None
+        };
+
+        PreparedMessage { frame_kind, frame: EncodedFrame::new(payload, frame_kind, false), compressed }
+    }
+}
+
+impl fmt::Debug for PreparedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PreparedMessage")
+            .field("frame_kind", &self.frame_kind)
+            .field("len", &self.frame.payload_len())
+            .field("compressed", &self.compressed.is_some())
+            .finish()
+    }
+}
+
+impl EncodedFrame {
+    fn new(payload: &[u8], frame_kind: FrameKind, compressed: bool) -> EncodedFrame {
+        let mut bytes = Vec::with_capacity(payload.len() + 10);
+        write_fragment(&mut bytes, payload, frame_kind, true, compressed, Role::Server)
+            .expect("writing to a vec can't fail");
+
+        EncodedFrame { header_len: bytes.len() - payload.len(), bytes }
+    }
+
+    fn payload(&self) -> &[u8] {
+        &self.bytes[self.header_len..]
+    }
+
+    fn payload_len(&self) -> usize {
+        self.bytes.len() - self.header_len
+    }
+}
+
+fn write_frame(writer: &mut impl Write, payload: &[u8], frame_kind: FrameKind, role: Role) -> io::Result<()> {
+    write_fragment(writer, payload, frame_kind, true, false, role)?;
 
     writer.flush()
 }
 
-fn write_len_header(len: usize, writer: &mut impl Write) -> io::Result<()> {
+fn write_message(writer: &mut impl Write, payload: &[u8], frame_kind: FrameKind, compressed: bool, max_frame_size: Option<usize>, role: Role) -> io::Result<()> {
+    let max_frame_size = match max_frame_size {
+        Some(max) if payload.len() > max => max,
+        _ => {
+            write_fragment(writer, payload, frame_kind, true, compressed, role)?;
+            return writer.flush();
+        },
+    };
+
+    // the first frame says what kind of message this is, and the rest are continuations
+    let frame_count = payload.chunks(max_frame_size).len();
+
+    for (i, chunk) in payload.chunks(max_frame_size).enumerate() {
+        // and only the first one says whether the message is compressed
+        let (kind, compressed) = if i == 0 { (frame_kind, compressed) } else { (FrameKind::Continue, false) };
+        write_fragment(writer, chunk, kind, i == frame_count-1, compressed, role)?;
+    }
+
+    writer.flush()
+}
+
+fn write_fragment(writer: &mut impl Write, payload: &[u8], frame_kind: FrameKind, is_last_frame: bool, compressed: bool, role: Role) -> io::Result<()> {
+    let fin_bit = if is_last_frame { 0b_1000_0000 } else { 0 };
+    let rsv1_bit = if compressed { 0b_0100_0000 } else { 0 };
+    writer.write_all(&[fin_bit | rsv1_bit | frame_kind as u8])?;
+
+    match role {
+        Role::Server => {
+            write_len_header(payload.len(), 0, writer)?;
+            writer.write_all(payload)
+        },
+        Role::Client => {
+            // a fresh key for every frame, so a page's script can't choose the bytes on the wire
+            let masking_key: [u8; 4] = rand::thread_rng().gen();
+            write_len_header(payload.len(), 0b_1000_0000, writer)?;
+            writer.write_all(&masking_key)?;
+
+            let masked: Vec<u8> = payload.iter().enumerate().map(|(i, byte)| byte ^ masking_key[i % 4]).collect();
+            writer.write_all(&masked)
+        },
+    }
+}
+
+fn write_len_header(len: usize, mask_bit: u8, writer: &mut impl Write) -> io::Result<()> {
     match len {
-        0..=125 => writer.write_all(&[len as u8]),
+        0..=125 => writer.write_all(&[mask_bit | len as u8]),
         126..=65535 => {
-            writer.write_all(&[126])?;
+            writer.write_all(&[mask_bit | 126])?;
             writer.write_all(&(len as u16).to_be_bytes())
         },
         _ => {
-            writer.write_all(&[127])?;
+            writer.write_all(&[mask_bit | 127])?;
             writer.write_all(&(len as u64).to_be_bytes())
         },
     }
@@ -0,0 +1,176 @@
+// the async api, driven by a local executor over a stream that hands out a few bytes at a time
+#![cfg(feature = "async")]
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+mod common;
+
+use futures::executor::block_on;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{SinkExt, StreamExt};
+use web_socket::{AsyncWebSocket, CloseCode, Deflate, Limits, WebSocketMessage, WebSocketWriter};
+use common::{masked, Sink};
+
+// reads `input` a few bytes at a time, saying it isn't ready in between, and keeps what's written
+struct Trickle {
+    input: Vec<u8>,
+    read_up_to: usize,
+    ready: bool,
+    output: Vec<u8>,
+}
+
+impl Trickle {
+    fn new(input: Vec<u8>) -> Trickle {
+        Trickle { input, read_up_to: 0, ready: false, output: Vec::new() }
+    }
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let start = self.read_up_to;
+        let len = buf.len().min(3).min(self.input.len() - start);
+        buf[..len].copy_from_slice(&self.input[start..start+len]);
+        self.read_up_to += len;
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for Trickle {
+    fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let len = buf.len().min(5);
+        self.output.extend_from_slice(&buf[..len]);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn close(code: u16) -> Vec<u8> {
+    masked(0x88, &code.to_be_bytes())
+}
+
+fn receive_all(socket: &mut AsyncWebSocket<Trickle>) -> Vec<WebSocketMessage> {
+    block_on(socket.by_ref().collect())
+}
+
+fn text(s: &str) -> WebSocketMessage { WebSocketMessage::Text(s.to_string()) }
+
+#[test]
+fn reads_messages_that_arrive_in_pieces() {
+    let long = "a".repeat(300);
+    let input = [
+        masked(0x81, b"hello"),
+        masked(0x02, &[1, 2]),
+        masked(0x80, &[3]),
+        masked(0x81, long.as_bytes()),
+        close(1000),
+    ].concat();
+
+    let mut socket = AsyncWebSocket::new(Trickle::new(input));
+    assert_eq!(receive_all(&mut socket), vec![text("hello"), WebSocketMessage::Binary(vec![1, 2, 3]), text(&long)]);
+    assert_eq!(socket.close_frame().unwrap().code, CloseCode::Normal);
+}
+
+#[test]
+fn answers_pings_and_echoes_the_close() {
+    let input = [masked(0x89, b"hi"), close(1001)].concat();
+
+    let mut socket = AsyncWebSocket::new(Trickle::new(input));
+    assert!(receive_all(&mut socket).is_empty());
+    assert_eq!(socket.get_ref().output, vec![0x8a, 2, b'h', b'i', 0x88, 2, 0x03, 0xe9]);
+}
+
+#[test]
+fn pongs_are_handed_to_the_callback() {
+    let pongs = Arc::new(Mutex::new(Vec::new()));
+    let pongs_clone = Arc::clone(&pongs);
+
+    let mut socket = AsyncWebSocket::new(Trickle::new([masked(0x8a, b"7"), close(1000)].concat()));
+    socket.on_pong(move |payload| pongs_clone.lock().unwrap().push(payload.to_vec()));
+    receive_all(&mut socket);
+
+    assert_eq!(*pongs.lock().unwrap(), vec![b"7".to_vec()]);
+}
+
+#[test]
+fn ends_abnormally_without_a_close_frame() {
+    let mut socket = AsyncWebSocket::new(Trickle::new(masked(0x81, b"bye")));
+    assert_eq!(receive_all(&mut socket), vec![text("bye")]);
+    assert_eq!(socket.close_frame().unwrap().code, CloseCode::Abnormal);
+}
+
+#[test]
+fn protocol_errors_close_the_connection() {
+    // unmasked, which clients aren't allowed to do
+    let mut socket = AsyncWebSocket::new(Trickle::new(vec![0x81, 0x01, b'x']));
+    assert!(receive_all(&mut socket).is_empty());
+    assert_eq!(socket.close_frame().unwrap().code, CloseCode::ProtocolError);
+    let output = &socket.get_ref().output;
+    assert_eq!((output[0], &output[2..4]), (0x88, &[0x03, 0xea][..]));
+}
+
+#[test]
+fn frames_over_the_limit_are_too_big() {
+    let mut socket = AsyncWebSocket::new(Trickle::new(masked(0x81, &[b'x'; 200])));
+    socket.set_limits(Limits { max_frame_size: 100, max_message_size: 100 });
+    assert!(receive_all(&mut socket).is_empty());
+    assert_eq!(socket.close_frame().unwrap().code, CloseCode::TooBig);
+}
+
+#[test]
+fn writes_the_same_frames_as_the_blocking_writer() {
+    let sink = Sink::default();
+    let mut writer = WebSocketWriter::new(sink.clone());
+    writer.set_max_frame_size(Some(4));
+    writer.write_string("split into frames").unwrap();
+    writer.write_bytes(&[9, 8, 7]).unwrap();
+
+    let mut socket = AsyncWebSocket::new(Trickle::new(Vec::new()));
+    socket.set_max_frame_size(Some(4));
+    block_on(async {
+        socket.send(text("split into frames")).await.unwrap();
+        socket.send(WebSocketMessage::Binary(vec![9, 8, 7])).await.unwrap();
+    });
+
+    assert_eq!(socket.get_ref().output, *sink.0.lock().unwrap());
+}
+
+#[test]
+fn compressed_messages_round_trip() {
+    let sink = Sink::default();
+    let mut writer = WebSocketWriter::new(sink.clone());
+    writer.set_deflate(Deflate::default());
+    writer.write_string("squish squish squish squish").unwrap();
+    // the writer's frame is unmasked, so mask it like a client would
+    let compressed = sink.0.lock().unwrap().clone();
+    let input = [masked(compressed[0], &compressed[2..]), close(1000)].concat();
+
+    let mut socket = AsyncWebSocket::new(Trickle::new(input));
+    socket.set_deflate(Deflate::default());
+    assert_eq!(receive_all(&mut socket), vec![text("squish squish squish squish")]);
+
+    block_on(socket.send(text("too late"))).unwrap_err();
+}
+
+#[test]
+fn closing_the_sink_sends_a_normal_close() {
+    let mut socket = AsyncWebSocket::new(Trickle::new(Vec::new()));
+    block_on(socket.close()).unwrap();
+    assert_eq!(socket.get_ref().output, vec![0x88, 2, 0x03, 0xe8]);
+    assert!(block_on(socket.send(text("after"))).is_err());
+}
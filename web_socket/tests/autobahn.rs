@@ -0,0 +1,342 @@
+// the cases from the autobahn test suite (https://github.com/crossbario/autobahn-testsuite) that
+// apply to a server reading client frames, run against byte buffers
+
+mod common;
+
+use web_socket::{WebSocketListener, WebSocketWriter, WebSocketMessage, CloseFrame, CloseCode, Limits};
+use common::Sink;
+
+const MASK: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+fn frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![first_byte];
+    match payload.len() {
+        n @ 0..=125 => bytes.push(0x80 | n as u8),
+        n @ 126..=0xffff => {
+            bytes.push(0x80 | 126);
+            bytes.extend_from_slice(&(n as u16).to_be_bytes());
+        },
+        n => {
+            bytes.push(0x80 | 127);
+            bytes.extend_from_slice(&(n as u64).to_be_bytes());
+        },
+    }
+    bytes.extend_from_slice(&MASK);
+    bytes.extend(payload.iter().enumerate().map(|(i, b)| b ^ MASK[i % 4]));
+    bytes
+}
+
+fn fin(opcode: u8, payload: &[u8]) -> Vec<u8> { frame(0x80 | opcode, payload) }
+fn part(opcode: u8, payload: &[u8]) -> Vec<u8> { frame(opcode, payload) }
+
+fn close_payload(code: u16, reason: &[u8]) -> Vec<u8> {
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason);
+    payload
+}
+
+struct Outcome {
+    messages: Vec<WebSocketMessage>,
+    close_frame: Option<CloseFrame>,
+    sent: Vec<(u8, Vec<u8>)>, // every frame the server wrote back, as (opcode, payload)
+}
+
+impl Outcome {
+    fn closed_with(&self) -> CloseCode {
+        self.close_frame.as_ref().expect("the connection should have closed").code
+    }
+
+    fn sent_close(&self) -> Option<&[u8]> {
+        self.sent.iter().rev().find(|&&(opcode, _)| opcode == 0x8).map(|(_, payload)| payload.as_slice())
+    }
+
+    fn sent_close_code(&self) -> u16 {
+        let payload = self.sent_close().expect("the server should have sent a close frame");
+        u16::from_be_bytes([payload[0], payload[1]])
+    }
+}
+
+fn run(frames: &[Vec<u8>]) -> Outcome {
+    run_with(Limits::default(), frames)
+}
+
+fn run_with(limits: Limits, frames: &[Vec<u8>]) -> Outcome {
+    let received = frames.concat();
+    let sink = Sink::default();
+
+    let mut listener = WebSocketListener::new(&received[..], WebSocketWriter::new(sink.clone()));
+    listener.set_limits(limits);
+    let messages = listener.by_ref().collect();
+    let close_frame = listener.close_frame().cloned();
+
+    let sent = read_server_frames(&sink.0.lock().unwrap());
+    Outcome { messages, close_frame, sent }
+}
+
+fn read_server_frames(mut bytes: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        assert_eq!(bytes[1] >> 7, 0, "server frames are never masked");
+        let (len, header_len) = match bytes[1] {
+            126 => (u16::from_be_bytes([bytes[2], bytes[3]]) as usize, 4),
+            127 => panic!("no test makes the server send a frame that long"),
+            n => (n as usize, 2),
+        };
+        frames.push((bytes[0] & 0xf, bytes[header_len..header_len+len].to_vec()));
+        bytes = &bytes[header_len+len..];
+    }
+    frames
+}
+
+fn text(s: &str) -> WebSocketMessage { WebSocketMessage::Text(s.to_string()) }
+
+// 1: framing
+
+#[test]
+fn text_messages_of_every_length_encoding() {
+    for &len in &[0, 125, 126, 127, 128, 65535, 65536] {
+        let payload = "*".repeat(len);
+        let outcome = run(&[fin(0x1, payload.as_bytes()), fin(0x8, &close_payload(1000, b""))]);
+        assert_eq!(outcome.messages, vec![text(&payload)], "length {}", len);
+        assert_eq!(outcome.closed_with(), CloseCode::Normal);
+    }
+}
+
+#[test]
+fn binary_messages_of_every_length_encoding() {
+    for &len in &[0, 125, 126, 65535, 65536] {
+        let payload = vec![0xfe; len];
+        let outcome = run(&[fin(0x2, &payload)]);
+        assert_eq!(outcome.messages, vec![WebSocketMessage::Binary(payload)], "length {}", len);
+    }
+}
+
+// 2: pings and pongs
+
+#[test]
+fn pings_are_answered_with_the_same_payload() {
+    let outcome = run(&[fin(0x9, b""), fin(0x9, b"hello"), fin(0x9, &[0xff; 125])]);
+    assert_eq!(outcome.sent, vec![(0xA, vec![]), (0xA, b"hello".to_vec()), (0xA, vec![0xff; 125])]);
+}
+
+#[test]
+fn unsolicited_pongs_are_ignored() {
+    let outcome = run(&[fin(0xA, b"unsolicited"), fin(0x1, b"still here")]);
+    assert_eq!(outcome.messages, vec![text("still here")]);
+    assert!(outcome.sent.is_empty());
+}
+
+#[test]
+fn ping_longer_than_125_bytes_is_a_protocol_error() {
+    let outcome = run(&[fin(0x9, &[0; 126])]);
+    assert_eq!(outcome.closed_with(), CloseCode::ProtocolError);
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+#[test]
+fn fragmented_ping_is_a_protocol_error() {
+    let outcome = run(&[part(0x9, b"frag"), fin(0x0, b"ment")]);
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+// 3: reserved bits
+
+#[test]
+fn reserved_bits_are_a_protocol_error() {
+    for &rsv in &[0b0100_0000, 0b0010_0000, 0b0001_0000, 0b0111_0000] {
+        let outcome = run(&[fin(0x1, b"before"), frame(0x80 | rsv | 0x1, b"bad")]);
+        assert_eq!(outcome.messages, vec![text("before")]);
+        assert_eq!(outcome.sent_close_code(), 1002, "rsv {:b}", rsv);
+    }
+}
+
+// 4: opcodes
+
+#[test]
+fn reserved_opcodes_are_a_protocol_error() {
+    for &opcode in &[0x3, 0x4, 0x5, 0x6, 0x7, 0xB, 0xC, 0xD, 0xE, 0xF] {
+        let outcome = run(&[fin(opcode, b"")]);
+        assert_eq!(outcome.sent_close_code(), 1002, "opcode {:x}", opcode);
+    }
+}
+
+// 5: fragmentation
+
+#[test]
+fn fragments_are_reassembled() {
+    let outcome = run(&[part(0x1, b"frag"), fin(0x0, b"ment"), part(0x2, &[1]), part(0x0, &[2]), fin(0x0, &[3])]);
+    assert_eq!(outcome.messages, vec![text("fragment"), WebSocketMessage::Binary(vec![1, 2, 3])]);
+}
+
+#[test]
+fn empty_fragments_are_fine() {
+    let outcome = run(&[part(0x1, b""), part(0x0, b""), fin(0x0, b"")]);
+    assert_eq!(outcome.messages, vec![text("")]);
+}
+
+#[test]
+fn control_frames_can_arrive_between_fragments() {
+    let outcome = run(&[part(0x1, b"frag"), fin(0x9, b"ping"), fin(0xA, b""), fin(0x0, b"ment")]);
+    assert_eq!(outcome.messages, vec![text("fragment")]);
+    assert_eq!(outcome.sent, vec![(0xA, b"ping".to_vec())]);
+}
+
+#[test]
+fn close_between_fragments_ends_the_connection() {
+    let outcome = run(&[part(0x1, b"frag"), fin(0x8, &close_payload(1000, b"")), fin(0x0, b"ment")]);
+    assert!(outcome.messages.is_empty());
+    assert_eq!(outcome.closed_with(), CloseCode::Normal);
+}
+
+#[test]
+fn continuation_without_a_message_is_a_protocol_error() {
+    let outcome = run(&[fin(0x0, b"orphan")]);
+    assert_eq!(outcome.sent_close_code(), 1002);
+
+    let outcome = run(&[fin(0x1, b"done"), part(0x0, b"orphan"), fin(0x0, b"")]);
+    assert_eq!(outcome.messages, vec![text("done")]);
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+#[test]
+fn new_message_before_the_last_one_finished_is_a_protocol_error() {
+    let outcome = run(&[part(0x1, b"one"), fin(0x1, b"two")]);
+    assert!(outcome.messages.is_empty());
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+// 6: utf8
+
+#[test]
+fn code_points_can_be_split_across_fragments() {
+    let snowman = "☃".as_bytes();
+    let outcome = run(&[part(0x1, &snowman[..1]), part(0x0, &snowman[1..2]), fin(0x0, &snowman[2..])]);
+    assert_eq!(outcome.messages, vec![text("☃")]);
+}
+
+#[test]
+fn invalid_utf8_is_an_invalid_payload() {
+    let cases: &[&[u8]] = &[
+        &[0xff],
+        &[0xce, 0xba, 0xe1, 0xbd], // truncated code point
+        &[0xed, 0xa0, 0x80], // a surrogate
+        &[0xc0, 0xaf], // overlong
+        &[0xf4, 0x90, 0x80, 0x80], // past U+10FFFF
+    ];
+
+    for &case in cases {
+        let outcome = run(&[fin(0x1, case)]);
+        assert!(outcome.messages.is_empty());
+        assert_eq!(outcome.closed_with(), CloseCode::InvalidPayload, "{:x?}", case);
+        assert_eq!(outcome.sent_close_code(), 1007);
+    }
+}
+
+#[test]
+fn binary_messages_are_not_checked_for_utf8() {
+    let outcome = run(&[fin(0x2, &[0xff, 0xfe])]);
+    assert_eq!(outcome.messages, vec![WebSocketMessage::Binary(vec![0xff, 0xfe])]);
+}
+
+// 7: closing
+
+#[test]
+fn close_frames_are_echoed() {
+    let outcome = run(&[fin(0x8, b"")]);
+    assert_eq!(outcome.closed_with(), CloseCode::NoStatus);
+    assert_eq!(outcome.sent_close(), Some(&[][..]));
+
+    let outcome = run(&[fin(0x8, &close_payload(1000, b"bye"))]);
+    assert_eq!(outcome.close_frame, Some(CloseFrame::new(CloseCode::Normal, "bye")));
+    assert_eq!(outcome.sent_close(), Some(&close_payload(1000, b"bye")[..]));
+}
+
+#[test]
+fn nothing_is_read_after_a_close() {
+    let outcome = run(&[fin(0x8, &close_payload(1000, b"")), fin(0x1, b"too late"), fin(0x9, b"")]);
+    assert!(outcome.messages.is_empty());
+    assert_eq!(outcome.sent.len(), 1);
+}
+
+#[test]
+fn one_byte_close_payload_is_a_protocol_error() {
+    let outcome = run(&[fin(0x8, &[0x3])]);
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+#[test]
+fn valid_close_codes_are_accepted() {
+    for &code in &[1000, 1001, 1002, 1003, 1007, 1008, 1009, 1010, 1011, 1012, 1013, 1014, 3000, 3999, 4000, 4999] {
+        let outcome = run(&[fin(0x8, &close_payload(code, b""))]);
+        assert_eq!(outcome.closed_with().to_u16(), code);
+        assert_eq!(outcome.sent_close_code(), code);
+    }
+}
+
+#[test]
+fn invalid_close_codes_are_a_protocol_error() {
+    for &code in &[0, 999, 1004, 1005, 1006, 1015, 1016, 1100, 2000, 2999, 5000, 65535] {
+        let outcome = run(&[fin(0x8, &close_payload(code, b""))]);
+        assert_eq!(outcome.closed_with(), CloseCode::ProtocolError, "code {}", code);
+        assert_eq!(outcome.sent_close_code(), 1002);
+    }
+}
+
+#[test]
+fn close_reason_must_be_utf8() {
+    let outcome = run(&[fin(0x8, &close_payload(1000, &[0xce, 0xba, 0xe1, 0xbd]))]);
+    assert_eq!(outcome.sent_close_code(), 1007);
+}
+
+// 9: limits, and things autobahn's client would never send
+
+#[test]
+fn unmasked_frames_are_a_protocol_error() {
+    let outcome = run(&[vec![0x81, 0x02, b'h', b'i']]);
+    assert!(outcome.messages.is_empty());
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+#[test]
+fn frames_over_the_limit_are_too_big() {
+    let limits = Limits { max_frame_size: 100, max_message_size: 1000 };
+    let outcome = run_with(limits, &[fin(0x2, &[0; 100]), fin(0x2, &[0; 101])]);
+    assert_eq!(outcome.messages.len(), 1);
+    assert_eq!(outcome.closed_with(), CloseCode::TooBig);
+    assert_eq!(outcome.sent_close_code(), 1009);
+}
+
+#[test]
+fn messages_over_the_limit_are_too_big() {
+    let limits = Limits { max_frame_size: 100, max_message_size: 250 };
+    let outcome = run_with(limits, &[part(0x1, &[b'a'; 100]), part(0x0, &[b'a'; 100]), fin(0x0, &[b'a'; 50])]);
+    assert_eq!(outcome.messages, vec![text(&"a".repeat(250))]);
+
+    let outcome = run_with(limits, &[part(0x1, &[b'a'; 100]), part(0x0, &[b'a'; 100]), fin(0x0, &[b'a'; 51])]);
+    assert!(outcome.messages.is_empty());
+    assert_eq!(outcome.sent_close_code(), 1009);
+}
+
+#[test]
+fn huge_length_headers_are_rejected_before_reading_the_payload() {
+    let mut header = vec![0x82, 0x80 | 127];
+    header.extend_from_slice(&(1u64 << 40).to_be_bytes());
+    header.extend_from_slice(&MASK);
+    let outcome = run(&[header]);
+    assert_eq!(outcome.sent_close_code(), 1009);
+
+    let mut header = vec![0x82, 0x80 | 127];
+    header.extend_from_slice(&u64::MAX.to_be_bytes());
+    header.extend_from_slice(&MASK);
+    let outcome = run(&[header]);
+    assert_eq!(outcome.sent_close_code(), 1002);
+}
+
+#[test]
+fn connection_dropping_mid_frame_is_abnormal() {
+    let truncated = fin(0x1, b"cut short");
+    let outcome = run(&[truncated[..8].to_vec()]);
+    assert!(outcome.messages.is_empty());
+    assert_eq!(outcome.closed_with(), CloseCode::Abnormal);
+    assert!(outcome.sent.is_empty());
+}
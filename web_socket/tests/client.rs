@@ -0,0 +1,183 @@
+// our client talking to a small echo server built from the same crate
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+mod common;
+
+use web_socket::{accept_key, Deflate, WebSocketClient, WebSocketListener, WebSocketMessage, WebSocketWriter, CloseCode, CloseFrame, ClientError, SharedStream};
+use common::Sink;
+
+fn read_request(tcp_stream: &TcpStream) -> Vec<String> {
+    let mut reader = BufReader::new(tcp_stream);
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" { break lines }
+        lines.push(line.trim_end().to_string());
+    }
+}
+
+fn header<'a>(request: &'a [String], name: &str) -> Option<&'a str> {
+    request.iter()
+        .filter_map(|line| line.find(':').map(|i| (&line[..i], line[i+1..].trim())))
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+fn echo_server(respond: fn(&[String]) -> String) -> String {
+    // answers one handshake with `respond`, then echoes every message back
+    let server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        let (mut tcp_stream, _) = server.accept().unwrap();
+        let request = read_request(&tcp_stream);
+        tcp_stream.write_all(respond(&request).as_bytes()).unwrap();
+
+        let (mut listener, mut writer) = web_socket::split(SharedStream::new(tcp_stream));
+        if let Some((deflate, _)) = header(&request, "Sec-WebSocket-Extensions").and_then(|offers| Deflate::default().negotiate(offers)) {
+            writer.set_deflate(deflate);
+            listener.set_deflate(deflate);
+        }
+
+        for message in listener {
+            match message {
+                WebSocketMessage::Text(text) => writer.write_string(&text).unwrap(),
+                WebSocketMessage::Binary(bytes) => writer.write_bytes(&bytes).unwrap(),
+            }
+        }
+    });
+
+    address
+}
+
+fn accept(request: &[String]) -> String {
+    let key = header(request, "Sec-WebSocket-Key").unwrap();
+    let extensions = header(request, "Sec-WebSocket-Extensions")
+        .and_then(|offers| Deflate::default().negotiate(offers))
+        .map(|(_, response)| format!("Sec-WebSocket-Extensions: {}\r\n", response))
+        .unwrap_or_default();
+
+    format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n{}\r\n", accept_key(key), extensions)
+}
+
+#[test]
+fn sends_a_valid_handshake() {
+    let address = echo_server(|request| {
+        assert_eq!(request[0], "GET /pusoy HTTP/1.1");
+        assert_eq!(header(request, "Upgrade"), Some("websocket"));
+        assert_eq!(header(request, "Sec-WebSocket-Version"), Some("13"));
+        assert_eq!(header(request, "Origin"), Some("https://ethan.ws"));
+        assert!(web_socket::is_valid_key(header(request, "Sec-WebSocket-Key").unwrap()));
+        accept(request)
+    });
+
+    WebSocketClient::new(&address, "/pusoy").header("Origin", "https://ethan.ws").connect().unwrap();
+}
+
+#[test]
+fn messages_round_trip() {
+    let address = echo_server(accept);
+    let (mut listener, mut writer) = WebSocketClient::new(&address, "/").connect().unwrap();
+
+    let long = "x".repeat(70_000);
+    writer.write_string("hello").unwrap();
+    writer.write_bytes(&[0, 1, 2]).unwrap();
+    writer.write_string(&long).unwrap();
+    writer.set_max_frame_size(Some(3));
+    writer.write_string("in pieces").unwrap();
+
+    assert_eq!(listener.next(), Some(WebSocketMessage::Text("hello".into())));
+    assert_eq!(listener.next(), Some(WebSocketMessage::Binary(vec![0, 1, 2])));
+    assert_eq!(listener.next(), Some(WebSocketMessage::Text(long)));
+    assert_eq!(listener.next(), Some(WebSocketMessage::Text("in pieces".into())));
+}
+
+#[test]
+fn pings_and_closes_work_from_the_client_side() {
+    let address = echo_server(accept);
+    let (mut listener, mut writer) = WebSocketClient::new(&address, "/").connect().unwrap();
+
+    let (pong_sender, pong_receiver) = std::sync::mpsc::channel();
+    listener.on_pong(move |payload| pong_sender.send(payload.to_vec()).unwrap());
+
+    writer.ping(b"are you there").unwrap();
+    writer.close(CloseCode::Normal, "done").unwrap();
+
+    assert_eq!(listener.next(), None);
+    assert_eq!(pong_receiver.recv().unwrap(), b"are you there");
+    assert_eq!(listener.close_frame().map(|frame| (frame.code, frame.reason.as_str())), Some((CloseCode::Normal, "done")));
+}
+
+#[test]
+fn deflate_is_negotiated_and_used() {
+    let address = echo_server(accept);
+    let (mut listener, mut writer) = WebSocketClient::new(&address, "/")
+        .deflate(Deflate { server_no_context_takeover: true, client_no_context_takeover: false })
+        .connect()
+        .unwrap();
+
+    for _ in 0..3 {
+        writer.write_string(r#"{"kind":"guess","guessIsLeft":true}"#).unwrap();
+        assert_eq!(listener.next(), Some(WebSocketMessage::Text(r#"{"kind":"guess","guessIsLeft":true}"#.into())));
+    }
+}
+
+#[test]
+fn rejected_handshakes_are_errors() {
+    let address = echo_server(|_| "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string());
+    match WebSocketClient::new(&address, "/missing").connect() {
+        Err(ClientError::Rejected(status_line)) => assert_eq!(status_line, "HTTP/1.1 404 Not Found"),
+        other => panic!("expected a rejection, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn wrong_accept_key_is_an_error() {
+    let address = echo_server(|_| {
+        format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept_key("not the key"))
+    });
+    assert!(matches!(WebSocketClient::new(&address, "/").connect(), Err(ClientError::BadResponse(_))));
+}
+
+#[test]
+fn extensions_we_never_offered_are_an_error() {
+    let address = echo_server(|request| {
+        accept(request).replace("\r\n\r\n", "\r\nSec-WebSocket-Extensions: permessage-deflate\r\n\r\n")
+    });
+    assert!(matches!(WebSocketClient::new(&address, "/").connect(), Err(ClientError::BadResponse(_))));
+}
+
+#[test]
+fn a_client_listener_reads_what_a_server_wrote() {
+    let sink = Sink::default();
+    let mut server = WebSocketWriter::new(sink.clone());
+    server.write_string("hello").unwrap();
+    server.ping(b"are you there").unwrap();
+    server.write_bytes(&[1, 2, 3]).unwrap();
+    server.close(CloseCode::GoingAway, "bye").unwrap();
+
+    let answers = Sink::default();
+    let bytes = sink.0.lock().unwrap().clone();
+    let mut listener = WebSocketListener::new(&bytes[..], WebSocketWriter::client(answers.clone()));
+    assert_eq!(listener.by_ref().collect::<Vec<_>>(), vec![
+        WebSocketMessage::Text("hello".to_string()),
+        WebSocketMessage::Binary(vec![1, 2, 3]),
+    ]);
+    assert_eq!(listener.close_frame().map(|frame| frame.code), Some(CloseCode::GoingAway));
+
+    // the pong and the echoed close are masked, or a server wouldn't take them
+    let answers = answers.0.lock().unwrap().clone();
+    let pongs = Arc::new(Mutex::new(Vec::new()));
+    let pongs_clone = Arc::clone(&pongs);
+    let mut server = WebSocketListener::new(&answers[..], WebSocketWriter::new(io::sink()));
+    server.on_pong(move |payload| pongs_clone.lock().unwrap().push(payload.to_vec()));
+
+    assert_eq!(server.next(), None);
+    assert_eq!(*pongs.lock().unwrap(), vec![b"are you there".to_vec()]);
+    assert_eq!(server.close_frame(), Some(&CloseFrame::new(CloseCode::GoingAway, "bye")));
+}
@@ -0,0 +1,58 @@
+// helpers shared by the tests that write frames by hand. not every test uses all of them
+#![allow(dead_code)]
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+// a writer that can be cloned, so the test can look at what's been written while a WebSocketWriter
+// still owns it
+#[derive(Clone, Default)]
+pub struct Sink(pub Arc<Mutex<Vec<u8>>>);
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+pub fn frames(mut bytes: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    // (first byte, payload) for each unmasked frame
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        let (len, header_len) = match bytes[1] {
+            126 => (u16::from_be_bytes([bytes[2], bytes[3]]) as usize, 4),
+            127 => {
+                let mut len = [0; 8];
+                len.copy_from_slice(&bytes[2..10]);
+                (u64::from_be_bytes(len) as usize, 10)
+            },
+            n => (n as usize, 2),
+        };
+        frames.push((bytes[0], bytes[header_len..header_len+len].to_vec()));
+        bytes = &bytes[header_len+len..];
+    }
+    frames
+}
+
+pub fn masked(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+    // a frame from a client, with the shortest length encoding that fits
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let mut bytes = vec![first_byte];
+    match payload.len() {
+        n @ 0..=125 => bytes.push(0x80 | n as u8),
+        n @ 126..=0xffff => {
+            bytes.push(0x80 | 126);
+            bytes.extend_from_slice(&(n as u16).to_be_bytes());
+        },
+        n => {
+            bytes.push(0x80 | 127);
+            bytes.extend_from_slice(&(n as u64).to_be_bytes());
+        },
+    }
+    bytes.extend_from_slice(&mask);
+    bytes.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    bytes
+}
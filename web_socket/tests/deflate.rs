@@ -0,0 +1,165 @@
+// permessage-deflate, https://tools.ietf.org/html/rfc7692
+
+use std::io;
+
+mod common;
+
+use web_socket::{Deflate, WebSocketListener, WebSocketMessage, WebSocketWriter, CloseCode};
+use common::{frames, masked, Sink};
+
+fn write_compressed(deflate: Deflate, messages: &[&str]) -> Vec<(u8, Vec<u8>)> {
+    let sink = Sink::default();
+    let mut writer = WebSocketWriter::new(sink.clone());
+    writer.set_deflate(deflate);
+    for message in messages {
+        writer.write_string(message).unwrap();
+    }
+    let bytes = sink.0.lock().unwrap().clone();
+    frames(&bytes)
+}
+
+fn listen(deflate: Option<Deflate>, frames: &[(u8, Vec<u8>)]) -> (Vec<WebSocketMessage>, Option<CloseCode>) {
+    let received: Vec<u8> = frames.iter().flat_map(|(first_byte, payload)| masked(*first_byte, payload)).collect();
+
+    let mut listener = WebSocketListener::new(&received[..], WebSocketWriter::new(io::sink()));
+    if let Some(deflate) = deflate { listener.set_deflate(deflate) }
+    let messages = listener.by_ref().collect();
+    let close_code = listener.close_frame().map(|frame| frame.code);
+
+    (messages, close_code)
+}
+
+fn text(s: &str) -> WebSocketMessage { WebSocketMessage::Text(s.to_string()) }
+
+#[test]
+fn negotiates_a_plain_offer() {
+    let (agreed, response) = Deflate::default().negotiate("permessage-deflate").unwrap();
+    assert_eq!(agreed, Deflate::default());
+    assert_eq!(response, "permessage-deflate");
+}
+
+#[test]
+fn negotiates_what_the_browser_sends() {
+    let (agreed, response) = Deflate::default().negotiate("permessage-deflate; client_max_window_bits").unwrap();
+    assert_eq!(agreed, Deflate::default());
+    assert_eq!(response, "permessage-deflate");
+}
+
+#[test]
+fn agrees_to_context_takeover_requests_from_either_side() {
+    let (agreed, response) = Deflate::default().negotiate("permessage-deflate; server_no_context_takeover").unwrap();
+    assert!(agreed.server_no_context_takeover);
+    assert_eq!(response, "permessage-deflate; server_no_context_takeover");
+
+    let ours = Deflate { server_no_context_takeover: false, client_no_context_takeover: true };
+    let (agreed, response) = ours.negotiate("permessage-deflate").unwrap();
+    assert_eq!(agreed, ours);
+    assert_eq!(response, "permessage-deflate; client_no_context_takeover");
+}
+
+#[test]
+fn server_window_bits_must_be_the_full_window() {
+    let (_, response) = Deflate::default().negotiate("permessage-deflate; server_max_window_bits=15").unwrap();
+    assert_eq!(response, "permessage-deflate; server_max_window_bits=15");
+
+    assert_eq!(Deflate::default().negotiate("permessage-deflate; server_max_window_bits=10"), None);
+}
+
+#[test]
+fn falls_back_to_a_later_offer() {
+    let offers = "permessage-deflate; server_max_window_bits=8, permessage-deflate; client_max_window_bits=12";
+    let (_, response) = Deflate::default().negotiate(offers).unwrap();
+    assert_eq!(response, "permessage-deflate");
+}
+
+#[test]
+fn rejects_bad_offers() {
+    for &offers in &[
+        "",
+        "x-webkit-deflate-frame",
+        "permessage-deflate; unknown_param",
+        "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+        "permessage-deflate; server_no_context_takeover=1",
+        "permessage-deflate; server_max_window_bits",
+        "permessage-deflate; client_max_window_bits=7",
+        "permessage-deflate; client_max_window_bits=16",
+    ] {
+        assert_eq!(Deflate::default().negotiate(offers), None, "{}", offers);
+    }
+}
+
+#[test]
+fn compressed_messages_set_rsv1_and_round_trip() {
+    let state = r#"{"kind":"updateGameState","tanks":[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]}"#;
+    let written = write_compressed(Deflate::default(), &[state, state]);
+
+    assert!(written.iter().all(|&(first_byte, _)| first_byte == 0b1100_0001));
+    assert!(written[0].1.len() < state.len());
+    // the second message can point back at the first
+    assert!(written[1].1.len() < written[0].1.len());
+
+    assert_eq!(listen(Some(Deflate::default()), &written).0, vec![text(state), text(state)]);
+}
+
+#[test]
+fn no_context_takeover_compresses_each_message_alone() {
+    let deflate = Deflate { server_no_context_takeover: true, client_no_context_takeover: true };
+    let written = write_compressed(deflate, &["hello hello hello", "hello hello hello"]);
+    assert_eq!(written[0].1, written[1].1);
+
+    // and the listener resets between messages, so each one inflates alone
+    let reversed: Vec<_> = written.into_iter().rev().collect();
+    assert_eq!(listen(Some(deflate), &reversed).0, vec![text("hello hello hello"), text("hello hello hello")]);
+}
+
+#[test]
+fn compressed_messages_can_be_fragmented() {
+    let written = write_compressed(Deflate::default(), &["fragmented and compressed"]);
+    let payload = &written[0].1;
+    let (first, rest) = payload.split_at(payload.len() / 2);
+    let fragments = vec![(0b0100_0001, first.to_vec()), (0b1000_0000, rest.to_vec())];
+
+    assert_eq!(listen(Some(Deflate::default()), &fragments).0, vec![text("fragmented and compressed")]);
+}
+
+#[test]
+fn uncompressed_messages_are_still_allowed() {
+    let frames = vec![(0b1000_0001, b"plain".to_vec())];
+    assert_eq!(listen(Some(Deflate::default()), &frames).0, vec![text("plain")]);
+}
+
+#[test]
+fn rsv1_without_negotiation_is_a_protocol_error() {
+    let written = write_compressed(Deflate::default(), &["compressed"]);
+    assert_eq!(listen(None, &written), (vec![], Some(CloseCode::ProtocolError)));
+}
+
+#[test]
+fn rsv1_on_control_and_continuation_frames_is_a_protocol_error() {
+    let ping = vec![(0b1100_1001, vec![])];
+    assert_eq!(listen(Some(Deflate::default()), &ping).1, Some(CloseCode::ProtocolError));
+
+    let continuation = vec![(0b0000_0001, b"a".to_vec()), (0b1100_0000, b"b".to_vec())];
+    assert_eq!(listen(Some(Deflate::default()), &continuation).1, Some(CloseCode::ProtocolError));
+}
+
+#[test]
+fn corrupt_data_is_an_invalid_payload() {
+    let frames = vec![(0b1100_0001, vec![0xff, 0xff, 0xff])];
+    assert_eq!(listen(Some(Deflate::default()), &frames).1, Some(CloseCode::InvalidPayload));
+}
+
+#[test]
+fn inflating_past_the_message_limit_is_too_big() {
+    let big = "a".repeat(1 << 16);
+    let written = write_compressed(Deflate::default(), &[&big]);
+    assert!(written[0].1.len() < 1000);
+
+    let received = masked(written[0].0, &written[0].1);
+
+    let mut listener = WebSocketListener::new(&received[..], WebSocketWriter::new(io::sink()));
+    listener.set_deflate(Deflate::default());
+    listener.set_limits(web_socket::Limits { max_frame_size: 1000, max_message_size: 1000 });
+    assert_eq!(listener.next(), None);
+    assert_eq!(listener.close_frame().map(|frame| frame.code), Some(CloseCode::TooBig));
+}
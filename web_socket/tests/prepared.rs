@@ -0,0 +1,154 @@
+// messages encoded once and written to many peers, and text written straight into the writer's
+// own buffer
+
+use std::io::{self, Write};
+
+mod common;
+
+use web_socket::{Deflate, PreparedMessage, WebSocketListener, WebSocketMessage, WebSocketWriter, CloseCode};
+use common::{frames, masked, Sink};
+
+fn writer(deflate: Option<Deflate>) -> (WebSocketWriter, Sink) {
+    let sink = Sink::default();
+    let mut writer = WebSocketWriter::new(sink.clone());
+    if let Some(deflate) = deflate { writer.set_deflate(deflate) }
+    (writer, sink)
+}
+
+fn written(sink: &Sink) -> Vec<u8> {
+    sink.0.lock().unwrap().clone()
+}
+
+fn listen(deflate: Option<Deflate>, frames: &[(u8, Vec<u8>)]) -> Vec<WebSocketMessage> {
+    let received: Vec<u8> = frames.iter().flat_map(|(first_byte, payload)| masked(*first_byte, payload)).collect();
+
+    let mut listener = WebSocketListener::new(&received[..], WebSocketWriter::new(io::sink()));
+    if let Some(deflate) = deflate { listener.set_deflate(deflate) }
+    listener.collect()
+}
+
+fn text(s: &str) -> WebSocketMessage { WebSocketMessage::Text(s.to_string()) }
+
+const NO_CONTEXT_TAKEOVER: Deflate = Deflate { server_no_context_takeover: true, client_no_context_takeover: false };
+
+#[test]
+fn writes_the_same_bytes_as_write_string() {
+    let message = "the same thing for everyone ".repeat(10);
+    let prepared = PreparedMessage::text(&message, false);
+
+    let (mut expected, expected_sink) = writer(None);
+    expected.write_string(&message).unwrap();
+
+    for _ in 0..3 {
+        let (mut peer, sink) = writer(None);
+        peer.write_prepared(&prepared).unwrap();
+        assert_eq!(written(&sink), written(&expected_sink));
+    }
+}
+
+#[test]
+fn binary_messages_keep_their_opcode() {
+    let (mut peer, sink) = writer(None);
+    peer.write_prepared(&PreparedMessage::binary(&[1, 2, 3], false)).unwrap();
+    assert_eq!(written(&sink), vec![0x82, 3, 1, 2, 3]);
+}
+
+#[test]
+fn compressed_once_for_peers_without_context_takeover() {
+    let message = "compress me once, compress me once, compress me once";
+    let prepared = PreparedMessage::text(message, true);
+
+    let (mut expected, expected_sink) = writer(Some(NO_CONTEXT_TAKEOVER));
+    expected.write_string(message).unwrap();
+
+    let (mut peer, sink) = writer(Some(NO_CONTEXT_TAKEOVER));
+    peer.write_prepared(&prepared).unwrap();
+    peer.write_prepared(&prepared).unwrap();
+
+    let frames = frames(&written(&sink));
+    assert_eq!(frames[0].0, 0xc1);
+    assert_eq!(written(&sink)[..written(&expected_sink).len()], written(&expected_sink)[..]);
+    assert_eq!(frames[0], frames[1]);
+
+    let messages = listen(Some(Deflate::default()), &frames);
+    assert_eq!(messages, vec![text(message), text(message)]);
+}
+
+#[test]
+fn peers_with_context_takeover_compress_it_themselves() {
+    // the second copy only makes sense to a peer whose window holds the first
+    let message = "shared context, shared context, shared context";
+    let prepared = PreparedMessage::text(message, true);
+
+    let (mut peer, sink) = writer(Some(Deflate::default()));
+    peer.write_prepared(&prepared).unwrap();
+    peer.write_string("in between").unwrap();
+    peer.write_prepared(&prepared).unwrap();
+
+    let frames = frames(&written(&sink));
+    assert!(frames.iter().all(|(first_byte, _)| *first_byte == 0xc1));
+    assert!(frames[2].1.len() < frames[0].1.len());
+
+    let messages = listen(Some(Deflate::default()), &frames);
+    assert_eq!(messages, vec![text(message), text("in between"), text(message)]);
+}
+
+#[test]
+fn uncompressed_messages_skip_the_deflater() {
+    let (mut peer, sink) = writer(Some(Deflate::default()));
+    peer.write_prepared(&PreparedMessage::text("plain", false)).unwrap();
+    peer.write_string("squished").unwrap();
+
+    let frames = frames(&written(&sink));
+    assert_eq!(frames[0], (0x81, b"plain".to_vec()));
+
+    let messages = listen(Some(Deflate::default()), &frames);
+    assert_eq!(messages, vec![text("plain"), text("squished")]);
+}
+
+#[test]
+fn still_split_into_frames_when_too_long() {
+    let (mut peer, sink) = writer(None);
+    peer.set_max_frame_size(Some(4));
+    peer.write_prepared(&PreparedMessage::text("fragmented", false)).unwrap();
+
+    let frames = frames(&written(&sink));
+    assert_eq!(frames, vec![
+        (0x01, b"frag".to_vec()),
+        (0x00, b"ment".to_vec()),
+        (0x80, b"ed".to_vec()),
+    ]);
+}
+
+#[test]
+fn closed_writers_refuse_prepared_messages() {
+    let (mut peer, _) = writer(None);
+    peer.close(CloseCode::Normal, "").unwrap();
+    assert!(peer.write_prepared(&PreparedMessage::text("too late", false)).is_err());
+}
+
+#[test]
+fn text_written_into_the_buffer_is_the_same_as_write_string() {
+    let (mut expected, expected_sink) = writer(Some(NO_CONTEXT_TAKEOVER));
+    let (mut peer, sink) = writer(Some(NO_CONTEXT_TAKEOVER));
+
+    // the buffer is reused, so the second message can't still have the first one in it
+    for message in ["{\"kind\":\"first\",\"padding\":\"xxxxxxxx\"}", "{\"kind\":\"second\"}"].iter() {
+        expected.write_string(message).unwrap();
+        peer.write_text_with(|buffer| buffer.write_all(message.as_bytes())).unwrap();
+    }
+
+    assert_eq!(written(&sink), written(&expected_sink));
+}
+
+#[test]
+fn text_written_into_the_buffer_has_to_be_utf8() {
+    let (mut peer, sink) = writer(None);
+    let error = peer.write_text_with(|buffer| buffer.write_all(&[b'"', 0xff, b'"'])).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(written(&sink).is_empty());
+
+    let (mut peer, _) = writer(None);
+    peer.close(CloseCode::Normal, "").unwrap();
+    assert!(peer.write_text_with(|buffer| buffer.write_all(b"too late")).is_err());
+}
@@ -1,7 +1,8 @@
-use server::{PeerId, Disconnect, GlobalState};
+use server::{PeerId, Disconnect, GlobalState, SessionId};
 use std::collections::HashMap;
-use web_socket::{WebSocketMessage, WebSocketWriter};
-use json::{json, Json};
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame, PreparedMessage};
+use json::{json, Json, JsonWriter};
+use std::io;
 use rand::{random, thread_rng, Rng};
 use std::str::FromStr;
 
@@ -10,11 +11,17 @@ const MAP_HEIGHT: f64 = 9.0;
 
 pub struct ArenaGlobalState {
     players: HashMap<PeerId, Player>,
+    next_player_number: u64,
 }
 
 impl GlobalState for ArenaGlobalState {
-    fn new_peer(&mut self, id: PeerId, writer: WebSocketWriter) {
-        self.players.insert(id, Player::new(writer));
+    fn new_peer(&mut self, id: PeerId, _session: SessionId, mut writer: WebSocketWriter, _subprotocol: Option<&str>) {
+        // everyone gets the same game state, so players need to know which one is them
+        let number = self.next_player_number;
+        self.next_player_number += 1;
+
+        let _ = writer.write_string(&json!({id: number}).to_string());
+        self.players.insert(id, Player::new(number, writer));
     }
 
     fn on_message_receive(&mut self, id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect> {
@@ -29,32 +36,41 @@ impl GlobalState for ArenaGlobalState {
         Ok(())
     }
 
-    fn on_disconnect(&mut self, id: PeerId) {
+    fn on_disconnect(&mut self, id: PeerId, _close_frame: &CloseFrame) {
         self.players.remove(&id);
     }
 
     fn periodic(&mut self) {
         // announce game state to all players every tenth of a second
-        for id in self.players.keys().cloned().collect::<Vec<PeerId>>() {
-            let array = self.players.iter()
-                .filter(|&(&i, _)| i != id)
-                .map(|(_, player)| player.as_json())
-                .collect();
-
-            let json_string = Json::Array(array).to_string();
+        let bytes = self.players_json().expect("writing to a vec can't fail");
+        let message = PreparedMessage::text(std::str::from_utf8(&bytes).expect("json is utf8"), true);
 
-            let _ = self.players.get_mut(&id).unwrap().writer.write_string(&json_string);
+        for player in self.players.values_mut() {
+            let _ = player.writer.write_prepared(&message);
         }
     }
 }
 
 impl ArenaGlobalState {
     pub fn new() -> ArenaGlobalState {
-        ArenaGlobalState { players: HashMap::new() }
+        ArenaGlobalState { players: HashMap::new(), next_player_number: 0 }
+    }
+
+    fn players_json(&self) -> io::Result<Vec<u8>> {
+        let mut writer = JsonWriter::new(Vec::new());
+
+        writer.begin_array()?;
+        for player in self.players.values() {
+            player.write_json(&mut writer)?;
+        }
+        writer.end_array()?;
+
+        writer.finish()
     }
 }
 
 struct Player {
+    number: u64,
     writer: WebSocketWriter,
     color: Json,
     x: f64,
@@ -62,8 +78,9 @@ struct Player {
 }
 
 impl Player {
-    fn new(writer: WebSocketWriter) -> Player {
+    fn new(number: u64, writer: WebSocketWriter) -> Player {
         Player {
+            number,
             writer,
             color: json!({r: (random::<u8>()), g:(random::<u8>()), b:(random::<u8>())}),
             x: thread_rng().gen_range(0.0, MAP_WIDTH),
@@ -71,11 +88,14 @@ impl Player {
         }
     }
 
-    fn as_json(&self) -> Json {
-        json!({
-            x: (self.x),
-            y: (self.y),
-            color: (self.color.clone()),
-        })
+    fn write_json(&self, writer: &mut JsonWriter<Vec<u8>>) -> io::Result<()> {
+        writer.begin_object()?
+            .key("id")?.number(self.number)?
+            .key("x")?.number(self.x)?
+            .key("y")?.number(self.y)?
+            .key("color")?.json(&self.color)?
+            .end_object()?;
+
+        Ok(())
     }
 }
@@ -3,10 +3,10 @@ use rand::distributions::{Distribution, Standard};
 
 use std::collections::{HashSet, HashMap};
 
-use web_socket::{WebSocketMessage, WebSocketWriter};
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame};
 use json::{Json, json, jsons};
 
-use server::{PeerId, GlobalState, Disconnect};
+use server::{PeerId, GlobalState, Disconnect, SessionId};
 
 
 const WIDTH: usize = 10;
@@ -38,7 +38,7 @@ impl FillerGlobalState {
 }
 
 impl GlobalState for FillerGlobalState {
-    fn new_peer(&mut self, id: PeerId, writer: WebSocketWriter) {
+    fn new_peer(&mut self, id: PeerId, _session: SessionId, writer: WebSocketWriter, _subprotocol: Option<&str>) {
         self.active_players.insert(id, Player::new(writer));
         let player = self.active_players.get_mut(&id).unwrap();
         let _ = player.writer.write_string(&player.game_state.jsonify().to_string());
@@ -69,7 +69,7 @@ impl GlobalState for FillerGlobalState {
         Ok(())
     }
 
-    fn on_disconnect(&mut self, id: PeerId) {
+    fn on_disconnect(&mut self, id: PeerId, _close_frame: &CloseFrame) {
         self.active_players.remove(&id);
     }
 
@@ -129,11 +129,11 @@ impl GameState {
 
     pub fn jsonify(&self) -> String {
         let left_territory = self.left_territory.iter()
-            .map(|&(x, y)| json!({x: (x as f64), y: (y as f64)}))
+            .map(|&(x, y)| json!({x: x, y: y}))
             .collect();
 
         let right_territory = self.right_territory.iter()
-            .map(|&(x, y)| json!({x: (x as f64), y: (y as f64)}))
+            .map(|&(x, y)| json!({x: x, y: y}))
             .collect();
 
         let available_colors = Json::Array(self.valid_colors().iter().map(|c| c.jsonify()).collect());
@@ -1,56 +1,63 @@
-use server::{GlobalState, PeerId, Disconnect};
+use server::{GlobalState, PeerId, Disconnect, SessionId};
 use std::io::{BufReader, BufRead};
 use std::fs::File;
 use crate::GOD_SET_PATH;
-use json::Json;
-use web_socket::{WebSocketMessage, WebSocketWriter};
+use json::Jsonable;
+use json_derive::Jsonable;
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame};
 
 pub struct GodSetGlobalState {
     json: String,
 }
 
+// what the page gets for each line of the god set
+#[derive(Jsonable)]
+#[json(rename_all = "camelCase")]
+struct Term {
+    year_start: u16,
+    year_end: u16,
+    social: bool,
+    political: bool,
+    economic: bool,
+    term: String,
+    definition: String,
+}
+
 impl GodSetGlobalState {
     pub fn new() -> GodSetGlobalState {
         let file = BufReader::new(File::open(GOD_SET_PATH).unwrap());
 
-        let json = Json::Array(file.lines()
+        let terms = file.lines()
             .map(|line| {
                 let line = line.ok()?;
                 let mut split = line.trim_end().split("\t");
                 let _chapter = split.next()?;
                 let _section = split.next()?;
-                let year_start: u16 = split.next()?.parse().ok()?;
-                let year_end: u16 = split.next()?.parse().ok()?;
-                let social: bool = split.next()?.parse().ok()?;
-                let political: bool = split.next()?.parse().ok()?;
-                let economic: bool = split.next()?.parse().ok()?;
-                let term = split.next()?.to_string();
-                let definition = split.next()?.to_string();
-
-                Some(Json::Object([
-                    ("yearStart", Json::Number(year_start as f64)),
-                    ("yearEnd", Json::Number(year_end as f64)),
-                    ("social", Json::Boolean(social)),
-                    ("political", Json::Boolean(political)),
-                    ("economic", Json::Boolean(economic)),
-                    ("term", Json::String(term)),
-                    ("definition", Json::String(definition)),
-                ].iter().map(|(a, b)| (a.to_string(), b.clone())).collect()))
+
+                Some(Term {
+                    year_start: split.next()?.parse().ok()?,
+                    year_end: split.next()?.parse().ok()?,
+                    social: split.next()?.parse().ok()?,
+                    political: split.next()?.parse().ok()?,
+                    economic: split.next()?.parse().ok()?,
+                    term: split.next()?.to_string(),
+                    definition: split.next()?.to_string(),
+                })
             })
-            .collect::<Option<Vec<Json>>>().unwrap());
+            .collect::<Option<Vec<Term>>>().unwrap();
 
-        GodSetGlobalState { json: json.to_string() }
+        GodSetGlobalState { json: terms.into_json().to_string() }
     }
 }
 
 impl GlobalState for GodSetGlobalState {
-    fn new_peer(&mut self, _id: PeerId, mut tcp_stream: WebSocketWriter) {
+    fn new_peer(&mut self, _id: PeerId, _session: SessionId, mut tcp_stream: WebSocketWriter, _subprotocol: Option<&str>) {
         let _ = tcp_stream.write_string(&self.json);
     }
 
     fn on_message_receive(&mut self, _id: PeerId, _message: WebSocketMessage) -> Result<(), Disconnect> { Err(Disconnect) }
 
-    fn on_disconnect(&mut self, _id: PeerId) { }
+    fn on_disconnect(&mut self, _id: PeerId, _close_frame: &CloseFrame) { }
 
     fn periodic(&mut self) { }
 }
\ No newline at end of file
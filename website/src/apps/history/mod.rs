@@ -1,9 +1,10 @@
-use server::{GlobalState, PeerId, Disconnect};
-use web_socket::{WebSocketMessage, WebSocketWriter};
+use server::{GlobalState, PeerId, Disconnect, SessionId};
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame, PreparedMessage};
 use std::collections::{HashMap, HashSet};
 
-use json::{Json, jsons, json};
+use json::{Json, Map, FromJsonError, jsons, json};
 use std::str::FromStr;
+use std::convert::TryFrom;
 use std::option::NoneError;
 use std::fmt;
 use std::fmt::Debug;
@@ -21,11 +22,25 @@ pub struct HistoryGlobalState {
     active_games: HashMap<GameId, Box<dyn GameSpecific>>,
     game_id_generator: GameIdGenerator,
     vocabulary_model: VocabularyModel,
+    sessions: HashMap<PeerId, SessionId>,
+    away: HashMap<SessionId, (PeerId, GameId, String)>, // left an active game, with their old id and username
 }
 
 impl GlobalState for HistoryGlobalState {
-    fn new_peer(&mut self, id: PeerId, writer: WebSocketWriter) {
+    fn new_peer(&mut self, id: PeerId, session: SessionId, writer: WebSocketWriter, _subprotocol: Option<&str>) {
         self.users.insert(id, writer);
+        self.sessions.insert(id, session);
+
+        // put someone who refreshed the page back in their game, with their score
+        if let Some((old_id, game_id, username)) = self.away.remove(&session) {
+            if let Some(game) = self.active_games.get_mut(&game_id) {
+                self.users.add_username(id, username);
+
+                if game.rejoin(old_id, id, &mut self.users, &mut self.vocabulary_model) {
+                    self.users.add_game_id(id, game_id);
+                }
+            }
+        }
     }
 
     fn on_message_receive(&mut self, id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect> {
@@ -38,7 +53,7 @@ impl GlobalState for HistoryGlobalState {
                 let username = json.get("username")?.get_string()?.to_string();
                 self.users.add_username(id, username.clone());
 
-                match Lobby::new(id, json.get("settings")?, &mut self.vocabulary_model) {
+                match Lobby::new(id, &json_text, &mut self.vocabulary_model) {
                     Ok(lobby) => {
                         let game_id = self.game_id_generator.next();
                         self.lobbies.insert(game_id, lobby);
@@ -96,7 +111,9 @@ impl GlobalState for HistoryGlobalState {
         Ok(())
     }
 
-    fn on_disconnect(&mut self, id: PeerId) {
+    fn on_disconnect(&mut self, id: PeerId, close_frame: &CloseFrame) {
+        println!("{:?} disconnected: {}", id, close_frame);
+
         // what game_id were they in?
 
         if let Some(game_id) = self.users.get_game_id(id) {
@@ -110,29 +127,38 @@ impl GlobalState for HistoryGlobalState {
                 let host_left = game.leave(id, &mut self.users, &mut self.vocabulary_model);
                 if host_left {
                     self.active_games.remove(&game_id);
+                } else if let Some(&session) = self.sessions.get(&id) {
+                    self.away.insert(session, (id, game_id, self.users.get_username(id).to_string()));
                 }
 
             }
         }
 
         self.users.remove(id);
+        self.sessions.remove(&id);
     }
 
     fn periodic(&mut self) {
         for game in self.active_games.values_mut() {
             game.periodic(&mut self.users, &mut self.vocabulary_model);
         }
+
+        let active_games = &self.active_games;
+        self.away.retain(|_, &mut (_, game_id, _)| active_games.contains_key(&game_id));
     }
 }
 
 impl HistoryGlobalState {
-    pub fn new() -> HistoryGlobalState {
+    pub fn new(god_set_path: &str, vocabulary_log_path: &str) -> HistoryGlobalState {
+        // the terms are read from the god set, and every wrong answer is appended to the log
         HistoryGlobalState {
             users: Users::new(),
             lobbies: HashMap::new(),
             active_games: HashMap::new(),
             game_id_generator: GameIdGenerator::new(),
-            vocabulary_model: VocabularyModel::new()
+            vocabulary_model: VocabularyModel::new(god_set_path, vocabulary_log_path),
+            sessions: HashMap::new(),
+            away: HashMap::new(),
         }
     }
 }
@@ -147,15 +173,13 @@ struct Lobby {
 }
 
 impl Lobby {
-    fn new(host: PeerId, json: &Json, vocabulary: &mut VocabularyModel) -> Result<Lobby, LobbyCreateError> {
-        let json_map = json.get_object()?;
-
-        let start = get_chapter_thing(json_map.get("startSection")?.get_string()?)?;
-        let end = get_chapter_thing(json_map.get("endSection")?.get_string()?)?;
+    fn new(host: PeerId, message: &Json, vocabulary: &mut VocabularyModel) -> Result<Lobby, LobbyCreateError> {
+        let start = get_chapter_thing(message.string_at("/settings/startSection")?)?;
+        let end = get_chapter_thing(message.string_at("/settings/endSection")?)?;
 
         let query = Query::new(start, end, vocabulary).ok_or(LobbyCreateError::BlankRange)?;
 
-        let game_kind = GameKind::from_str(json_map.get("gameKind")?.get_string()?)?;
+        let game_kind = GameKind::from_str(message.string_at("/settings/gameKind")?)?;
 
         Ok(Lobby { host, peers: HashSet::new(), query, game_kind })
     }
@@ -193,7 +217,7 @@ impl Lobby {
     fn announce_members(&self, users: &mut Users) {
         let string = jsons!({
             kind: "refreshLobby",
-            users: (Json::Array(self.peers.iter().map(|&u| Json::String(users.get_username(u).to_string())).collect())),
+            users: (self.peers.iter().map(|&u| users.get_username(u)).collect::<Vec<_>>()),
         });
 
         self.send_to_all(users, string);
@@ -203,17 +227,18 @@ impl Lobby {
         let string = jsons!({
             kind: "startingGame",
             host: (users.get_username(self.host)),
-            users: (Json::Array(self.peers.iter().map(|&u| Json::String(users.get_username(u).to_string())).collect())),
+            users: (self.peers.iter().map(|&u| users.get_username(u)).collect::<Vec<_>>()),
         });
 
         self.send_to_all(users, string);
     }
 
     fn send_to_all(&self, users: &mut Users, string: String) {
-        let _ = users.get_writer(self.host).write_string(&string.clone());
+        let message = PreparedMessage::text(&string, true);
+        let _ = users.get_writer(self.host).write_prepared(&message);
 
         for &user in self.peers.iter() {
-            let _ = users.get_writer(user).write_string(&string);
+            let _ = users.get_writer(user).write_prepared(&message);
         }
     }
 }
@@ -245,13 +270,20 @@ impl GameKind {
 }
 
 
-#[derive(Copy, Clone )]
+#[derive(Clone)]
 enum LobbyCreateError {
     UnableToParseChapters,
     BlankRange,
+    InvalidSettings(FromJsonError),
     Other,
 }
 
+impl From<FromJsonError> for LobbyCreateError {
+    fn from(e: FromJsonError) -> LobbyCreateError {
+        LobbyCreateError::InvalidSettings(e)
+    }
+}
+
 impl From<NoneError> for LobbyCreateError {
     fn from(_: NoneError) -> LobbyCreateError {
         LobbyCreateError::Other
@@ -266,11 +298,12 @@ impl From<std::io::Error> for LobbyCreateError {
 
 impl fmt::Display for LobbyCreateError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", match *self {
-            LobbyCreateError::UnableToParseChapters => "Unable to interpret your chapter range",
-            LobbyCreateError::BlankRange => "No terms were found in that range",
-            LobbyCreateError::Other => "Error in creating your game",
-        })
+        match *self {
+            LobbyCreateError::UnableToParseChapters => write!(f, "Unable to interpret your chapter range"),
+            LobbyCreateError::BlankRange => write!(f, "No terms were found in that range"),
+            LobbyCreateError::InvalidSettings(ref e) => write!(f, "Invalid game settings: {}", e),
+            LobbyCreateError::Other => write!(f, "Error in creating your game"),
+        }
     }
 }
 
@@ -287,9 +320,10 @@ fn get_chapter_thing(string: &str) -> Result<(u8, u8), LobbyCreateError> {
 }
 
 trait GameSpecific: Send+Debug {
-    fn receive_message(&mut self, id: PeerId, message: &HashMap<String, Json>, users: &mut Users, vocabulary: &mut VocabularyModel) -> Result<(), Disconnect>;
+    fn receive_message(&mut self, id: PeerId, message: &Map, users: &mut Users, vocabulary: &mut VocabularyModel) -> Result<(), Disconnect>;
     fn periodic(&mut self, users: &mut Users, vocabulary: &mut VocabularyModel);
     fn leave(&mut self, id: PeerId, users: &mut Users, vocabulary: &mut VocabularyModel) -> bool;
+    fn rejoin(&mut self, old_id: PeerId, new_id: PeerId, users: &mut Users, vocabulary: &mut VocabularyModel) -> bool;
 }
 
 pub struct Users {
@@ -336,7 +370,7 @@ struct GameId(u32);
 
 impl GameId {
     fn from_json(json: &Json) -> Option<GameId> {
-        Some(GameId(json.get_number()? as u32))
+        json.get_u64().and_then(|n| u32::try_from(n).ok()).map(GameId)
     }
 
     fn stringify(&self) -> String {
@@ -2,7 +2,7 @@ use crate::apps::history::{GameSpecific, Users};
 use crate::apps::history::vocabulary_model::{VocabularyModel, Query, MultipleChoiceQuestion};
 
 use server::{PeerId, Disconnect};
-use json::{Json, jsons, json};
+use json::{Json, Map, jsons, json};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
@@ -42,7 +42,7 @@ impl QuizGame {
 }
 
 impl GameSpecific for QuizGame {
-    fn receive_message(&mut self, id: PeerId, message: &HashMap<String, Json>, users: &mut Users, vocabulary: &mut VocabularyModel) -> Result<(), Disconnect> {
+    fn receive_message(&mut self, id: PeerId, message: &Map, users: &mut Users, vocabulary: &mut VocabularyModel) -> Result<(), Disconnect> {
         match message.get("kind")?.get_string()? {
             "nextQuestion" if id == self.host => {
                 // update all of our scores first
@@ -103,12 +103,29 @@ impl GameSpecific for QuizGame {
             }
 
         } else {
+            // keep their score around in case they come back
             self.players.remove(&id);
-            self.scores.remove(&id);
             self.submitted_answers.remove(&id);
 
         }
 
         was_host
     }
+
+    fn rejoin(&mut self, old_id: PeerId, new_id: PeerId, users: &mut Users, vocabulary: &mut VocabularyModel) -> bool {
+        if old_id == self.host || self.players.contains(&old_id) { return false }
+
+        if let Some(score) = self.scores.remove(&old_id) {
+            self.scores.insert(new_id, score);
+        }
+        self.players.insert(new_id);
+
+        let _ = users.get_writer(new_id).write_string(&jsons!({
+            kind: "initialStuff",
+            question: (self.current_question.jsonify(vocabulary)),
+            score: (self.scores.get(&new_id).copied().unwrap_or(0.0)),
+        }));
+
+        true
+    }
 }
\ No newline at end of file
@@ -1,6 +1,5 @@
 use std::collections::{HashSet, HashMap};
 use std::fs::{read_to_string, File, OpenOptions};
-use crate::{GOD_SET_PATH, VOCABULARY_LOG_PATH};
 use rand::{thread_rng, Rng};
 use rand::seq::SliceRandom;
 use std::io::{self, Write, BufReader, BufRead};
@@ -17,11 +16,11 @@ pub struct VocabularyModel {
 
 
 impl VocabularyModel {
-    pub fn new() -> VocabularyModel {
-        let terms =  read_to_string(GOD_SET_PATH).unwrap().parse().unwrap();
+    pub fn new(god_set_path: &str, vocabulary_log_path: &str) -> VocabularyModel {
+        let terms =  read_to_string(god_set_path).unwrap().parse().unwrap();
 
         VocabularyModel {
-            confusion: ConfusionModel::new(VOCABULARY_LOG_PATH, &terms).unwrap(),
+            confusion: ConfusionModel::new(vocabulary_log_path, &terms).unwrap(),
             terms,
         }
     }
@@ -1,38 +1,33 @@
 use rand::{Rng, thread_rng};
 use std::collections::HashSet;
-use server::{GlobalState, PeerId, Disconnect};
-use web_socket::{WebSocketWriter, WebSocketMessage};
+use server::{GlobalState, PeerId, Disconnect, SessionId, DecodeError};
+use web_socket::{WebSocketWriter, WebSocketMessage, CloseFrame, PreparedMessage};
 use std::fmt::Debug;
 use std::collections::HashMap;
-use json::{Json, jsons, json};
+use json::{Json, MessageSchemas, Schema, jsons, json};
 use std::io::{BufReader};
 use std::fs::{File};
 use std::iter::once;
 use lazy_static::lazy_static;
-use crate::WORD_LIST_PATH;
 use std::io::BufRead;
 use crate::apps::pusoy::pusoy_game::PusoyGame;
 
 
 
 lazy_static! {
-    static ref WORD_LIST: Vec<String> = {
-        let reader = BufReader::new(File::open(WORD_LIST_PATH).unwrap());
-
-        let words: Vec<String> = reader.lines()
-            .map(|line| line.unwrap().trim().to_string())
-            .collect();
-
-        assert!(words.is_sorted());
-        assert!(words.iter().all(|w| w.chars().all(|c| matches!(c, 'a'..='z' | '-'))));
-
-        words
-    };
+    static ref MESSAGES: MessageSchemas = MessageSchemas::new()
+        .kind("create", Schema::object().field("username", Schema::string().min_len(1)))
+        .kind("join", Schema::object().field("username", Schema::string().min_len(1)).field("gameId", Schema::string()))
+        .kind("begin", Schema::object())
+        .kind("play", Schema::object().field("index", Schema::integer().min(0.0)))
+        .kind("playCardsArray", Schema::object().field("cards", Schema::array(Schema::string()).max_len(13)));
 }
 
 
 pub struct PusoyGlobalState {
     unregistered_users: HashMap<PeerId, WebSocketWriter>,
+    sessions: HashMap<PeerId, SessionId>,
+    away: HashMap<SessionId, GameId>, // disconnected from an active game, their seat is waiting for them
 
     game_id_generator: GameIdGenerator,
 
@@ -42,10 +37,13 @@ pub struct PusoyGlobalState {
 }
 
 impl PusoyGlobalState {
-    pub fn new() -> PusoyGlobalState {
+    pub fn new(word_list_path: &str) -> PusoyGlobalState {
+        // game ids are words from the list, which has one word per line, sorted
         PusoyGlobalState {
             unregistered_users: HashMap::new(),
-            game_id_generator: GameIdGenerator::new(),
+            sessions: HashMap::new(),
+            away: HashMap::new(),
+            game_id_generator: GameIdGenerator::new(read_word_list(word_list_path)),
             in_game: HashMap::new(),
             lobbies: HashMap::new(),
             active_games: HashMap::new(),
@@ -53,7 +51,7 @@ impl PusoyGlobalState {
     }
 
     fn lobby_from_id(&mut self, json: &Json) -> Option<GameId> {
-        let game_id = GameId::from_word(json.get_string()?)?;
+        let game_id = self.game_id_generator.from_word(json.get_string()?)?;
 
         if self.lobbies.contains_key(&game_id) {
             Some(game_id)
@@ -64,36 +62,67 @@ impl PusoyGlobalState {
 }
 
 impl GlobalState for PusoyGlobalState {
-    fn new_peer(&mut self, id: PeerId, writer: WebSocketWriter) {
+    fn new_peer(&mut self, id: PeerId, session: SessionId, writer: WebSocketWriter, _subprotocol: Option<&str>) {
+        self.sessions.insert(id, session);
+
+        // someone who refreshed the page gets their old seat back if the game is still going
+        let rejoining = match self.away.remove(&session) {
+            Some(game_id) => self.active_games.get_mut(&game_id).map(|game| (game_id, game)),
+            None => None,
+        };
+
+        let writer = match rejoining {
+            Some((game_id, game)) => match game.rejoin(session, id, writer) {
+                Ok(()) => {
+                    self.in_game.insert(id, game_id);
+                    return;
+                },
+                Err(writer) => writer,
+            },
+            None => writer,
+        };
+
         self.unregistered_users.insert(id, writer);
     }
 
     fn on_message_receive(&mut self, id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect> {
         println!("{:?}: {}", id, message.get_text()?);
         let json_text: Json = message.get_text()?.parse().ok()?;
+        let kind = match MESSAGES.validate(&json_text) {
+            Ok(kind) => kind,
+            Err(violations) => {
+                // ignore it, and tell them why if they aren't in a game yet
+                if let Some(writer) = self.unregistered_users.get_mut(&id) {
+                    let _ = writer.write_string(&DecodeError::Invalid(violations).to_json().to_string());
+                }
+                return Ok(());
+            },
+        };
         let json = json_text.get_object()?;
 
-        match json.get("kind")?.get_string()? {
+        match kind {
             "create" => {
                 let username = json.get("username")?.get_string()?.to_string();
 
+                let session = *self.sessions.get(&id)?;
                 let writer = self.unregistered_users.remove(&id)?;
 
                 let game_id = self.game_id_generator.next();
 
-                let lobby = Lobby::new(id, writer, username, game_id);
+                let lobby = Lobby::new(id, session, writer, username, self.game_id_generator.word(game_id));
 
                 self.lobbies.insert(game_id, lobby);
                 self.in_game.insert(id, game_id);
             },
             "join" => {
                 let username = json.get("username")?.get_string()?.to_string();
+                let session = *self.sessions.get(&id)?;
 
                 match self.lobby_from_id(json.get("gameId")?) {
                     Some(game_id) => {
                         self.in_game.insert(id, game_id);
                         let lobby = self.lobbies.get_mut(&game_id).unwrap();
-                        lobby.join(id, self.unregistered_users.remove(&id)?, username, game_id)
+                        lobby.join(id, session, self.unregistered_users.remove(&id)?, username, self.game_id_generator.word(game_id))
                     },
                     None => { let _ = self.unregistered_users.get_mut(&id)?.write_string(&jsons!({kind:"invalidGameId"})); }
                 }
@@ -121,7 +150,8 @@ impl GlobalState for PusoyGlobalState {
 
     }
 
-    fn on_disconnect(&mut self, id: PeerId) {
+    fn on_disconnect(&mut self, id: PeerId, close_frame: &CloseFrame) {
+        println!("{:?} disconnected: {}", id, close_frame);
         if let Some(game_id) = self.in_game.get(&id) {
             if let Some(lobby) = self.lobbies.get_mut(&game_id) {
                 let host_left = lobby.leave(id);
@@ -130,21 +160,27 @@ impl GlobalState for PusoyGlobalState {
                 }
 
             } else if let Some(game) = self.active_games.get_mut(&game_id) {
-                let host_left = game.leave(id);
-                if host_left {
+                let everyone_left = game.leave(id);
+                if everyone_left {
                     self.active_games.remove(&game_id);
+                } else if let Some(&session) = self.sessions.get(&id) {
+                    self.away.insert(session, *game_id);
                 }
             }
         }
 
         self.in_game.remove(&id);
         self.unregistered_users.remove(&id);
+        self.sessions.remove(&id);
     }
 
     fn periodic(&mut self) {
         for game in self.active_games.values_mut() {
             game.periodic();
         }
+
+        let active_games = &self.active_games;
+        self.away.retain(|_, game_id| active_games.contains_key(game_id));
     }
 }
 
@@ -158,14 +194,14 @@ struct Lobby {
 }
 
 impl Lobby {
-    fn new(host_id: PeerId, writer: WebSocketWriter, username: String, game_id: GameId) -> Lobby {
-        let host = Member::new(host_id, writer, username.clone());
+    fn new(host_id: PeerId, session: SessionId, writer: WebSocketWriter, username: String, game_id: &str) -> Lobby {
+        let host = Member::new(host_id, session, writer, username.clone());
         let mut ret = Lobby { host, players: Vec::new() };
 
         ret.host.write_ignore(&jsons!({
             kind: "createSuccess",
             host: username,
-            gameId: (game_id.stringify()),
+            gameId: game_id,
         }));
 
         ret
@@ -175,16 +211,16 @@ impl Lobby {
         self.players.iter().any(|p| p.id == id)
     }
 
-    fn join(&mut self, user: PeerId, writer: WebSocketWriter, username: String, game_id: GameId) {
+    fn join(&mut self, user: PeerId, session: SessionId, writer: WebSocketWriter, username: String, game_id: &str) {
         if !self.contains_player(user) && self.host.id != user {
-            let mut player = Member::new(user, writer, username);
+            let mut player = Member::new(user, session, writer, username);
 
             let host_username = self.host.username.clone();
 
             player.write_ignore(&jsons!({
                 kind: "joinSuccess",
                 host: host_username,
-                gameId: (game_id.stringify()),
+                gameId: game_id,
             }));
 
             self.players.push(player);
@@ -210,20 +246,21 @@ impl Lobby {
     fn announce_players(&mut self) {
         self.send_to_all(&jsons!({
             kind: "refreshLobby",
-            players: (Json::Array(self.players.iter().map(|u| Json::String(u.username.clone())).collect())),
+            players: (self.players.iter().map(|u| u.username.as_str()).collect::<Vec<_>>()),
         }));
     }
 
     fn announce_beginning(&mut self) {
         self.send_to_all(&jsons!({
             kind: "begin",
-            players: (Json::Array(self.players.iter().chain(Some(&self.host)).map(|p| Json::String(p.username.clone())).collect())),
+            players: (self.players.iter().chain(Some(&self.host)).map(|p| p.username.as_str()).collect::<Vec<_>>()),
         }));
     }
 
     fn send_to_all(&mut self, string: &str) {
+        let message = PreparedMessage::text(string, true);
         for player in self.players.iter_mut().chain(once(&mut self.host)) {
-            player.write_ignore(string);
+            let _ = player.writer.write_prepared(&message);
         }
     }
 }
@@ -231,19 +268,43 @@ impl Lobby {
 #[derive(Debug)]
 pub struct Member {
     id: PeerId,
+    session: SessionId,
     writer: WebSocketWriter,
     username: String,
+    connected: bool,
 }
 
 impl Member {
-    fn new(id: PeerId, writer: WebSocketWriter, username: String) -> Member {
-        Member { id, writer, username }
+    fn new(id: PeerId, session: SessionId, writer: WebSocketWriter, username: String) -> Member {
+        Member { id, session, writer, username, connected: true }
     }
 
     pub fn get_id(&self) -> PeerId {
         self.id
     }
 
+    pub fn get_session(&self) -> SessionId {
+        self.session
+    }
+
+    pub fn get_username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn disconnect(&mut self) {
+        self.connected = false;
+    }
+
+    pub fn reconnect(&mut self, id: PeerId, writer: WebSocketWriter) {
+        self.id = id;
+        self.writer = writer;
+        self.connected = true;
+    }
+
     pub fn write_ignore(&mut self, string: &str) {
         let _ = self.writer.write_string(string);
     }
@@ -254,30 +315,30 @@ struct GameId {
     word_index: usize,
 }
 
-impl GameId {
-    fn from_word(word: &str) -> Option<GameId> {
-        WORD_LIST.binary_search_by(|probe| probe.as_str().cmp(word)).ok()
-            .map(|word_index| GameId { word_index })
-    }
-
-    fn stringify(&self) -> String {
-        WORD_LIST[self.word_index].clone()
-    }
-}
-
 struct GameIdGenerator {
+    words: Vec<String>,
     unavailable: HashSet<usize>,
 }
 
 impl GameIdGenerator {
-    fn new() -> GameIdGenerator {
+    fn new(words: Vec<String>) -> GameIdGenerator {
         GameIdGenerator {
+            words,
             unavailable: HashSet::new(),
         }
     }
 
+    fn from_word(&self, word: &str) -> Option<GameId> {
+        self.words.binary_search_by(|probe| probe.as_str().cmp(word)).ok()
+            .map(|word_index| GameId { word_index })
+    }
+
+    fn word(&self, game_id: GameId) -> &str {
+        &self.words[game_id.word_index]
+    }
+
     fn next(&mut self) -> GameId {
-        let len = WORD_LIST.len();
+        let len = self.words.len();
         assert!(self.unavailable.len() < len, "ran out of game id's");
 
         loop {
@@ -287,6 +348,18 @@ impl GameIdGenerator {
         }
     }
 }
+fn read_word_list(path: &str) -> Vec<String> {
+    let reader = BufReader::new(File::open(path).unwrap());
+
+    let words: Vec<String> = reader.lines()
+        .map(|line| line.unwrap().trim().to_string())
+        .collect();
+
+    assert!(words.is_sorted());
+    assert!(words.iter().all(|w| w.chars().all(|c| matches!(c, 'a'..='z' | '-'))));
+
+    words
+}
 //
 // pub fn get_expected_pass_count(play1: Play, play2: Play) -> f64 {
 //     lazy_static! {
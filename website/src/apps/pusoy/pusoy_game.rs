@@ -1,8 +1,8 @@
-use server::{PeerId, Disconnect};
+use server::{PeerId, Disconnect, SessionId, clock};
+use web_socket::WebSocketWriter;
 use crate::apps::pusoy::Member;
 use pusoy::{GameState, all_plays, Card, Cards, Play, RandomPlayer, Player};
-use json::{Json, jsons, json};
-use std::collections::HashMap;
+use json::{Json, Map, jsons, json};
 use rand::thread_rng;
 use rand::seq::SliceRandom;
 use std::time::{Duration, Instant};
@@ -24,7 +24,7 @@ impl PusoyGame {
     pub fn new(humans: Vec<Member>) -> PusoyGame {
         let virtual_players = build_virtual_players(humans.len());
         let state = GameState::new(virtual_players.len());
-        let turn_begin = Instant::now();
+        let turn_begin = clock::now();
         let available_plays = state.get_interface().valid_plays();
         let mut ret = PusoyGame { humans, virtual_players, available_plays, turn_begin, state };
         ret.turn_transition();
@@ -33,7 +33,7 @@ impl PusoyGame {
         ret
     }
 
-    pub fn receive_message(&mut self, id: PeerId, message: &HashMap<String, Json>) -> Result<(), Disconnect> {
+    pub fn receive_message(&mut self, id: PeerId, message: &Map) -> Result<(), Disconnect> {
         let coming_from_current_player = self.humans[self.virtual_players[self.state.current_player()].unwrap()].get_id() == id;
 
         match message.get("kind")?.get_string()? {
@@ -65,7 +65,7 @@ impl PusoyGame {
 
     pub fn periodic(&mut self) {
         let is_human = self.virtual_players[self.state.current_player()].is_some();
-        let elapsed = self.turn_begin.elapsed();
+        let elapsed = clock::now().duration_since(self.turn_begin);
 
         if is_human && elapsed >= HUMAN_PLAYER_MAX_TURN_LENGTH {
             // force a move
@@ -77,8 +77,37 @@ impl PusoyGame {
         }
     }
 
-    pub fn leave(&mut self, _id: PeerId) -> bool {
-        true
+    pub fn leave(&mut self, id: PeerId) -> bool {
+        // their seat stays, and gets played for them when their turn times out
+        if let Some(human) = self.humans.iter_mut().find(|h| h.get_id() == id) {
+            human.disconnect();
+        }
+
+        self.humans.iter().all(|h| !h.is_connected())
+    }
+
+    pub fn rejoin(&mut self, session: SessionId, id: PeerId, writer: WebSocketWriter) -> Result<(), WebSocketWriter> {
+        let human_index = match self.humans.iter().position(|h| h.get_session() == session && !h.is_connected()) {
+            Some(human_index) => human_index,
+            None => return Err(writer),
+        };
+
+        self.humans[human_index].reconnect(id, writer);
+
+        let usernames: Vec<_> = self.humans.iter().map(|h| h.get_username().to_string()).collect();
+        self.humans[human_index].write_ignore(&jsons!({
+            kind: "begin",
+            players: usernames,
+        }));
+
+        let virtual_index = self.virtual_players.iter().position(|&p| p == Some(human_index)).unwrap();
+        self.send_transition(virtual_index);
+
+        if virtual_index == self.state.current_player() {
+            self.give_turn_brief();
+        }
+
+        Ok(())
     }
 
     //////////////////////////// OTHER FUNCTIONS /////////////////////
@@ -107,34 +136,36 @@ impl PusoyGame {
                 self.available_plays = self.state.get_interface().valid_plays();
                 self.give_turn_brief();
 
-                self.turn_begin = Instant::now();
+                self.turn_begin = clock::now();
             },
         }
     }
 
     fn turn_transition(&mut self) {
-        let card_counts = Json::Array(self.state.hands().iter().map(|c| Json::Number(c.len() as f64)).collect());
+        for i in 0..self.virtual_players.len() {
+            self.send_transition(i);
+        }
+    }
 
-        let on_table = match self.state.cards_on_table() {
-            Some(play) => jsonify_cards(play.cards()),
-            None => Json::Array(vec![]),
-        };
+    fn send_transition(&mut self, virtual_index: usize) {
+        if let Some(human_id) = self.virtual_players[virtual_index] {
+            let card_counts: Vec<_> = self.state.hands().iter().map(|c| c.len()).collect();
+
+            let on_table = match self.state.cards_on_table() {
+                Some(play) => jsonify_cards(play.cards()),
+                None => Json::Array(vec![]),
+            };
 
-        for (i, human_id) in self.virtual_players.iter().copied().enumerate() {
-            if let Some(human_id) = human_id {
-                let human = &mut self.humans[human_id];
-                let hand = jsonify_cards(self.state.hands()[i]);
-
-                human.write_ignore(&jsons!({
-                    kind: "transition",
-                    yourId: i,
-                    turnIndex: (self.state.current_player()),
-                    hand: hand,
-                    onTable: (on_table.clone()),
-                    cardCounts: (card_counts.clone()),
-                }));
-
-            }
+            let hand = jsonify_cards(self.state.hands()[virtual_index]);
+
+            self.humans[human_id].write_ignore(&jsons!({
+                kind: "transition",
+                yourId: virtual_index,
+                turnIndex: (self.state.current_player()),
+                hand: hand,
+                onTable: on_table,
+                cardCounts: card_counts,
+            }));
         }
     }
 
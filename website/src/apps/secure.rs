@@ -1,5 +1,5 @@
-use server::{GlobalState, PeerId, Disconnect};
-use web_socket::{WebSocketMessage, WebSocketWriter};
+use server::{GlobalState, PeerId, Disconnect, SessionId};
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame};
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use crate::PASSWORD_LOG_PATH;
@@ -17,7 +17,7 @@ impl SecureGlobalState {
 }
 
 impl GlobalState for SecureGlobalState {
-    fn new_peer(&mut self, _id: PeerId, mut _tcp_stream: WebSocketWriter) { }
+    fn new_peer(&mut self, _id: PeerId, _session: SessionId, mut _tcp_stream: WebSocketWriter, _subprotocol: Option<&str>) { }
 
     fn on_message_receive(&mut self, _id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect> {
         if let WebSocketMessage::Text(string) = message {
@@ -27,7 +27,7 @@ impl GlobalState for SecureGlobalState {
         Ok(())
     }
 
-    fn on_disconnect(&mut self, _id: PeerId) { }
+    fn on_disconnect(&mut self, _id: PeerId, _close_frame: &CloseFrame) { }
 
     fn periodic(&mut self) { }
 }
\ No newline at end of file
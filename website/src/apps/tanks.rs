@@ -1,15 +1,14 @@
 use std::collections::HashMap;
-use std::time::{UNIX_EPOCH, SystemTime};
 use rand::{thread_rng, Rng, random};
 
 use crate::{GOD_SET_PATH};
-use server::{GlobalState, PeerId, Disconnect};
-use json::Json;
-use std::str::FromStr;
+use server::clock::unix_time_millis;
+use server::{GlobalState, PeerId, Disconnect, SessionId, decode_or_reply, send};
+use json::{json, Json, JsonWriter};
 use rand::seq::SliceRandom;
-use web_socket::{WebSocketMessage, WebSocketWriter};
+use web_socket::{WebSocketMessage, WebSocketWriter, CloseFrame, CloseCode};
 use std::fs::File;
-use std::io::{BufReader, BufRead};
+use std::io::{self, BufReader, BufRead};
 
 const MAP_WIDTH: f64 = 500.0;
 const MAP_HEIGHT: f64 = 500.0;
@@ -19,6 +18,20 @@ const PLAYER_VELOCITY: f64 = 0.04;
 const PLAYER_RADIUS: f64 = 10.0;
 const LASER_DURATION_MILLIS: f64 = 300.0;
 
+server::request! {
+    enum TanksRequest {
+        UpdateFacing = "updateFacing" { new_facing: f64 = "newFacing" },
+        Guess = "guess" { guess_is_left: bool = "guessIsLeft" },
+        Fire = "fire" {},
+    }
+}
+
+server::response! {
+    enum TanksResponse {
+        Kill = "kill" {},
+    }
+}
+
 pub struct TanksGlobalState {
     last_updated: u64, // our last updated time
     stars_json: Json,
@@ -28,7 +41,7 @@ pub struct TanksGlobalState {
 }
 
 impl GlobalState for TanksGlobalState {
-    fn new_peer(&mut self, id: PeerId, tcp_stream: WebSocketWriter) {
+    fn new_peer(&mut self, id: PeerId, _session: SessionId, tcp_stream: WebSocketWriter, _subprotocol: Option<&str>) {
         self.new_player(id, tcp_stream);
         self.announce();
     }
@@ -36,34 +49,32 @@ impl GlobalState for TanksGlobalState {
     fn on_message_receive(&mut self, id: PeerId, message: WebSocketMessage) -> Result<(), Disconnect> {
         if !self.has_player(id) { return Err(Disconnect) } // that means we're dead!
 
-        let a = Json::from_str(message.get_text()?).ok()?;
-        let map = a.get_object()?;
+        let request = decode_or_reply(&message, &mut self.players.get_mut(&id)?.tcp_stream)?;
 
-        match map.get("kind")?.get_string()? {
-            "updateFacing" => {
-                self.update_facing(id, map.get("newFacing")?.get_number()?);
+        match request {
+            TanksRequest::UpdateFacing { new_facing } => {
+                self.update_facing(id, new_facing);
                 self.announce();
                 Ok(())
             },
-            "guess" => {
-                if self.guessed_correctly(id, map.get("guessIsLeft")?.get_bool()?) {
+            TanksRequest::Guess { guess_is_left } => {
+                if self.guessed_correctly(id, guess_is_left) {
                     self.announce();
                     Ok(())
                 } else {
                     Err(Disconnect)
                 }
             },
-            "fire" => {
+            TanksRequest::Fire {} => {
                 // boom bam bop
                 self.shoot_laser(id);
                 self.announce();
                 Ok(())
             },
-            _ => Err(Disconnect),
         }
     }
 
-    fn on_disconnect(&mut self, id: PeerId) {
+    fn on_disconnect(&mut self, id: PeerId, _close_frame: &CloseFrame) {
         self.remove_player(id);
     }
 
@@ -76,14 +87,7 @@ impl TanksGlobalState {
             .map(|_| (thread_rng().gen_range(0.0, MAP_WIDTH as f64), thread_rng().gen_range(0.0, MAP_HEIGHT as f64)))
             .collect();
 
-        let stars_json = Json::Array(stars.iter()
-            .map(|&(x, y)| {
-                let mut map = HashMap::new();
-                map.insert("x".into(), Json::Number(x));
-                map.insert("y".into(), Json::Number(y));
-                Json::Object(map)
-            })
-            .collect());
+        let stars_json = Json::Array(stars.iter().map(|&(x, y)| json!({x: x, y: y})).collect());
 
         let questions = match cool_vector() {
             Some(questions) if !questions.is_empty() => questions,
@@ -100,18 +104,17 @@ impl TanksGlobalState {
     }
 
     fn announce(&mut self) {
-        for id in self.players.keys().cloned().collect::<Vec<PeerId>>() {
-            let message = self.game_state_message_to(id).to_string();
-            let writer = &mut self.players.get_mut(&id).unwrap().tcp_stream;
-            let _ = writer.write_string(&message);
+        for (&id, player) in &self.players {
+            // a clone shares the connection, and lets us keep looking at everyone else
+            let mut writer = player.tcp_stream.clone();
+            let _ = writer.write_text_with(|out| self.write_game_state_to(id, out));
         }
     }
 
     fn kill(&mut self, id: PeerId) {
         let tcp_stream = &mut self.players.get_mut(&id).unwrap().tcp_stream;
-        let mut map = HashMap::new();
-        map.insert("kind".into(), Json::String("kill".into()));
-        let _ = tcp_stream.write_string(&Json::Object(map).to_string());
+        let _ = send(tcp_stream, TanksResponse::Kill {});
+        let _ = tcp_stream.close(CloseCode::Normal, "killed");
         self.remove_player(id);
     }
 
@@ -174,43 +177,50 @@ impl TanksGlobalState {
         self.players.get_mut(&id).unwrap().facing = new_facing;
     }
 
-    fn game_state_message_to(&self, receiver: PeerId) -> Json {
+    fn write_game_state_to(&self, receiver: PeerId, out: &mut Vec<u8>) -> io::Result<()> {
         // contains:
         //      a number called `time` that stores the time that x and y were last valid
         //      an array called `stars` each element with an x and y
         //      an array called 'players' each with properties x, y, vx, vy
         //      an array called `lasers` each with an x, y, facing, and expire
         //      an object called 'us' with the same x, y, vx, vy
+        // written as we go, since everyone gets their own
+        let mut writer = JsonWriter::new(out);
+
+        writer.begin_object()?
+            .key("kind")?.string("updateGameState")?
+            .key("gameState")?.begin_object()?
+            .key("time")?.number(self.last_updated)?
+            .key("stars")?.json(&self.stars_json)?
+            .key("players")?.begin_array()?;
+
+        for player in self.players.values() {
+            player.write_json(&mut writer)?;
+        }
 
-        let lasers = Json::Array(self.lasers.iter()
-            .map(|laser| {
-                let mut map = HashMap::new();
-                map.insert("x".into(), Json::Number(laser.x));
-                map.insert("y".into(), Json::Number(laser.y));
-                map.insert("facing".into(), Json::Number(laser.facing));
-                map.insert("expire".into(), Json::Number(laser.expire));
-                Json::Object(map)
-            })
-            .collect());
+        writer.end_array()?.key("us")?;
+        self.players[&receiver].write_json(&mut writer)?;
+
+        writer.key("lasers")?.begin_array()?;
+        for laser in &self.lasers {
+            writer.begin_object()?
+                .key("x")?.number(laser.x)?
+                .key("y")?.number(laser.y)?
+                .key("facing")?.number(laser.facing)?
+                .key("expire")?.number(laser.expire)?
+                .end_object()?;
+        }
+        writer.end_array()?.end_object()?;
 
         let question = &self.players[&receiver].question;
-        let mut question_map = HashMap::new();
-        question_map.insert("definition".into(), Json::String(question.definition.clone()));
-        question_map.insert("left".into(), Json::String(question.left.clone()));
-        question_map.insert("right".into(), Json::String(question.right.clone()));
-
-        let mut game_state_map = HashMap::new();
-        game_state_map.insert("time".into(), Json::Number(self.last_updated as f64));
-        game_state_map.insert("stars".into(), self.stars_json.clone());
-        game_state_map.insert("players".into(), Json::Array(self.players.values().map(PlayerInfo::jsonify).collect::<Vec<Json>>()));
-        game_state_map.insert("us".into(), self.players[&receiver].jsonify());
-        game_state_map.insert("lasers".into(), lasers);
-
-        Json::Object([
-            ("kind".into(), Json::String("updateGameState".into())),
-            ("gameState".into(), Json::Object(game_state_map)),
-            ("question".into(), Json::Object(question_map)),
-        ].iter().cloned().collect())
+        writer.key("question")?.begin_object()?
+            .key("definition")?.string(&question.definition)?
+            .key("left")?.string(&question.left)?
+            .key("right")?.string(&question.right)?
+            .end_object()?
+            .end_object()?;
+
+        writer.finish().map(drop)
     }
 
     fn update(&mut self) {
@@ -266,17 +276,19 @@ impl PlayerInfo {
         (vx, vy)
     }
 
-    fn jsonify(&self) -> Json {
+    fn write_json(&self, writer: &mut JsonWriter<&mut Vec<u8>>) -> io::Result<()> {
         let (vx, vy) = self.velocity();
 
-        let mut map = HashMap::new();
-        map.insert("x".into(), Json::Number(self.x));
-        map.insert("y".into(), Json::Number(self.y));
-        map.insert("vx".into(), Json::Number(vx));
-        map.insert("vy".into(), Json::Number(vy));
-        map.insert("color".into(), Json::String(self.color.clone()));
-        map.insert("shield".into(), Json::Number(self.shield as f64));
-        Json::Object(map)
+        writer.begin_object()?
+            .key("x")?.number(self.x)?
+            .key("y")?.number(self.y)?
+            .key("vx")?.number(vx)?
+            .key("vy")?.number(vy)?
+            .key("color")?.string(&self.color)?
+            .key("shield")?.number(self.shield)?
+            .end_object()?;
+
+        Ok(())
     }
 }
 
@@ -339,10 +351,3 @@ fn cool_vector() -> Option<Vec<(String, String)>> {
             Some((split[8].to_string(), split[9].to_string()))
         }).collect::<Option<_>>()
 }
-
-fn unix_time_millis() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock read failed")
-        .as_millis() as u64
-}
\ No newline at end of file
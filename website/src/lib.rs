@@ -0,0 +1,9 @@
+#![feature(try_trait, is_sorted)]
+
+pub mod apps;
+
+pub const RESOURCES_PATH: &str = "/home/pi/Desktop/server/resources";
+pub const GOD_SET_PATH: &str = "/home/pi/Desktop/server/resources/apush/godset.txt";
+pub const VOCABULARY_LOG_PATH: &str = "/home/pi/Desktop/server/vocabularyLog.txt";
+pub const PASSWORD_LOG_PATH: &str = "/home/pi/Desktop/server/passwordLog.txt";
+pub const WORD_LIST_PATH: &str = "/home/pi/Desktop/server/wordList.txt";
@@ -1,32 +1,39 @@
-#![feature(try_trait, is_sorted)]
-
 use std::sync::{Arc, Mutex};
-use server::Server;
+use server::{Server, RateLimit, RateLimitAction};
+use web_socket::Deflate;
 use std::time::Duration;
 use std::path::PathBuf;
 
-mod apps;
-use apps::*;
-
-const RESOURCES_PATH: &str = "/home/pi/Desktop/server/resources";
-const GOD_SET_PATH: &str = "/home/pi/Desktop/server/resources/apush/godset.txt";
-const VOCABULARY_LOG_PATH: &str = "/home/pi/Desktop/server/vocabularyLog.txt";
-const PASSWORD_LOG_PATH: &str = "/home/pi/Desktop/server/passwordLog.txt";
-const WORD_LIST_PATH: &str = "/home/pi/Desktop/server/wordList.txt";
+use website::apps::*;
+use website::{RESOURCES_PATH, GOD_SET_PATH, VOCABULARY_LOG_PATH, WORD_LIST_PATH};
 
 const MAX_HTTP_REQUEST_SIZE: usize = 2048;
 const PERIOD_LENGTH: Duration = Duration::from_millis(100);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
 
 fn main() {
     let mut server = Server::new("website".into(), PathBuf::from(RESOURCES_PATH), MAX_HTTP_REQUEST_SIZE, PERIOD_LENGTH);
 
-    server.web_socket_add("/filler".into(), Arc::new(Mutex::new(FillerGlobalState::new())));
+    server.web_socket_add("/filler".into(), Arc::new(Mutex::new(FillerGlobalState::new())))
+        .heartbeat(HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT);
     server.web_socket_add("/godset".into(), Arc::new(Mutex::new(GodSetGlobalState::new())));
-    server.web_socket_add("/tanks".into(), Arc::new(Mutex::new(TanksGlobalState::new())));
-    server.web_socket_add("/history".into(), Arc::new(Mutex::new(HistoryGlobalState::new())));
-    server.web_socket_add("/arena".into(), Arc::new(Mutex::new(ArenaGlobalState::new())));
-    server.web_socket_add("/secure".into(), Arc::new(Mutex::new(SecureGlobalState::new())));
-    server.web_socket_add("/pusoy".into(), Arc::new(Mutex::new(PusoyGlobalState::new())));
+    server.web_socket_add("/tanks".into(), Arc::new(Mutex::new(TanksGlobalState::new())))
+        .rate_limit(RateLimit::new(30.0, 30, RateLimitAction::Drop))
+        .deflate(Deflate::default())
+        .heartbeat(HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT);
+    server.web_socket_add("/history".into(), Arc::new(Mutex::new(HistoryGlobalState::new(GOD_SET_PATH, VOCABULARY_LOG_PATH))))
+        .deflate(Deflate::default())
+        .heartbeat(HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT);
+    server.web_socket_add("/arena".into(), Arc::new(Mutex::new(ArenaGlobalState::new())))
+        .rate_limit(RateLimit::new(60.0, 60, RateLimitAction::Drop))
+        // the game state is compressed once for everyone, which needs a fresh compressor each message
+        .deflate(Deflate { server_no_context_takeover: true, ..Deflate::default() })
+        .heartbeat(HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT);
+    server.web_socket_add("/secure".into(), Arc::new(Mutex::new(SecureGlobalState::new())))
+        .rate_limit(RateLimit::new(1.0, 5, RateLimitAction::Disconnect));
+    server.web_socket_add("/pusoy".into(), Arc::new(Mutex::new(PusoyGlobalState::new(WORD_LIST_PATH))))
+        .heartbeat(HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT);
 
     server.start();
 }
\ No newline at end of file
@@ -0,0 +1,214 @@
+// history quiz lobbies and games, driven through a TestHarness
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use json::{json, Json};
+use server::{PeerId, TestHarness};
+use website::apps::HistoryGlobalState;
+
+const GOD_SET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/apush/godset.txt");
+
+struct Quiz {
+    harness: TestHarness<HistoryGlobalState>,
+    vocabulary_log: PathBuf,
+}
+
+impl Quiz {
+    fn new(test: &str) -> Quiz {
+        // every test gets its own log, since they run at the same time
+        let vocabulary_log = env::temp_dir().join(format!("history-vocabulary-{}-{}.txt", process::id(), test));
+        fs::write(&vocabulary_log, "").unwrap();
+        let state = HistoryGlobalState::new(GOD_SET_PATH, vocabulary_log.to_str().unwrap());
+
+        Quiz { harness: TestHarness::new(state), vocabulary_log }
+    }
+
+    fn create(&mut self, username: &str) -> (PeerId, u64) {
+        // a new lobby and its game id
+        let host = self.harness.connect();
+        self.harness.send_json(host, &json!({
+            kind: "create",
+            username: username,
+            settings: {startSection: "1.1", endSection: "1.2", gameKind: "gameKindQuiz"},
+        })).unwrap();
+
+        let received = self.harness.received(host);
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].string_at("/kind"), Ok("createSuccess"));
+        assert_eq!(received[0].string_at("/hostName"), Ok(username));
+
+        (host, received[0].string_at("/gameId").unwrap().parse().unwrap())
+    }
+
+    fn join(&mut self, username: &str, game_id: u64) -> PeerId {
+        let id = self.harness.connect();
+        self.harness.send_json(id, &json!({kind: "join", username: username, id: game_id})).unwrap();
+        id
+    }
+}
+
+impl Drop for Quiz {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.vocabulary_log);
+    }
+}
+
+fn users(message: &Json) -> Vec<&str> {
+    let mut users: Vec<&str> = message.pointer("/users").and_then(Json::get_array).unwrap().iter()
+        .map(|user| user.get_string().unwrap())
+        .collect();
+    users.sort();
+    users
+}
+
+fn correct_answer(question: &Json) -> usize {
+    // which of the terms the god set gives the question's definition for
+    let definition = question.string_at("/definition").unwrap();
+    let terms = question.pointer("/terms").and_then(Json::get_array).unwrap();
+    let god_set = fs::read_to_string(GOD_SET_PATH).unwrap();
+
+    terms.iter()
+        .position(|term| {
+            let term = term.get_string().unwrap();
+            god_set.lines().any(|line| {
+                let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+                fields[8] == term && fields[9] == definition
+            })
+        })
+        .unwrap()
+}
+
+#[test]
+fn lobby() {
+    let mut quiz = Quiz::new("lobby");
+    let (host, game_id) = quiz.create("ethan");
+
+    let first = quiz.join("sam", game_id);
+    let received = quiz.harness.received(first);
+    assert_eq!(received[0], json!({kind: "joinSuccess", hostName: "ethan"}));
+    assert_eq!(received[1].string_at("/kind"), Ok("refreshLobby"));
+    assert_eq!(users(&received[1]), vec!["sam"]);
+    assert_eq!(received.len(), 2);
+
+    let second = quiz.join("alex", game_id);
+    assert_eq!(quiz.harness.received_kinds(second), vec!["joinSuccess", "refreshLobby"]);
+    let received = quiz.harness.received(host);
+    assert_eq!(received.len(), 2);
+    assert_eq!(users(&received[1]), vec!["alex", "sam"]);
+
+    quiz.harness.disconnect(first);
+    let received = quiz.harness.received(second);
+    assert_eq!(received.len(), 1);
+    assert_eq!(users(&received[0]), vec!["alex"]);
+
+    quiz.harness.disconnect(host);
+    assert_eq!(quiz.harness.received_kinds(second), vec!["hostAbandoned"]);
+}
+
+#[test]
+fn games_that_cannot_be_made_or_found() {
+    let mut quiz = Quiz::new("cannot");
+    let peer = quiz.harness.connect();
+
+    quiz.harness.send_json(peer, &json!({
+        kind: "create",
+        username: "ethan",
+        settings: {startSection: "1", endSection: "1.2", gameKind: "gameKindQuiz"},
+    })).unwrap();
+    assert_eq!(quiz.harness.received(peer), vec![json!({kind: "createFailed", message: "Unable to interpret your chapter range"})]);
+
+    quiz.harness.send_json(peer, &json!({
+        kind: "create",
+        username: "ethan",
+        settings: {startSection: "90.1", endSection: "90.2", gameKind: "gameKindQuiz"},
+    })).unwrap();
+    assert_eq!(quiz.harness.received(peer), vec![json!({kind: "createFailed", message: "No terms were found in that range"})]);
+
+    let lost = quiz.join("sam", 12345);
+    assert_eq!(quiz.harness.received_kinds(lost), vec!["invalidGameId"]);
+}
+
+#[test]
+fn a_round_of_questions() {
+    let mut quiz = Quiz::new("round");
+    let (host, game_id) = quiz.create("ethan");
+    let right = quiz.join("sam", game_id);
+    let wrong = quiz.join("alex", game_id);
+    for &id in [host, right, wrong].iter() {
+        quiz.harness.received(id);
+    }
+
+    quiz.harness.send_json(host, &json!({kind: "start"})).unwrap();
+    let mut question = Json::Null;
+    for &id in [host, right, wrong].iter() {
+        let received = quiz.harness.received(id);
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].string_at("/kind"), Ok("startingGame"));
+        assert_eq!(received[0].string_at("/host"), Ok("ethan"));
+        assert_eq!(users(&received[0]), vec!["alex", "sam"]);
+        assert_eq!(received[1].string_at("/kind"), Ok("initialStuff"));
+        question = received[1].pointer("/question").unwrap().clone();
+    }
+
+    // nothing happens on its own in a quiz
+    quiz.harness.periodic();
+    for &id in [host, right, wrong].iter() {
+        assert_eq!(quiz.harness.received(id), Vec::<Json>::new());
+    }
+
+    let answer = correct_answer(&question);
+    quiz.harness.send_json(right, &json!({kind: "submitAnswer", answer: answer})).unwrap();
+    quiz.harness.send_json(wrong, &json!({kind: "submitAnswer", answer: (if answer == 0 { 1 } else { 0 })})).unwrap();
+    assert_eq!(fs::read_to_string(&quiz.vocabulary_log).unwrap().lines().count(), 2);
+
+    // only the host moves things along
+    assert!(quiz.harness.send_json(right, &json!({kind: "nextQuestion"})).is_err());
+    assert!(!quiz.harness.is_connected(right));
+
+    quiz.harness.send_json(host, &json!({kind: "nextQuestion"})).unwrap();
+    let received = quiz.harness.received(wrong);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].string_at("/kind"), Ok("updateStuff"));
+    assert_eq!(received[0].pointer("/wasCorrect"), Some(&json!(false)));
+    assert_eq!(received[0].pointer("/score"), Some(&json!(0.0)));
+
+    let received = quiz.harness.received(host);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].pointer("/scores"), Some(&json!([{username: "alex", score: 0.0}])));
+}
+
+#[test]
+fn refreshing_the_page_keeps_your_score() {
+    let mut quiz = Quiz::new("refresh");
+    let (host, game_id) = quiz.create("ethan");
+    let player = quiz.join("sam", game_id);
+    quiz.harness.send_json(host, &json!({kind: "start"})).unwrap();
+
+    let question = quiz.harness.received(player).pop().unwrap();
+    let answer = correct_answer(question.pointer("/question").unwrap());
+    quiz.harness.send_json(player, &json!({kind: "submitAnswer", answer: answer})).unwrap();
+    quiz.harness.send_json(host, &json!({kind: "nextQuestion"})).unwrap();
+    let received = quiz.harness.received(player);
+    assert_eq!(received[0].pointer("/wasCorrect"), Some(&json!(true)));
+    assert_eq!(received[0].pointer("/score"), Some(&json!(1.0)));
+
+    let session = quiz.harness.session(player);
+    quiz.harness.disconnect(player);
+    quiz.harness.received(host);
+
+    let player = quiz.harness.reconnect(session);
+    let received = quiz.harness.received(player);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].string_at("/kind"), Ok("initialStuff"));
+    assert_eq!(received[0].pointer("/score"), Some(&json!(1.0)));
+
+    quiz.harness.send_json(host, &json!({kind: "nextQuestion"})).unwrap();
+    let received = quiz.harness.received(host);
+    assert_eq!(received[0].pointer("/scores"), Some(&json!([{username: "sam", score: 1.0}])));
+
+    quiz.harness.disconnect(host);
+    assert_eq!(quiz.harness.received_kinds(player), vec!["updateStuff", "hostAbandoned"]);
+}
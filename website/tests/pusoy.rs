@@ -0,0 +1,169 @@
+// pusoy lobbies and games, driven through a TestHarness
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Duration;
+
+use json::{json, Json};
+use server::{PeerId, TestHarness};
+use website::apps::PusoyGlobalState;
+
+const PERIOD_LENGTH: Duration = Duration::from_millis(100);
+
+fn harness(test: &str) -> TestHarness<PusoyGlobalState> {
+    // every test gets its own word list, since they run at the same time
+    let path = env::temp_dir().join(format!("pusoy-words-{}-{}.txt", process::id(), test));
+    fs::write(&path, "apple-pie\nbanana\ncherry\n").unwrap();
+    let state = PusoyGlobalState::new(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+
+    TestHarness::new(state)
+}
+
+fn create(harness: &mut TestHarness<PusoyGlobalState>, username: &str) -> (PeerId, String) {
+    // a new lobby and its game id
+    let host = harness.connect();
+    harness.send_json(host, &json!({kind: "create", username: username})).unwrap();
+
+    let received = harness.received(host);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].string_at("/kind"), Ok("createSuccess"));
+    assert_eq!(received[0].string_at("/host"), Ok(username));
+
+    (host, received[0].string_at("/gameId").unwrap().to_string())
+}
+
+fn join(harness: &mut TestHarness<PusoyGlobalState>, username: &str, game_id: &str) -> PeerId {
+    let id = harness.connect();
+    harness.send_json(id, &json!({kind: "join", username: username, gameId: game_id})).unwrap();
+    id
+}
+
+fn players(message: &Json) -> Vec<&str> {
+    message.pointer("/players").and_then(Json::get_array).unwrap().iter()
+        .map(|player| player.get_string().unwrap())
+        .collect()
+}
+
+#[test]
+fn lobby() {
+    let mut harness = harness("lobby");
+    let (host, game_id) = create(&mut harness, "ethan");
+    assert!(["apple-pie", "banana", "cherry"].contains(&game_id.as_str()));
+
+    let guest = join(&mut harness, "sam", &game_id);
+    let received = harness.received(guest);
+    assert_eq!(received[0], json!({kind: "joinSuccess", host: "ethan", gameId: (game_id.clone())}));
+    assert_eq!(received[1].string_at("/kind"), Ok("refreshLobby"));
+    assert_eq!(players(&received[1]), vec!["sam"]);
+    assert_eq!(received.len(), 2);
+
+    let received = harness.received(host);
+    assert_eq!(received.len(), 1);
+    assert_eq!(players(&received[0]), vec!["sam"]);
+
+    harness.disconnect(guest);
+    let received = harness.received(host);
+    assert_eq!(received.len(), 1);
+    assert_eq!(players(&received[0]), Vec::<&str>::new());
+}
+
+#[test]
+fn joining_a_game_that_does_not_exist() {
+    let mut harness = harness("does-not-exist");
+    create(&mut harness, "ethan");
+
+    let lost = join(&mut harness, "sam", "durian");
+    assert_eq!(harness.received_kinds(lost), vec!["invalidGameId"]);
+
+    // they can still join or make a game of their own
+    harness.send_json(lost, &json!({kind: "create", username: "sam"})).unwrap();
+    assert_eq!(harness.received_kinds(lost), vec!["createSuccess"]);
+}
+
+#[test]
+fn host_leaving_a_lobby() {
+    let mut harness = harness("host-leaving");
+    let (host, game_id) = create(&mut harness, "ethan");
+    let guest = join(&mut harness, "sam", &game_id);
+    harness.received(guest);
+
+    harness.disconnect(host);
+    assert_eq!(harness.received_kinds(guest), vec!["hostAbandoned"]);
+}
+
+#[test]
+fn invalid_messages_get_an_error() {
+    let mut harness = harness("invalid");
+    let peer = harness.connect();
+
+    harness.send_json(peer, &json!({kind: "create", username: ""})).unwrap();
+    harness.send_json(peer, &json!({kind: "dance"})).unwrap();
+
+    let received = harness.received(peer);
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0], json!({
+        kind: "error",
+        message: "message is invalid: must have at least 1 characters at /username",
+        violations: ["must have at least 1 characters at /username"],
+    }));
+    assert_eq!(received[1].string_at("/message"), Ok("message is invalid: unknown kind `dance` at /kind"));
+    assert!(harness.is_connected(peer));
+
+    // but there's no talking to someone who doesn't send json
+    assert!(harness.send_text(peer, "not json").is_err());
+    assert!(!harness.is_connected(peer));
+}
+
+#[test]
+fn a_game_plays_itself_out_while_nobody_moves() {
+    let mut harness = harness("game");
+    let (host, game_id) = create(&mut harness, "ethan");
+    let guest = join(&mut harness, "sam", &game_id);
+    harness.received(host);
+    harness.received(guest);
+
+    harness.send_json(host, &json!({kind: "begin"})).unwrap();
+    for &id in [host, guest].iter() {
+        let received = harness.received(id);
+        assert_eq!(received[0], json!({kind: "begin", players: ["sam", "ethan"]}));
+        assert_eq!(received[1].string_at("/kind"), Ok("transition"));
+        let hand = received[1].pointer("/hand").and_then(Json::get_array).unwrap();
+        assert_eq!(hand.len(), 13);
+    }
+
+    // the machines play after a couple seconds, and people who take too long get a move made for them
+    harness.advance_periodic(Duration::from_secs(61), PERIOD_LENGTH);
+    for &id in [host, guest].iter() {
+        assert!(harness.received_kinds(id).contains(&"transition".to_string()));
+    }
+
+    // with nobody playing, somebody runs out of cards eventually. a second is plenty fine grained
+    // for turns that last seconds
+    harness.advance_periodic(Duration::from_secs(6 * 60 * 60), Duration::from_secs(1));
+    assert!(harness.received_kinds(host).contains(&"over".to_string()));
+    assert!(harness.received_kinds(guest).contains(&"over".to_string()));
+}
+
+#[test]
+fn refreshing_the_page_gets_your_seat_back() {
+    let mut harness = harness("refresh");
+    let (host, game_id) = create(&mut harness, "ethan");
+    let guest = join(&mut harness, "sam", &game_id);
+    harness.send_json(host, &json!({kind: "begin"})).unwrap();
+    harness.received(guest);
+
+    let session = harness.session(guest);
+    harness.disconnect(guest);
+    harness.periodic();
+
+    let guest = harness.reconnect(session);
+    let received = harness.received(guest);
+    assert_eq!(received[0], json!({kind: "begin", players: ["sam", "ethan"]}));
+    assert_eq!(received[1].string_at("/kind"), Ok("transition"));
+
+    // but a new browser doesn't
+    let stranger = harness.connect();
+    assert_eq!(harness.received(stranger), Vec::<Json>::new());
+}